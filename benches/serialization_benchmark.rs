@@ -0,0 +1,93 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use num_bigint::BigUint;
+use zkp::{serialization, serialization::CompressedBigUint, ZKP};
+
+/// DER INTEGER: tag, definite length, then big-endian two's-complement content
+fn der_encode(value: &BigUint) -> Vec<u8> {
+    let mut content = value.to_bytes_be();
+    if content[0] & 0x80 != 0 {
+        content.insert(0, 0);
+    }
+
+    let mut out = vec![0x02];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let len_bytes: Vec<u8> = content
+            .len()
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|b| *b == 0)
+            .collect();
+        out.push(0x80 | len_bytes.len() as u8);
+        out.extend(len_bytes);
+    }
+    out.extend(content);
+    out
+}
+
+fn der_decode(bytes: &[u8]) -> BigUint {
+    assert_eq!(bytes[0], 0x02, "not a DER INTEGER");
+    let (len, offset) = if bytes[1] & 0x80 == 0 {
+        (bytes[1] as usize, 2)
+    } else {
+        let n = (bytes[1] & 0x7f) as usize;
+        let len = bytes[2..2 + n]
+            .iter()
+            .fold(0usize, |acc, b| acc << 8 | *b as usize);
+        (len, 2 + n)
+    };
+    BigUint::from_bytes_be(&bytes[offset..offset + len])
+}
+
+fn benchmark_serialization_formats(c: &mut Criterion) {
+    let zkp = ZKP::new(None).unwrap();
+    let value = ZKP::generate_random_number_below(&zkp.p).unwrap();
+
+    // Throughput is over each format's encoded length, so criterion reports
+    // how large the encodings are alongside how fast they round-trip
+    let encoded_bytes = |len: usize| Throughput::Bytes(len as u64);
+    let mut group = c.benchmark_group("serialization_round_trip");
+
+    group.throughput(encoded_bytes(
+        serialization::serialize_biguint(&value).len(),
+    ));
+    group.bench_function("raw", |b| {
+        b.iter(|| {
+            let bytes = serialization::serialize_biguint(black_box(&value));
+            serialization::deserialize_biguint(&bytes).unwrap()
+        })
+    });
+
+    group.throughput(encoded_bytes(hex::encode(value.to_bytes_be()).len()));
+    group.bench_function("hex", |b| {
+        b.iter(|| {
+            let encoded = hex::encode(black_box(&value).to_bytes_be());
+            BigUint::from_bytes_be(&hex::decode(encoded).unwrap())
+        })
+    });
+
+    group.throughput(encoded_bytes(STANDARD.encode(value.to_bytes_be()).len()));
+    group.bench_function("base64", |b| {
+        b.iter(|| {
+            let encoded = STANDARD.encode(black_box(&value).to_bytes_be());
+            BigUint::from_bytes_be(&STANDARD.decode(encoded).unwrap())
+        })
+    });
+
+    group.throughput(encoded_bytes(der_encode(&value).len()));
+    group.bench_function("der", |b| {
+        b.iter(|| der_decode(&der_encode(black_box(&value))))
+    });
+
+    group.throughput(encoded_bytes(CompressedBigUint::new(&value).len()));
+    group.bench_function("compact", |b| {
+        b.iter(|| CompressedBigUint::new(black_box(&value)).to_biguint())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_serialization_formats);
+criterion_main!(benches);
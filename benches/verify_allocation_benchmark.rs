@@ -0,0 +1,203 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use criterion::{
+    criterion_group, criterion_main,
+    measurement::{Measurement, ValueFormatter},
+    BenchmarkId, Criterion, Throughput,
+};
+use tonic::Request;
+use zkp::{serialization, PROTOCOL_VERSION, ZKP};
+
+// Compile the server binary's source into the bench so it can drive the real
+// `AuthImpl` in-process, as the integration tests do.
+#[allow(dead_code)]
+#[path = "../src/server.rs"]
+mod server;
+
+use server::{
+    zkp_auth::{
+        auth_server::Auth, AuthenticationAnswerRequest, AuthenticationChallengeRequest,
+        RegisterRequest,
+    },
+    AuthImpl, ServerConfig,
+};
+
+/// System allocator that counts every allocation made through it
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Criterion measurement reporting heap allocations instead of wall time
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> u64 {
+        ALLOCATIONS.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, start: u64) -> u64 {
+        ALLOCATIONS.load(Ordering::Relaxed) - start
+    }
+
+    fn add(&self, v1: &u64, v2: &u64) -> u64 {
+        v1 + v2
+    }
+
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn to_f64(&self, value: &u64) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &AllocationFormatter
+    }
+}
+
+struct AllocationFormatter;
+
+impl ValueFormatter for AllocationFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        _throughput: &Throughput,
+        _values: &mut [f64],
+    ) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+/// Register one user and return a server ready to answer its challenges
+async fn setup(config: &ServerConfig) -> (AuthImpl, String, num_bigint::BigUint) {
+    let auth_impl = AuthImpl::with_config(config).unwrap();
+    let (username, x, y1, y2) = auth_impl.active_group().deterministic_user(0);
+    auth_impl
+        .register(Request::new(RegisterRequest {
+            user: username.clone(),
+            y1: serialization::serialize_biguint(&y1),
+            y2: serialization::serialize_biguint(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            rp_id: String::new(),
+            password_hash: String::new(),
+            salt: Vec::new(),
+        }))
+        .await
+        .unwrap();
+    (auth_impl, username, x)
+}
+
+/// Issue and solve a fresh challenge for `username`
+async fn prepare_answer(
+    auth_impl: &AuthImpl,
+    username: &str,
+    x: &num_bigint::BigUint,
+) -> AuthenticationAnswerRequest {
+    // Skip the one-challenge-per-second rate limit between rounds
+    if let Some(user_info) = auth_impl.user_info.write().await.get_mut(username) {
+        user_info.last_challenge_timestamp = None;
+    }
+
+    let zkp = auth_impl.active_group();
+    let k = ZKP::generate_random_number_below(&(&zkp.q - 1u32)).unwrap() + 1u32;
+    let (r1, r2) = zkp.compute_pair(&k).unwrap();
+    let challenge = auth_impl
+        .create_authentication_challenge(Request::new(AuthenticationChallengeRequest {
+            user: username.to_string(),
+            r1: serialization::serialize_biguint(&r1),
+            r2: serialization::serialize_biguint(&r2),
+            protocol_version: PROTOCOL_VERSION,
+            pow_nonce: 0,
+            rp_id: String::new(),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+
+    let c = serialization::deserialize_biguint(&challenge.c).unwrap();
+    AuthenticationAnswerRequest {
+        auth_id: challenge.auth_id,
+        s: serialization::serialize_biguint(&zkp.solve(&k, &c, x).unwrap()),
+    }
+}
+
+fn benchmark_verify_allocations(c: &mut Criterion<Allocations>) {
+    // A single-threaded runtime keeps other threads from skewing the count
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("verify_authentication_allocations");
+    group.sample_size(10);
+
+    let configs = [
+        ("unbatched", ServerConfig::default()),
+        (
+            "batched",
+            ServerConfig {
+                verify_batch_window_ms: Some(0),
+                ..Default::default()
+            },
+        ),
+    ];
+    for (name, config) in configs {
+        let (auth_impl, username, x) = runtime.block_on(setup(&config));
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_custom(|iters| {
+                let mut allocations = 0;
+                for _ in 0..iters {
+                    let answer = runtime.block_on(prepare_answer(&auth_impl, &username, &x));
+                    let start = ALLOCATIONS.load(Ordering::Relaxed);
+                    runtime
+                        .block_on(auth_impl.verify_authentication(Request::new(answer)))
+                        .unwrap();
+                    allocations += ALLOCATIONS.load(Ordering::Relaxed) - start;
+                }
+                allocations
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().with_measurement(Allocations);
+    targets = benchmark_verify_allocations
+}
+criterion_main!(benches);
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use num_bigint::BigUint;
+use tonic::Request;
+use zkp::{serialization, PROTOCOL_VERSION, ZKP};
+
+// Compile the server binary's source into the bench so it can drive the real
+// `AuthImpl` in-process, as the integration tests do.
+#[allow(dead_code)]
+#[path = "../src/server.rs"]
+mod server;
+
+use server::{
+    zkp_auth::{
+        auth_server::Auth, AuthenticationAnswerRequest, AuthenticationChallengeRequest,
+        RegisterRequest, ServerInfoRequest,
+    },
+    AuthImpl,
+};
+
+const CONCURRENCY: [usize; 6] = [1, 2, 4, 8, 16, 32];
+
+/// Verifications in flight while `GetServerInfo` latency is measured
+const BACKGROUND_LOAD: [usize; 3] = [0, 16, 64];
+
+/// Register one deterministic user per concurrent caller
+async fn register_users(auth_impl: &AuthImpl, count: usize) -> Vec<(String, BigUint)> {
+    let zkp = auth_impl.active_group();
+    let mut users = Vec::with_capacity(count);
+    for index in 0..count as u64 {
+        let (username, x, y1, y2) = zkp.deterministic_user(index);
+        auth_impl
+            .register(Request::new(RegisterRequest {
+                user: username.clone(),
+                y1: serialization::serialize_biguint(&y1),
+                y2: serialization::serialize_biguint(&y2),
+                protocol_version: PROTOCOL_VERSION,
+                rp_id: String::new(),
+                password_hash: String::new(),
+                salt: Vec::new(),
+            }))
+            .await
+            .unwrap();
+        users.push((username, x));
+    }
+    users
+}
+
+/// Issue a challenge to each user and solve it, returning the answers to submit
+async fn prepare_answers(
+    auth_impl: &AuthImpl,
+    users: &[(String, BigUint)],
+) -> Vec<AuthenticationAnswerRequest> {
+    let zkp = auth_impl.active_group();
+    let mut answers = Vec::with_capacity(users.len());
+    for (username, x) in users {
+        // Skip the one-challenge-per-second rate limit between rounds
+        if let Some(user_info) = auth_impl.user_info.write().await.get_mut(username) {
+            user_info.last_challenge_timestamp = None;
+        }
+
+        let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let (r1, r2) = zkp.compute_pair(&k).unwrap();
+        let challenge = auth_impl
+            .create_authentication_challenge(Request::new(AuthenticationChallengeRequest {
+                user: username.clone(),
+                r1: serialization::serialize_biguint(&r1),
+                r2: serialization::serialize_biguint(&r2),
+                protocol_version: PROTOCOL_VERSION,
+                pow_nonce: 0,
+                rp_id: String::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let c = serialization::deserialize_biguint(&challenge.c).unwrap();
+        answers.push(AuthenticationAnswerRequest {
+            auth_id: challenge.auth_id,
+            s: serialization::serialize_biguint(&zkp.solve(&k, &c, x).unwrap()),
+        });
+    }
+    answers
+}
+
+/// Time `iters` verifications submitted `concurrency` at a time
+///
+/// Only the concurrent `verify_authentication` calls are timed; issuing and
+/// solving the challenges for each round happens off the clock.
+async fn timed_verifications(
+    auth_impl: &Arc<AuthImpl>,
+    users: &[(String, BigUint)],
+    iters: u64,
+) -> Duration {
+    let mut elapsed = Duration::ZERO;
+    let mut remaining = iters as usize;
+
+    while remaining > 0 {
+        let round = remaining.min(users.len());
+        let answers = prepare_answers(auth_impl, &users[..round]).await;
+
+        let start = Instant::now();
+        let tasks: Vec<_> = answers
+            .into_iter()
+            .map(|answer| {
+                let auth_impl = auth_impl.clone();
+                tokio::spawn(async move {
+                    auth_impl
+                        .verify_authentication(Request::new(answer))
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        for task in futures::future::join_all(tasks).await {
+            task.unwrap();
+        }
+        elapsed += start.elapsed();
+
+        remaining -= round;
+    }
+
+    elapsed
+}
+
+/// Time `iters` `GetServerInfo` calls, each made while `users.len()`
+/// verifications are in flight
+///
+/// The probe is spawned like any other request, so the timing includes the
+/// wait for a free worker thread; verifications that hold the workers show
+/// up here as tail latency.
+async fn timed_probes_under_load(
+    auth_impl: &Arc<AuthImpl>,
+    users: &[(String, BigUint)],
+    iters: u64,
+) -> Duration {
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..iters {
+        let answers = prepare_answers(auth_impl, users).await;
+        let load: Vec<_> = answers
+            .into_iter()
+            .map(|answer| {
+                let auth_impl = auth_impl.clone();
+                tokio::spawn(async move {
+                    auth_impl
+                        .verify_authentication(Request::new(answer))
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+
+        let probe_impl = auth_impl.clone();
+        let start = Instant::now();
+        let probe = tokio::spawn(async move {
+            probe_impl
+                .get_server_info(Request::new(ServerInfoRequest {}))
+                .await
+                .unwrap();
+            Instant::now()
+        });
+        elapsed += probe.await.unwrap() - start;
+
+        for task in futures::future::join_all(load).await {
+            task.unwrap();
+        }
+    }
+    elapsed
+}
+
+fn benchmark_probe_latency_under_load(c: &mut Criterion) {
+    // Few workers, so blocking them with modpows would be easy to see
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("server_info_latency_under_verify_load");
+    group.sample_size(10);
+
+    for load in BACKGROUND_LOAD {
+        let auth_impl = Arc::new(AuthImpl::new().unwrap());
+        let users = runtime.block_on(register_users(&auth_impl, load));
+
+        group.bench_with_input(BenchmarkId::from_parameter(load), &load, |b, _| {
+            b.iter_custom(|iters| {
+                runtime.block_on(timed_probes_under_load(&auth_impl, &users, iters))
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn benchmark_verify_throughput(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("verify_authentication_throughput");
+    group.throughput(Throughput::Elements(1));
+    group.sample_size(10);
+
+    for concurrency in CONCURRENCY {
+        let auth_impl = Arc::new(AuthImpl::new().unwrap());
+        let users = runtime.block_on(register_users(&auth_impl, concurrency));
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(concurrency),
+            &concurrency,
+            |b, _| {
+                b.iter_custom(|iters| {
+                    runtime.block_on(timed_verifications(&auth_impl, &users, iters))
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    benchmark_verify_throughput,
+    benchmark_probe_latency_under_load
+);
+criterion_main!(benches);
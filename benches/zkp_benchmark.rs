@@ -1,7 +1,12 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use zkp::ZKP;
+use zkp::{ec::EcZkp, ZkpGroup, ZKP};
 
 fn benchmark_zkp_operations(c: &mut Criterion) {
+    // Repeated construction over the predefined group
+    c.bench_function("zkp_new_predefined", |b| {
+        b.iter(|| ZKP::new(black_box(None)).unwrap())
+    });
+
     let zkp = ZKP::new(None).unwrap();
     let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
     let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
@@ -21,6 +26,25 @@ fn benchmark_zkp_operations(c: &mut Criterion) {
         })
     });
 
+    c.bench_function("prover_round", |b| {
+        b.iter(|| {
+            let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+            let (r1, r2) = zkp.commitment(&k).unwrap();
+            (
+                r1,
+                r2,
+                zkp.solve(&k, black_box(&c_value), black_box(&x)).unwrap(),
+            )
+        })
+    });
+
+    c.bench_function("prover_single_pass", |b| {
+        b.iter(|| {
+            zkp.prover_single_pass(black_box(&x), black_box(&c_value))
+                .unwrap()
+        })
+    });
+
     let s = zkp.solve(&k, &c_value, &x).unwrap();
 
     c.bench_function("verify", |b| {
@@ -37,6 +61,28 @@ fn benchmark_zkp_operations(c: &mut Criterion) {
         })
     });
 
+    let batch: Vec<_> = (0..64)
+        .map(|_| {
+            let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+            let (r1, r2) = zkp.compute_pair(&k).unwrap();
+            let s = zkp.solve(&k, &c_value, &x).unwrap();
+            (r1, r2, y1.clone(), y2.clone(), c_value.clone(), s)
+        })
+        .collect();
+
+    c.bench_function("verify_64_individually", |b| {
+        b.iter(|| {
+            batch
+                .iter()
+                .map(|(r1, r2, y1, y2, c, s)| zkp.verify(r1, r2, y1, y2, c, s).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+
+    c.bench_function("verify_batch_64", |b| {
+        b.iter(|| zkp.verify_batch(black_box(&batch)).unwrap())
+    });
+
     c.bench_function("full_zkp_flow", |b| {
         b.iter(|| {
             let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
@@ -47,11 +93,47 @@ fn benchmark_zkp_operations(c: &mut Criterion) {
             let (r1, r2) = zkp.compute_pair(&k).unwrap();
             let s = zkp.solve(&k, &c, &x).unwrap();
             let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap();
-            
+
             black_box(result)
         })
     });
 }
 
-criterion_group!(benches, benchmark_zkp_operations);
-criterion_main!(benches);
\ No newline at end of file
+/// Prover and verifier cost on one backend
+fn benchmark_group_backend<G: ZkpGroup>(c: &mut Criterion, name: &str, group: &G) {
+    let x = group.random_scalar().unwrap();
+    let k = group.random_scalar().unwrap();
+    let c_value = group.random_scalar().unwrap();
+    let (y1, y2) = group.compute_pair(&x).unwrap();
+    let (r1, r2) = group.compute_pair(&k).unwrap();
+    let s = group.solve(&k, &c_value, &x).unwrap();
+
+    let mut backends = c.benchmark_group("backend");
+    backends.bench_function(format!("{}/compute_pair", name), |b| {
+        b.iter(|| group.compute_pair(black_box(&k)).unwrap())
+    });
+    backends.bench_function(format!("{}/verify", name), |b| {
+        b.iter(|| {
+            group
+                .verify(
+                    black_box(&r1),
+                    black_box(&r2),
+                    black_box(&y1),
+                    black_box(&y2),
+                    black_box(&c_value),
+                    black_box(&s),
+                )
+                .unwrap()
+        })
+    });
+    backends.finish();
+}
+
+fn benchmark_backends(c: &mut Criterion) {
+    let zkp = ZKP::new(None).unwrap();
+    benchmark_group_backend(c, "modp_1024", &zkp);
+    benchmark_group_backend(c, "ristretto255", &EcZkp::new());
+}
+
+criterion_group!(benches, benchmark_zkp_operations, benchmark_backends);
+criterion_main!(benches);
@@ -1,4 +1,19 @@
 fn main() {
+    // Optional pin of the group checksum baked into the binary; the value is
+    // compared against the group in use when the server starts.
+    println!("cargo:rerun-if-env-changed=ZKP_PINNED_GROUP_CHECKSUM");
+    if let Ok(pinned) = std::env::var("ZKP_PINNED_GROUP_CHECKSUM") {
+        let pinned = pinned.trim();
+        if !pinned.is_empty()
+            && (pinned.len() != 64 || !pinned.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            panic!(
+                "ZKP_PINNED_GROUP_CHECKSUM must be a 64 character hex SHA-256, got {:?}",
+                pinned
+            );
+        }
+    }
+
     tonic_build::configure()
         .build_server(true)
         .out_dir("src/")
@@ -1,20 +1,18 @@
-use std::io::{self, Write};
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::Result;
-use clap::Parser;
-use num_bigint::BigUint;
-use tracing::{error, info, instrument};
+use clap::{Parser, Subcommand};
+use tracing::{error, info};
 
-use zkp::{serialization, ZkpResult, ZKP};
-
-pub mod zkp_auth {
-    include!("./zkp_auth.rs");
-}
-
-use zkp_auth::{
-    auth_client::AuthClient, AuthenticationAnswerRequest, AuthenticationChallengeRequest,
-    RegisterRequest,
+use tonic::transport::Channel;
+use zkp::client::{
+    authenticate_with_commitment_for_rp, register_user_with_hash, Commitment, CommitmentPool,
+    RetryPolicy,
 };
+use zkp::zkp_auth::{auth_client::AuthClient, ServerInfoRequest};
+use zkp::{PasswordHash, ProofTranscript, ZKP};
 
 /// Command line arguments for the ZKP client
 #[derive(Parser, Debug)]
@@ -22,16 +20,80 @@ use zkp_auth::{
 #[command(about = "A Zero Knowledge Proof authentication client")]
 struct Args {
     /// Server address to connect to
-    #[arg(short, long, default_value = "http://127.0.0.1:50051")]
+    #[arg(short, long, global = true, default_value = "http://127.0.0.1:50051")]
     server: String,
 
-    /// Username for authentication
-    #[arg(short, long)]
-    username: Option<String>,
+    /// Relying party to bind the password-derived secret to; must match the server's
+    #[arg(long, global = true, default_value = "")]
+    rp_id: String,
 
-    /// Skip interactive mode and use provided values
-    #[arg(long)]
+    /// Never prompt: take the password from --password-file or ZKP_PASSWORD
+    #[arg(long, global = true)]
     non_interactive: bool,
+
+    /// File holding the password for non-interactive mode (first line is used)
+    #[arg(long, global = true, requires = "non_interactive")]
+    password_file: Option<PathBuf>,
+
+    /// Hex group checksum the server must advertise; abort before sending anything otherwise
+    #[arg(long, global = true)]
+    expect_group_checksum: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Register a new user with the server
+    Register {
+        #[command(flatten)]
+        user: UserArgs,
+
+        /// Hash that maps the password to the secret: sha256, sha512 or blake3
+        #[arg(long, default_value_t = PasswordHash::default())]
+        password_hash: PasswordHash,
+    },
+    /// Authenticate an existing user and print the session id
+    Login {
+        #[command(flatten)]
+        user: UserArgs,
+
+        /// Retry the challenge request and the answer this many times when the server is busy
+        #[arg(long, default_value_t = 0)]
+        retries: u32,
+
+        /// Initial backoff between retries in milliseconds, doubled on each attempt
+        #[arg(long, default_value_t = 250)]
+        retry_backoff_ms: u64,
+
+        /// Pool of precomputed commitments to draw from
+        #[arg(long)]
+        pool_file: Option<PathBuf>,
+    },
+    /// Precompute login commitments into a pool file while idle
+    PrecomputePool {
+        /// Pool file to add the commitments to
+        #[arg(long)]
+        pool_file: PathBuf,
+
+        /// Number of commitments to add
+        #[arg(long)]
+        count: usize,
+    },
+    /// Verify a JSON proof transcript against the default group, exiting 0 if valid
+    Verify {
+        /// Read the transcript from standard input
+        #[arg(long, required = true)]
+        stdin: bool,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct UserArgs {
+    /// Username to act as (prompted for if omitted)
+    #[arg(short, long)]
+    username: Option<String>,
 }
 
 /// Secure password input without echoing to terminal
@@ -39,7 +101,7 @@ fn read_password(prompt: &str) -> Result<String> {
     print!("{}", prompt);
     io::stdout().flush()?;
 
-    let password = rpassword::read_password()?;
+    let password = rpassword::read_password().map_err(input_error)?;
     Ok(password)
 }
 
@@ -48,100 +110,138 @@ fn read_input(prompt: &str) -> Result<String> {
     print!("{}", prompt);
     io::stdout().flush()?;
 
+    read_line_from(&mut io::stdin().lock())
+}
+
+/// Read one trimmed line, treating closed input as an error rather than ""
+fn read_line_from(reader: &mut impl BufRead) -> Result<String> {
     let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    if reader.read_line(&mut input)? == 0 {
+        return Err(eof_error());
+    }
     Ok(input.trim().to_string())
 }
 
-/// Convert password string to BigUint deterministically
-fn password_to_biguint(password: &str, zkp: &ZKP) -> BigUint {
-    use sha2::{Digest, Sha256};
-
-    let mut hasher = Sha256::new();
-    hasher.update(password.as_bytes());
-    let hash = hasher.finalize();
+/// Report closed input clearly instead of as a bare I/O error
+fn input_error(e: io::Error) -> anyhow::Error {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        eof_error()
+    } else {
+        e.into()
+    }
+}
 
-    let password_biguint = BigUint::from_bytes_be(&hash);
+fn eof_error() -> anyhow::Error {
+    anyhow::anyhow!("Input closed (EOF) before a value was entered")
+}
 
-    // Reduce modulo q to ensure it's in valid range
-    password_biguint % &zkp.q
+/// Password for non-interactive mode, from `password_file` or else `env_password`
+///
+/// Only the first line of the file is used, without its line ending.
+fn non_interactive_password(
+    password_file: Option<&Path>,
+    env_password: Option<String>,
+) -> Result<String> {
+    match (password_file, env_password) {
+        (Some(path), _) => {
+            let contents = std::fs::read_to_string(path).map_err(|e| {
+                anyhow::anyhow!("Failed to read password file {}: {}", path.display(), e)
+            })?;
+            Ok(contents.lines().next().unwrap_or_default().to_string())
+        }
+        (None, Some(password)) => Ok(password),
+        (None, None) => Err(anyhow::anyhow!(
+            "Non-interactive mode needs --password-file or ZKP_PASSWORD"
+        )),
+    }
 }
 
-/// Perform user registration
-#[instrument(skip(client, zkp, password))]
-async fn register_user(
-    client: &mut AuthClient<tonic::transport::Channel>,
-    zkp: &ZKP,
-    username: &str,
-    password: &str,
-) -> ZkpResult<()> {
-    info!("Starting registration for user: {}", username);
-
-    let password_biguint = password_to_biguint(password, zkp);
-    let (y1, y2) = zkp.compute_pair(&password_biguint)?;
-
-    let request = RegisterRequest {
-        user: username.to_string(),
-        y1: serialization::serialize_biguint(&y1),
-        y2: serialization::serialize_biguint(&y2),
-    };
+/// Verify the JSON transcript read from `input`, writing a report to `report`
+fn verify_transcript(zkp: &ZKP, input: &mut impl Read, report: &mut impl Write) -> Result<bool> {
+    let mut json = String::new();
+    input.read_to_string(&mut json)?;
+    let t = ProofTranscript::from_json(&json)
+        .map_err(|e| anyhow::anyhow!("Invalid transcript: {}", e))?;
+
+    let details = zkp
+        .verify_detailed(&t.r1, &t.r2, &t.y1, &t.y2, &t.c, &t.s)
+        .map_err(|e| anyhow::anyhow!("Verification failed: {}", e))?;
+
+    writeln!(report, "valid: {}", details.valid)?;
+    writeln!(report, "r1:            {}", t.r1.to_str_radix(16))?;
+    writeln!(
+        report,
+        "recomputed r1: {}",
+        details.recomputed_r1.to_str_radix(16)
+    )?;
+    writeln!(report, "r2:            {}", t.r2.to_str_radix(16))?;
+    writeln!(
+        report,
+        "recomputed r2: {}",
+        details.recomputed_r2.to_str_radix(16)
+    )?;
+    Ok(details.valid)
+}
 
-    client
-        .register(request)
-        .await
-        .map_err(|e| zkp::ZkpError::ComputationError(format!("Registration failed: {}", e)))?;
+impl Args {
+    /// Password for the current command, prompting unless non-interactive
+    fn password(&self, prompt: &str) -> Result<String> {
+        let password = if self.non_interactive {
+            non_interactive_password(
+                self.password_file.as_deref(),
+                std::env::var("ZKP_PASSWORD").ok(),
+            )?
+        } else {
+            read_password(prompt)?
+        };
+
+        if password.is_empty() {
+            return Err(anyhow::anyhow!("Password cannot be empty"));
+        }
+        Ok(password)
+    }
 
-    info!("✅ Registration successful for user: {}", username);
-    Ok(())
+    /// The given username, prompting for one unless non-interactive
+    fn username(&self, user: &UserArgs) -> Result<String> {
+        let username = match &user.username {
+            Some(username) => username.clone(),
+            None if self.non_interactive => {
+                return Err(anyhow::anyhow!("Username required in non-interactive mode"))
+            }
+            None => read_input("Please enter your username: ")?,
+        };
+
+        if username.is_empty() {
+            return Err(anyhow::anyhow!("Username cannot be empty"));
+        }
+        Ok(username)
+    }
 }
 
-/// Perform user authentication
-#[instrument(skip(client, zkp, password))]
-async fn authenticate_user(
-    client: &mut AuthClient<tonic::transport::Channel>,
-    zkp: &ZKP,
-    username: &str,
-    password: &str,
-) -> ZkpResult<String> {
-    info!("Starting authentication for user: {}", username);
-
-    let password_biguint = password_to_biguint(password, zkp);
-    let k = ZKP::generate_random_number_below(&zkp.q)?;
-    let (r1, r2) = zkp.compute_pair(&k)?;
-
-    // Request challenge
-    let challenge_request = AuthenticationChallengeRequest {
-        user: username.to_string(),
-        r1: serialization::serialize_biguint(&r1),
-        r2: serialization::serialize_biguint(&r2),
-    };
-
-    let challenge_response = client
-        .create_authentication_challenge(challenge_request)
+/// Connect to the server and adopt its group
+///
+/// Servers predating GetServerInfo keep `zkp`. The adopted group must match
+/// `expected_checksum` when one is given.
+async fn connect(
+    server: &str,
+    zkp: ZKP,
+    expected_checksum: Option<&str>,
+) -> Result<(AuthClient<Channel>, ZKP)> {
+    let mut client = AuthClient::connect(server.to_string())
         .await
-        .map_err(|e| zkp::ZkpError::ComputationError(format!("Challenge request failed: {}", e)))?
-        .into_inner();
-
-    let auth_id = challenge_response.auth_id;
-    let c = serialization::deserialize_biguint(&challenge_response.c)?;
+        .map_err(|e| anyhow::anyhow!("Failed to connect to server: {}", e))?;
 
-    // Solve challenge
-    let s = zkp.solve(&k, &c, &password_biguint)?;
+    info!("✅ Connected to server at {}", server);
 
-    // Submit solution
-    let answer_request = AuthenticationAnswerRequest {
-        auth_id,
-        s: serialization::serialize_biguint(&s),
+    let zkp = match client.get_server_info(ServerInfoRequest {}).await {
+        Ok(response) => ZKP::from_server_info(&response.into_inner())
+            .map_err(|e| anyhow::anyhow!("Server advertised an unusable group: {}", e))?,
+        Err(status) if status.code() == tonic::Code::Unimplemented => zkp,
+        Err(status) => return Err(anyhow::anyhow!("Failed to query server info: {}", status)),
     };
-
-    let answer_response = client
-        .verify_authentication(answer_request)
-        .await
-        .map_err(|e| zkp::ZkpError::ComputationError(format!("Authentication failed: {}", e)))?
-        .into_inner();
-
-    info!("✅ Authentication successful for user: {}", username);
-    Ok(answer_response.session_id)
+    zkp.check_pinned_checksum(expected_checksum)
+        .map_err(|e| anyhow::anyhow!("Refusing to use the server's group: {}", e))?;
+    Ok((client, zkp))
 }
 
 #[tokio::main]
@@ -156,63 +256,184 @@ async fn main() -> Result<()> {
     // Initialize ZKP
     let zkp = ZKP::new(None).map_err(|e| anyhow::anyhow!("Failed to initialize ZKP: {}", e))?;
 
-    // Connect to server
-    let mut client = AuthClient::connect(args.server.clone())
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to connect to server: {}", e))?;
+    match &args.command {
+        Command::Verify { .. } => {
+            let valid = verify_transcript(&zkp, &mut io::stdin().lock(), &mut io::stderr())?;
+            std::process::exit(if valid { 0 } else { 1 });
+        }
+        Command::PrecomputePool { pool_file, count } => {
+            let password = args.password("Please enter your password to protect the pool: ")?;
+            let total = CommitmentPool::new(pool_file)
+                .fill(&zkp, &password, *count)
+                .map_err(|e| anyhow::anyhow!("Failed to precompute commitments: {}", e))?;
+            println!("Commitment pool now holds {} entries", total);
+            Ok(())
+        }
+        Command::Register {
+            user,
+            password_hash,
+        } => {
+            let username = args.username(user)?;
+            let password = args.password("Please enter a password for registration: ")?;
+            let (mut client, zkp) =
+                connect(&args.server, zkp, args.expect_group_checksum.as_deref()).await?;
+
+            match register_user_with_hash(
+                &mut client,
+                &zkp,
+                &args.rp_id,
+                *password_hash,
+                &username,
+                &password,
+            )
+            .await
+            {
+                Ok(user_id) => {
+                    info!("Registration completed successfully");
+                    println!("User ID: {}", user_id);
+                    Ok(())
+                }
+                Err(e) => {
+                    error!("Registration failed: {}", e);
+                    Err(anyhow::anyhow!("Registration failed: {}", e))
+                }
+            }
+        }
+        Command::Login {
+            user,
+            retries,
+            retry_backoff_ms,
+            pool_file,
+        } => {
+            let username = args.username(user)?;
+            let password = args.password("Please enter your password: ")?;
+            let (mut client, zkp) =
+                connect(&args.server, zkp, args.expect_group_checksum.as_deref()).await?;
+
+            let retry = RetryPolicy {
+                max_retries: *retries,
+                initial_backoff: Duration::from_millis(*retry_backoff_ms),
+            };
+
+            let pool = pool_file.as_ref().map(CommitmentPool::new);
+            let pooled = match &pool {
+                Some(pool) => pool
+                    .take(&zkp, &password)
+                    .map_err(|e| anyhow::anyhow!("Failed to read commitment pool: {}", e))?,
+                None => None,
+            };
+            let commitment = match pooled {
+                Some(commitment) => commitment,
+                None => {
+                    if pool.is_some() {
+                        info!("Commitment pool is empty, computing a fresh commitment");
+                    }
+                    Commitment::generate(&zkp).map_err(|e| anyhow::anyhow!("{}", e))?
+                }
+            };
+
+            match authenticate_with_commitment_for_rp(
+                &mut client,
+                &zkp,
+                &args.rp_id,
+                &username,
+                &password,
+                commitment,
+                &retry,
+            )
+            .await
+            {
+                Ok(session_id) => {
+                    info!("🎉 Authentication successful!");
+                    println!("Session ID: {}", session_id);
+                    Ok(())
+                }
+                Err(e) => {
+                    error!("Authentication failed: {}", e);
+                    Err(anyhow::anyhow!("Authentication failed: {}", e))
+                }
+            }
+        }
+    }
+}
 
-    info!("✅ Connected to server at {}", args.server);
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    // Get username
-    let username = if let Some(username) = args.username {
-        username
-    } else if args.non_interactive {
-        return Err(anyhow::anyhow!("Username required in non-interactive mode"));
-    } else {
-        read_input("Please enter your username: ")?
-    };
+    #[test]
+    fn test_closed_input_reports_eof() {
+        let err = read_line_from(&mut io::empty()).unwrap_err();
+        assert!(err.to_string().contains("EOF"));
 
-    if username.is_empty() {
-        return Err(anyhow::anyhow!("Username cannot be empty"));
+        let err = rpassword::read_password_from_bufread(&mut io::empty())
+            .map_err(input_error)
+            .unwrap_err();
+        assert!(err.to_string().contains("EOF"));
     }
 
-    // Registration phase
-    let registration_password = if args.non_interactive {
-        return Err(anyhow::anyhow!(
-            "Non-interactive mode not fully supported yet"
-        ));
-    } else {
-        read_password("Please enter a password for registration: ")?
-    };
+    #[test]
+    fn test_read_line_trims_input() {
+        let mut reader = io::Cursor::new("  alice \n");
+        assert_eq!(read_line_from(&mut reader).unwrap(), "alice");
 
-    if registration_password.is_empty() {
-        return Err(anyhow::anyhow!("Password cannot be empty"));
+        let mut blank = io::Cursor::new("\n");
+        assert_eq!(read_line_from(&mut blank).unwrap(), "");
     }
 
-    match register_user(&mut client, &zkp, &username, &registration_password).await {
-        Ok(_) => info!("Registration completed successfully"),
-        Err(e) => {
-            error!("Registration failed: {}", e);
-            return Err(anyhow::anyhow!("Registration failed: {}", e));
-        }
+    #[test]
+    fn test_non_interactive_password_sources() {
+        let path = std::env::temp_dir().join(format!("zkp-password-{}", std::process::id()));
+        std::fs::write(&path, "from file\nignored\n").unwrap();
+
+        // The file wins over the environment
+        let password = non_interactive_password(Some(&path), Some("from env".to_string())).unwrap();
+        assert_eq!(password, "from file");
+        assert_eq!(
+            non_interactive_password(None, Some("from env".to_string())).unwrap(),
+            "from env"
+        );
+        assert!(non_interactive_password(None, None).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(non_interactive_password(Some(&path), None).is_err());
     }
 
-    // Authentication phase
-    let auth_password = if args.non_interactive {
-        registration_password
-    } else {
-        read_password("Please enter your password to authenticate: ")?
-    };
-
-    match authenticate_user(&mut client, &zkp, &username, &auth_password).await {
-        Ok(session_id) => {
-            info!("🎉 Authentication successful!");
-            println!("Session ID: {}", session_id);
-            Ok(())
-        }
-        Err(e) => {
-            error!("Authentication failed: {}", e);
-            Err(anyhow::anyhow!("Authentication failed: {}", e))
-        }
+    #[test]
+    fn test_verify_transcript_from_reader() {
+        let zkp = ZKP::new(None).unwrap();
+        let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let c = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let (y1, y2) = zkp.compute_pair(&x).unwrap();
+        let (r1, r2) = zkp.compute_pair(&k).unwrap();
+        let s = zkp.solve(&k, &c, &x).unwrap();
+        let mut transcript = ProofTranscript {
+            r1,
+            r2,
+            y1,
+            y2,
+            c,
+            s,
+            ..Default::default()
+        };
+
+        let mut report = Vec::new();
+        let mut input = io::Cursor::new(transcript.to_json().unwrap());
+        assert!(verify_transcript(&zkp, &mut input, &mut report).unwrap());
+        assert!(String::from_utf8(report)
+            .unwrap()
+            .starts_with("valid: true"));
+
+        transcript.s = (&transcript.s + 1u32) % &zkp.q;
+        let mut report = Vec::new();
+        let mut input = io::Cursor::new(transcript.to_json().unwrap());
+        assert!(!verify_transcript(&zkp, &mut input, &mut report).unwrap());
+        assert!(String::from_utf8(report)
+            .unwrap()
+            .starts_with("valid: false"));
+
+        let mut garbage = io::Cursor::new("{}");
+        assert!(verify_transcript(&zkp, &mut garbage, &mut Vec::new()).is_err());
     }
 }
@@ -1,5 +1,13 @@
+// Seeded challenges are only for replay tests; never let them into a release build
+#[cfg(all(feature = "test-determinism", not(debug_assertions)))]
+compile_error!("the test-determinism feature cannot be enabled in release builds");
+
+use std::ops::RangeInclusive;
+use std::sync::OnceLock;
+
 use num_bigint::{BigUint, RandBigInt};
-use rand::Rng;
+use num_traits::ToPrimitive;
+use rand::{CryptoRng, Rng, RngCore};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{info, instrument, warn};
@@ -13,13 +21,80 @@ pub enum ZkpError {
     SerializationError(String),
     #[error("Computation error: {0}")]
     ComputationError(String),
+    /// Catch-all for input problems without a dedicated variant
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+    #[error("Serialization error: Empty byte array")]
+    EmptyInput,
+    #[error("Invalid input: Exponent must be less than q")]
+    ExponentTooLarge,
+    /// A secret, nonce or challenge passed to the prover is not below q
+    #[error("Invalid input: All parameters must be less than q")]
+    ScalarOutOfRange,
+    /// A proof's challenge or answer is not below q
+    #[error("Invalid input: Challenge and solution must be less than q")]
+    ChallengeOutOfRange,
+    /// A proof's commitment or public value is not below p
+    #[error("Invalid input: All commitments must be less than p")]
+    CommitmentOutOfRange,
+    #[error("Invalid input: Public values must not be the identity")]
+    IdentityPublicValue,
+    // Group validation failures; `details` lists the parameter sizes
+    #[error("Invalid input: Group parameters exceed the {max_bits}-bit limit ({details})")]
+    GroupTooLarge { max_bits: u64, details: String },
+    #[error("Invalid input: p and q must be greater than 1 ({details})")]
+    ModulusOutOfRange { details: String },
+    #[error("Invalid input: Generators must be greater than 1 and less than p ({details})")]
+    GeneratorOutOfRange { details: String },
+    #[error("Invalid input: Generators must be distinct ({details})")]
+    GeneratorsNotDistinct { details: String },
+    #[error("Invalid input: Generators must have order q ({details})")]
+    GeneratorOrder { details: String },
 }
 
 /// Result type for ZKP operations
 pub type ZkpResult<T> = Result<T, ZkpError>;
 
+/// Wrapper for secrets that prints as `***` through `Debug` and `Display`
+///
+/// Wrap challenge state, nonces and tokens that end up in structs or log
+/// fields, so a stray `{:?}` cannot leak them. Serde sees the inner value.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Redacted<T>(pub T);
+
+impl<T> Redacted<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> std::ops::Deref for Redacted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Redacted<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> std::fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl<T> std::fmt::Display for Redacted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
 /// Configuration for ZKP constants and parameters
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ZkpConfig {
@@ -38,26 +113,484 @@ impl Default for ZkpConfig {
 
 /// Serialization utilities for BigUint
 pub mod serialization {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
     use super::*;
 
+    /// Byte order of a serialized BigUint
+    ///
+    /// The wire protocol is big-endian; little-endian is for interop with
+    /// callers that store values that way.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub enum Endianness {
+        #[default]
+        Big,
+        Little,
+    }
+
     /// Serialize BigUint to big-endian bytes
-    #[instrument(skip(value))]
     pub fn serialize_biguint(value: &BigUint) -> Vec<u8> {
-        let bytes = value.to_bytes_be();
+        serialize_biguint_with(value, Endianness::Big)
+    }
+
+    /// Deserialize BigUint from big-endian bytes
+    pub fn deserialize_biguint(bytes: &[u8]) -> ZkpResult<BigUint> {
+        deserialize_biguint_with(bytes, Endianness::Big)
+    }
+
+    /// Serialize BigUint to little-endian bytes
+    pub fn serialize_biguint_le(value: &BigUint) -> Vec<u8> {
+        serialize_biguint_with(value, Endianness::Little)
+    }
+
+    /// Deserialize BigUint from little-endian bytes
+    pub fn deserialize_biguint_le(bytes: &[u8]) -> ZkpResult<BigUint> {
+        deserialize_biguint_with(bytes, Endianness::Little)
+    }
+
+    /// Serialize BigUint to hex of its big-endian bytes
+    pub fn serialize_biguint_hex(value: &BigUint) -> String {
+        hex::encode(serialize_biguint(value))
+    }
+
+    /// Deserialize BigUint from hex of its big-endian bytes
+    pub fn deserialize_biguint_hex(hex: &str) -> ZkpResult<BigUint> {
+        let bytes = hex::decode(hex)
+            .map_err(|e| ZkpError::SerializationError(format!("Invalid hex: {}", e)))?;
+        deserialize_biguint(&bytes)
+    }
+
+    /// Serialize BigUint to standard base64 of its big-endian bytes
+    pub fn serialize_biguint_base64(value: &BigUint) -> String {
+        BASE64.encode(serialize_biguint(value))
+    }
+
+    /// Deserialize BigUint from standard base64 of its big-endian bytes
+    pub fn deserialize_biguint_base64(base64: &str) -> ZkpResult<BigUint> {
+        let bytes = BASE64
+            .decode(base64)
+            .map_err(|e| ZkpError::SerializationError(format!("Invalid base64: {}", e)))?;
+        deserialize_biguint(&bytes)
+    }
+
+    /// Serialize BigUint to minimal bytes in the given byte order
+    #[instrument(skip(value))]
+    pub fn serialize_biguint_with(value: &BigUint, endianness: Endianness) -> Vec<u8> {
+        let bytes = match endianness {
+            Endianness::Big => value.to_bytes_be(),
+            Endianness::Little => value.to_bytes_le(),
+        };
         info!("Serialized BigUint with {} bytes", bytes.len());
         bytes
     }
 
-    /// Deserialize BigUint from big-endian bytes
+    /// Deserialize BigUint from bytes in the given byte order
     #[instrument(skip(bytes))]
-    pub fn deserialize_biguint(bytes: &[u8]) -> ZkpResult<BigUint> {
+    pub fn deserialize_biguint_with(bytes: &[u8], endianness: Endianness) -> ZkpResult<BigUint> {
         if bytes.is_empty() {
-            return Err(ZkpError::SerializationError("Empty byte array".to_string()));
+            return Err(ZkpError::EmptyInput);
         }
-        let value = BigUint::from_bytes_be(bytes);
+        let value = match endianness {
+            Endianness::Big => BigUint::from_bytes_be(bytes),
+            Endianness::Little => BigUint::from_bytes_le(bytes),
+        };
         info!("Deserialized BigUint from {} bytes", bytes.len());
         Ok(value)
     }
+
+    /// Compact storage for a BigUint that only needs to be reconstructed occasionally
+    ///
+    /// Holds the minimal big-endian bytes in a boxed slice (whose fat pointer
+    /// caches the length), avoiding the spare capacity and limb padding of a
+    /// full `BigUint`.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct CompressedBigUint {
+        bytes: Box<[u8]>,
+    }
+
+    impl CompressedBigUint {
+        /// Compress a BigUint into its minimal big-endian form
+        pub fn new(value: &BigUint) -> Self {
+            Self {
+                bytes: value.to_bytes_be().into_boxed_slice(),
+            }
+        }
+
+        /// Reconstruct the full BigUint
+        pub fn to_biguint(&self) -> BigUint {
+            BigUint::from_bytes_be(&self.bytes)
+        }
+
+        /// Number of stored bytes
+        pub fn len(&self) -> usize {
+            self.bytes.len()
+        }
+
+        /// Whether no bytes are stored
+        pub fn is_empty(&self) -> bool {
+            self.bytes.is_empty()
+        }
+
+        /// Total memory used, including the heap allocation
+        pub fn memory_footprint(&self) -> usize {
+            std::mem::size_of::<Self>() + self.bytes.len()
+        }
+    }
+
+    impl From<&BigUint> for CompressedBigUint {
+        fn from(value: &BigUint) -> Self {
+            Self::new(value)
+        }
+    }
+
+    /// Serde adapter writing a BigUint as a big-endian hex string
+    ///
+    /// Use with `#[serde(with = "serialization::hex_biguint")]`.
+    pub mod hex_biguint {
+        use num_bigint::BigUint;
+        use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(value: &BigUint, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&value.to_str_radix(16))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<BigUint, D::Error> {
+            let hex = String::deserialize(deserializer)?;
+            BigUint::parse_bytes(hex.trim_start_matches("0x").as_bytes(), 16)
+                .ok_or_else(|| D::Error::custom(format!("invalid hex integer: {:?}", hex)))
+        }
+    }
+}
+
+/// Hashcash-style proof of work the server can demand before issuing a challenge
+///
+/// A proof is a nonce such that the `Algorithm`'s hash of the context and
+/// the nonce has at least `difficulty` leading zero bits. The context binds the username,
+/// the commitment and a server-chosen seed, so a proof cannot be reused for
+/// another request.
+pub mod pow {
+    use std::fmt;
+    use std::str::FromStr;
+
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+
+    /// Status metadata carrying the difficulty the server requires
+    pub const DIFFICULTY_METADATA_KEY: &str = "x-pow-difficulty";
+    /// Status metadata carrying the hex seed the proof must be bound to
+    pub const SEED_METADATA_KEY: &str = "x-pow-seed";
+    /// Status metadata naming the puzzle algorithm; `sha256` when absent
+    pub const ALGORITHM_METADATA_KEY: &str = "x-pow-algorithm";
+    /// Highest difficulty the library client will try to solve
+    ///
+    /// Each bit doubles the expected work, so a server asking for more is
+    /// either misconfigured or trying to pin the client down.
+    pub const MAX_CLIENT_DIFFICULTY: u32 = 32;
+
+    /// Hash each proof-of-work attempt is computed with
+    ///
+    /// Written as `sha256` or `argon2:<memory_kib>` in config and metadata.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(try_from = "String", into = "String")]
+    pub enum Algorithm {
+        /// One SHA-256 per attempt; cheap, so dedicated hardware gains a lot
+        #[default]
+        Sha256,
+        /// One Argon2id pass over `memory_kib` per attempt, salted with the context
+        ///
+        /// Memory-hard: every attempt needs the whole buffer, which limits
+        /// how far attempts can be parallelized. Use far lower difficulties
+        /// than with `Sha256`.
+        Argon2 { memory_kib: u32 },
+    }
+
+    impl Algorithm {
+        /// Whether `nonce` is a proof of work of at least `difficulty` bits over `context`
+        pub fn is_valid(&self, context: &[u8], nonce: u64, difficulty: u32) -> bool {
+            self.digest(context, nonce)
+                .is_ok_and(|digest| leading_zero_bits(&digest) >= difficulty)
+        }
+
+        /// Find the smallest nonce that is a valid proof of work over `context`
+        ///
+        /// Takes about `2^difficulty` attempts. Fails if the algorithm cannot
+        /// hash `context`, such as a context shorter than Argon2's salt minimum.
+        pub fn solve(&self, context: &[u8], difficulty: u32) -> ZkpResult<u64> {
+            for nonce in 0..=u64::MAX {
+                if leading_zero_bits(&self.digest(context, nonce)?) >= difficulty {
+                    return Ok(nonce);
+                }
+            }
+            Err(ZkpError::ComputationError(
+                "No proof of work in the nonce space".to_string(),
+            ))
+        }
+
+        fn digest(&self, context: &[u8], nonce: u64) -> ZkpResult<[u8; 32]> {
+            match *self {
+                Algorithm::Sha256 => Ok(Sha256::new()
+                    .chain_update(context)
+                    .chain_update(nonce.to_be_bytes())
+                    .finalize()
+                    .into()),
+                Algorithm::Argon2 { memory_kib } => {
+                    let invalid =
+                        |e: argon2::Error| ZkpError::InvalidInput(format!("Argon2 failed: {}", e));
+                    let params =
+                        argon2::Params::new(memory_kib, 1, 1, Some(32)).map_err(invalid)?;
+                    let mut digest = [0u8; 32];
+                    argon2::Argon2::new(
+                        argon2::Algorithm::Argon2id,
+                        argon2::Version::V0x13,
+                        params,
+                    )
+                    .hash_password_into(&nonce.to_be_bytes(), context, &mut digest)
+                    .map_err(invalid)?;
+                    Ok(digest)
+                }
+            }
+        }
+    }
+
+    impl fmt::Display for Algorithm {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Algorithm::Sha256 => write!(f, "sha256"),
+                Algorithm::Argon2 { memory_kib } => write!(f, "argon2:{}", memory_kib),
+            }
+        }
+    }
+
+    impl FromStr for Algorithm {
+        type Err = ZkpError;
+
+        fn from_str(s: &str) -> ZkpResult<Self> {
+            match s.split_once(':') {
+                None if s == "sha256" => Ok(Algorithm::Sha256),
+                Some(("argon2", memory_kib)) => match memory_kib.parse() {
+                    Ok(memory_kib) if memory_kib >= argon2::Params::MIN_M_COST => {
+                        Ok(Algorithm::Argon2 { memory_kib })
+                    }
+                    _ => Err(ZkpError::InvalidInput(format!(
+                        "Invalid argon2 memory size: {} (at least {} KiB)",
+                        memory_kib,
+                        argon2::Params::MIN_M_COST
+                    ))),
+                },
+                _ => Err(ZkpError::InvalidInput(format!(
+                    "Unknown proof-of-work algorithm: {}",
+                    s
+                ))),
+            }
+        }
+    }
+
+    impl TryFrom<String> for Algorithm {
+        type Error = ZkpError;
+
+        fn try_from(s: String) -> ZkpResult<Self> {
+            s.parse()
+        }
+    }
+
+    impl From<Algorithm> for String {
+        fn from(algorithm: Algorithm) -> String {
+            algorithm.to_string()
+        }
+    }
+
+    /// Bytes a proof of work for this challenge request is computed over
+    pub fn context(user: &str, r1: &BigUint, r2: &BigUint, seed: &[u8]) -> Vec<u8> {
+        let mut context = b"zkp-pow".to_vec();
+        for part in [
+            user.as_bytes(),
+            r1.to_bytes_be().as_slice(),
+            r2.to_bytes_be().as_slice(),
+            seed,
+        ] {
+            context.extend_from_slice(&(part.len() as u64).to_be_bytes());
+            context.extend_from_slice(part);
+        }
+        context
+    }
+
+    /// Whether `nonce` is a SHA-256 proof of work of at least `difficulty` bits over `context`
+    pub fn is_valid(context: &[u8], nonce: u64, difficulty: u32) -> bool {
+        Algorithm::Sha256.is_valid(context, nonce, difficulty)
+    }
+
+    /// Find the smallest nonce that is a valid SHA-256 proof of work over `context`
+    ///
+    /// Takes about `2^difficulty` hashes.
+    pub fn solve(context: &[u8], difficulty: u32) -> u64 {
+        Algorithm::Sha256
+            .solve(context, difficulty)
+            .expect("SHA-256 hashes any context")
+    }
+
+    fn leading_zero_bits(bytes: &[u8]) -> u32 {
+        let mut bits = 0;
+        for byte in bytes {
+            bits += byte.leading_zeros();
+            if *byte != 0 {
+                break;
+            }
+        }
+        bits
+    }
+}
+
+/// Known-good SHA-256 checksum of the predefined group (see `ZKP::group_checksum`)
+pub const PREDEFINED_CONSTANTS_CHECKSUM: [u8; 32] = [
+    0x81, 0xde, 0xb6, 0x49, 0xc5, 0x72, 0x5f, 0x58, 0xb3, 0xe0, 0x3c, 0x24, 0x1c, 0xfa, 0x29, 0xee,
+    0x23, 0x5f, 0x6b, 0x3c, 0x8e, 0x8c, 0x6f, 0xd1, 0x10, 0x5f, 0x51, 0x6b, 0x60, 0x7c, 0x70, 0x37,
+];
+
+/// Group checksum pinned at build time through `ZKP_PINNED_GROUP_CHECKSUM`
+pub const PINNED_GROUP_CHECKSUM: Option<&str> = option_env!("ZKP_PINNED_GROUP_CHECKSUM");
+
+/// Protocol version spoken by this build's client
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Protocol versions this build's server accepts
+pub const SUPPORTED_PROTOCOL_VERSIONS: RangeInclusive<u32> = 1..=1;
+
+/// Resolve the protocol version a peer asked for
+///
+/// Zero means the peer predates version negotiation and is treated as 1.
+pub fn negotiate_protocol_version(requested: u32) -> ZkpResult<u32> {
+    let version = requested.max(1);
+    if SUPPORTED_PROTOCOL_VERSIONS.contains(&version) {
+        return Ok(version);
+    }
+
+    let supported: Vec<String> = SUPPORTED_PROTOCOL_VERSIONS.map(|v| v.to_string()).collect();
+    Err(ZkpError::InvalidInput(format!(
+        "Unsupported protocol version {}; supported versions: {}",
+        requested,
+        supported.join(", ")
+    )))
+}
+
+/// Reject a proof whose encoded values cannot be valid, without a group
+///
+/// Cheap enough for a gateway to run before any modular arithmetic: every
+/// value must be non-empty and no longer than its field, `p_len` bytes for
+/// `r1`, `r2`, `y1` and `y2` and `q_len` bytes for `c` and `s`. Returns the
+/// first problem found.
+#[allow(clippy::too_many_arguments)]
+pub fn prevalidate_proof(
+    r1: &[u8],
+    r2: &[u8],
+    y1: &[u8],
+    y2: &[u8],
+    c: &[u8],
+    s: &[u8],
+    p_len: usize,
+    q_len: usize,
+) -> ZkpResult<()> {
+    for (name, value, max_len) in [
+        ("r1", r1, p_len),
+        ("r2", r2, p_len),
+        ("y1", y1, p_len),
+        ("y2", y2, p_len),
+        ("c", c, q_len),
+        ("s", s, q_len),
+    ] {
+        prevalidate_value(name, value, max_len)?;
+    }
+    Ok(())
+}
+
+/// The `prevalidate_proof` check for a single encoded value
+pub fn prevalidate_value(name: &str, value: &[u8], max_len: usize) -> ZkpResult<()> {
+    if value.is_empty() {
+        return Err(ZkpError::InvalidInput(format!("{} is empty", name)));
+    }
+    if value.len() > max_len {
+        return Err(ZkpError::InvalidInput(format!(
+            "{} is {} bytes, longer than its {}-byte field",
+            name,
+            value.len(),
+            max_len
+        )));
+    }
+    Ok(())
+}
+
+/// Outcome of `ZKP::verify_detailed`
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyDetails {
+    pub valid: bool,
+    /// `alpha^s * y1^c mod p`, expected to equal `r1`
+    pub recomputed_r1: BigUint,
+    /// `beta^s * y2^c mod p`, expected to equal `r2`
+    pub recomputed_r2: BigUint,
+}
+
+/// RFC 3526 2048-bit MODP prime, a safe prime with generator 2
+const MODP_2048_PRIME: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFF",
+);
+
+/// RFC 3526 3072-bit MODP prime, a safe prime with generator 2
+const MODP_3072_PRIME: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+    "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+    "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+    "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+    "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A93AD2CAFFFFFFFFFFFFFFFF",
+);
+
+/// RFC 3526 4096-bit MODP prime, a safe prime with generator 2
+const MODP_4096_PRIME: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+    "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+    "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+    "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+    "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+    "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+    "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+    "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+    "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+    "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7",
+    "88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8",
+    "DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2",
+    "233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9",
+    "93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C934063199FFFFFFFFFFFFFFFF",
+);
+
+/// Well-known groups selectable instead of the predefined parameters
+///
+/// `Modp1024` is the predefined group from `get_constants` (RFC 5114,
+/// section 2.1). The others use the RFC 3526 safe primes with
+/// `q = (p - 1) / 2`, `alpha = 2` and `beta = 9`; larger groups trade speed
+/// for security margin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StandardGroup {
+    Modp1024,
+    Modp2048,
+    Modp3072,
+    Modp4096,
 }
 
 #[derive(Debug)]
@@ -78,20 +611,49 @@ impl ZKP {
             let (alpha, beta, p, q) = Self::get_constants();
             Ok(Self { p, q, alpha, beta })
         } else {
-            // For custom parameters, you would generate or load them here
-            Err(ZkpError::InvalidInput(
-                "Custom parameters not implemented".to_string(),
-            ))
+            Self::generate_parameters(config.key_size_bits)
         }
     }
 
+    /// Build a ZKP over one of the `StandardGroup`s
+    pub fn from_standard_group(group: StandardGroup) -> ZkpResult<Self> {
+        let prime = match group {
+            StandardGroup::Modp1024 => {
+                let (alpha, beta, p, q) = Self::get_constants();
+                return Self::from_parameters(p, q, alpha, beta);
+            }
+            StandardGroup::Modp2048 => MODP_2048_PRIME,
+            StandardGroup::Modp3072 => MODP_3072_PRIME,
+            StandardGroup::Modp4096 => MODP_4096_PRIME,
+        };
+        let p = BigUint::parse_bytes(prime.as_bytes(), 16)
+            .ok_or_else(|| ZkpError::ComputationError("Invalid MODP prime".to_string()))?;
+        let q: BigUint = (&p - 1u32) >> 1u32;
+
+        // 2 and 9 = 3^2 are both quadratic residues, so they have order q
+        Self::from_parameters(p, q, BigUint::from(2u32), BigUint::from(9u32))
+    }
+
+    /// Build a ZKP from explicit group parameters, e.g. a standardized MODP group
+    ///
+    /// Runs `validate_parameters`, which also requires both generators to
+    /// have order q mod p.
+    pub fn from_parameters(
+        p: BigUint,
+        q: BigUint,
+        alpha: BigUint,
+        beta: BigUint,
+    ) -> ZkpResult<Self> {
+        let zkp = Self { p, q, alpha, beta };
+        zkp.validate_parameters()?;
+        Ok(zkp)
+    }
+
     /// Improved compute_pair method that uses the struct's alpha and beta
     #[instrument(skip(self, exp))]
     pub fn compute_pair(&self, exp: &BigUint) -> ZkpResult<(BigUint, BigUint)> {
         if exp >= &self.q {
-            return Err(ZkpError::InvalidInput(
-                "Exponent must be less than q".to_string(),
-            ));
+            return Err(ZkpError::ExponentTooLarge);
         }
 
         let p1 = self.alpha.modpow(exp, &self.p);
@@ -101,26 +663,108 @@ impl ZKP {
         Ok((p1, p2))
     }
 
+    /// Public key `(y1, y2) = (alpha^x mod p, beta^x mod p)` for the secret `x`
+    ///
+    /// This is what a client sends when registering; the server stores it and
+    /// checks every later proof against it.
+    ///
+    /// ```
+    /// use zkp::{client::password_to_biguint, ZKP};
+    ///
+    /// let zkp = ZKP::new(None)?;
+    /// let secret = password_to_biguint("correct horse", &zkp);
+    /// // Registration sends (user, y1, y2)
+    /// let (y1, y2) = zkp.public_key(&secret)?;
+    /// assert_eq!((y1, y2), zkp.compute_pair(&secret)?);
+    /// # Ok::<(), zkp::ZkpError>(())
+    /// ```
+    pub fn public_key(&self, secret: &BigUint) -> ZkpResult<(BigUint, BigUint)> {
+        self.compute_pair(secret)
+    }
+
+    /// Commitment `(r1, r2) = (alpha^k mod p, beta^k mod p)` for the nonce `k`
+    ///
+    /// Sent when asking for a challenge. `k` must be fresh for every
+    /// authentication attempt and is later combined with the challenge in
+    /// `solve`.
+    ///
+    /// ```
+    /// use zkp::{client::password_to_biguint, ZKP};
+    ///
+    /// let zkp = ZKP::new(None)?;
+    /// let secret = password_to_biguint("correct horse", &zkp);
+    /// let (y1, y2) = zkp.public_key(&secret)?;
+    ///
+    /// // The challenge request sends (user, r1, r2)...
+    /// let k = ZKP::generate_random_number_below(&zkp.q)?;
+    /// let (r1, r2) = zkp.commitment(&k)?;
+    /// // ...the server answers with c, and the client replies with s
+    /// let c = ZKP::generate_random_number_below(&zkp.q)?;
+    /// let s = zkp.solve(&k, &c, &secret)?;
+    /// assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s)?);
+    /// # Ok::<(), zkp::ZkpError>(())
+    /// ```
+    pub fn commitment(&self, nonce: &BigUint) -> ZkpResult<(BigUint, BigUint)> {
+        self.compute_pair(nonce)
+    }
+
     /// Improved solve method with better error handling
     #[instrument(skip(self, k, c, x))]
     pub fn solve(&self, k: &BigUint, c: &BigUint, x: &BigUint) -> ZkpResult<BigUint> {
         if k >= &self.q || c >= &self.q || x >= &self.q {
-            return Err(ZkpError::InvalidInput(
-                "All parameters must be less than q".to_string(),
-            ));
+            return Err(ZkpError::ScalarOutOfRange);
         }
 
-        let result = if *k >= c * x {
-            (k - c * x).modpow(&BigUint::from(1u32), &self.q)
-        } else {
-            &self.q - (c * x - k).modpow(&BigUint::from(1u32), &self.q)
-        };
+        let result = self.answer(k, c, x);
 
         info!("Computed solution s");
         Ok(result)
     }
 
+    /// `s = k - c * x mod q` for inputs already checked to be below `q`
+    fn answer(&self, k: &BigUint, c: &BigUint, x: &BigUint) -> BigUint {
+        // Reduce c * x first so the result always lands in [0, q)
+        let cx = (c * x) % &self.q;
+        if *k >= cx {
+            k - cx
+        } else {
+            &self.q - (cx - k)
+        }
+    }
+
+    /// Commitment and answer `(r1, r2, s)` in one call, for a prover that already holds `c`
+    ///
+    /// Draws a fresh `k` itself and never returns it. Inputs are range-checked
+    /// once up front. The exponentiations are the same as `commitment` plus
+    /// `solve`; the point is the narrower API, not less work.
+    #[instrument(skip(self, x, c))]
+    pub fn prover_single_pass(
+        &self,
+        x: &BigUint,
+        c: &BigUint,
+    ) -> ZkpResult<(BigUint, BigUint, BigUint)> {
+        let k = Self::generate_random_number_below(&self.q)?;
+        self.prover_single_pass_with_nonce(x, c, &k)
+    }
+
+    fn prover_single_pass_with_nonce(
+        &self,
+        x: &BigUint,
+        c: &BigUint,
+        k: &BigUint,
+    ) -> ZkpResult<(BigUint, BigUint, BigUint)> {
+        if k >= &self.q || c >= &self.q || x >= &self.q {
+            return Err(ZkpError::ScalarOutOfRange);
+        }
+
+        let r1 = self.alpha.modpow(k, &self.p);
+        let r2 = self.beta.modpow(k, &self.p);
+        Ok((r1, r2, self.answer(k, c, x)))
+    }
+
     /// Improved verify method with comprehensive validation
+    ///
+    /// Fails with `IdentityPublicValue` if `y1` or `y2` is 1.
     #[instrument(skip(self, r1, r2, y1, y2, c, s))]
     pub fn verify(
         &self,
@@ -131,26 +775,10 @@ impl ZKP {
         c: &BigUint,
         s: &BigUint,
     ) -> ZkpResult<bool> {
-        // Input validation
-        if c >= &self.q || s >= &self.q {
-            return Err(ZkpError::InvalidInput(
-                "Challenge and solution must be less than q".to_string(),
-            ));
-        }
-
-        if r1 >= &self.p || r2 >= &self.p || y1 >= &self.p || y2 >= &self.p {
-            return Err(ZkpError::InvalidInput(
-                "All commitments must be less than p".to_string(),
-            ));
-        }
-
-        let cond1 = *r1
-            == (&self.alpha.modpow(s, &self.p) * y1.modpow(c, &self.p))
-                .modpow(&BigUint::from(1u32), &self.p);
+        self.check_inputs(r1, r2, y1, y2, c, s)?;
 
-        let cond2 = *r2
-            == (&self.beta.modpow(s, &self.p) * y2.modpow(c, &self.p))
-                .modpow(&BigUint::from(1u32), &self.p);
+        let cond1 = self.check_equation(r1, &self.alpha, y1, c, s);
+        let cond2 = self.check_equation(r2, &self.beta, y2, c, s);
 
         let is_valid = cond1 && cond2;
 
@@ -163,174 +791,3864 @@ impl ZKP {
         Ok(is_valid)
     }
 
-    /// Generate a cryptographically secure random number below the given bound
-    #[instrument(skip(bound))]
-    pub fn generate_random_number_below(bound: &BigUint) -> ZkpResult<BigUint> {
-        if *bound == BigUint::from(0u32) {
-            return Err(ZkpError::InvalidInput("Bound cannot be zero".to_string()));
-        }
+    /// Verify a proof with uniform control flow, whichever equation fails
+    ///
+    /// Both equations are always evaluated, their results are compared
+    /// against `r1`/`r2` byte by byte over the full width of `p` without an
+    /// early exit, and the outcomes are combined with a non-short-circuiting
+    /// `&`. The time therefore does not reveal which equation failed or
+    /// where the mismatch was. The modular exponentiations themselves come
+    /// from `num-bigint` and are not constant time; their exponents `s` and
+    /// `c` are public values of the proof. Range checks still reject
+    /// malformed input early.
+    #[instrument(skip(self, r1, r2, y1, y2, c, s))]
+    pub fn verify_ct(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> ZkpResult<bool> {
+        self.check_inputs(r1, r2, y1, y2, c, s)?;
 
-        let mut rng = rand::thread_rng();
-        let random_num = rng.gen_biguint_below(bound);
+        let cond1 = self.ct_eq(&self.recompute_commitment(&self.alpha, y1, c, s), r1);
+        let cond2 = self.ct_eq(&self.recompute_commitment(&self.beta, y2, c, s), r2);
 
-        info!("Generated random number");
-        Ok(random_num)
+        Ok(cond1 & cond2)
     }
 
-    /// Generate a cryptographically secure random string of specified length
-    #[instrument]
-    pub fn generate_random_string(size: usize) -> ZkpResult<String> {
-        if size == 0 {
-            return Err(ZkpError::InvalidInput("Size cannot be zero".to_string()));
-        }
-
-        let random_string: String = rand::thread_rng()
-            .sample_iter(rand::distributions::Alphanumeric)
-            .take(size)
-            .map(char::from)
-            .collect();
+    /// Verify a proof, also returning the commitments recomputed from `s` and `c`
+    ///
+    /// Intended for diagnosing failed verifications; use `verify` otherwise.
+    #[instrument(skip(self, r1, r2, y1, y2, c, s))]
+    pub fn verify_detailed(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> ZkpResult<VerifyDetails> {
+        let valid = self.verify(r1, r2, y1, y2, c, s)?;
+        let recompute =
+            |g: &BigUint, y: &BigUint| (g.modpow(s, &self.p) * y.modpow(c, &self.p)) % &self.p;
 
-        info!("Generated random string of length {}", size);
-        Ok(random_string)
+        Ok(VerifyDetails {
+            valid,
+            recomputed_r1: recompute(&self.alpha, y1),
+            recomputed_r2: recompute(&self.beta, y2),
+        })
     }
 
-    /// Get predefined cryptographic constants (1024-bit parameters)
-    #[instrument]
-    pub fn get_constants() -> (BigUint, BigUint, BigUint, BigUint) {
-        let p = BigUint::from_bytes_be(&hex::decode("B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B616073E28675A23D189838EF1E2EE652C013ECB4AEA906112324975C3CD49B83BFACCBDD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0A151AF5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371").unwrap());
-        let q = BigUint::from_bytes_be(
-            &hex::decode("F518AA8781A8DF278ABA4E7D64B7CB9D49462353").unwrap(),
-        );
-
-        let alpha = BigUint::from_bytes_be(
-            &hex::decode("A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31266FEA1E5C41564B777E690F5504F213160217B4B01B886A5E91547F9E2749F4D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28AD662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5").unwrap(),
-        );
-
-        // beta = alpha^i is also a generator
-        let exp = BigUint::from_bytes_be(&hex::decode("266FEA1E5C41564B777E69").unwrap());
-        let beta = alpha.modpow(&exp, &p);
+    /// The two verification equations with the actual values substituted
+    ///
+    /// For demos and debugging; values longer than 20 digits are shortened
+    /// to their leading and trailing digits. For the toy group the first line
+    /// reads `r1 =? alpha^s · y1^c mod p → 8 =? 4^5 · 2^4 mod 23 = 8 ✓`.
+    pub fn explain_verify(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> String {
+        let line = |name: &str, generator: (&str, &BigUint), y: (&str, &BigUint), r: &BigUint| {
+            let recomputed = (generator.1.modpow(s, &self.p) * y.1.modpow(c, &self.p)) % &self.p;
+            format!(
+                "{} =? {}^s · {}^c mod p → {} =? {}^{} · {}^{} mod {} = {} {}",
+                name,
+                generator.0,
+                y.0,
+                abbreviate(r),
+                abbreviate(generator.1),
+                abbreviate(s),
+                abbreviate(y.1),
+                abbreviate(c),
+                abbreviate(&self.p),
+                abbreviate(&recomputed),
+                if &recomputed == r { "✓" } else { "✗" }
+            )
+        };
 
-        (alpha, beta, p, q)
+        format!(
+            "{}\n{}",
+            line("r1", ("alpha", &self.alpha), ("y1", y1), r1),
+            line("r2", ("beta", &self.beta), ("y2", y2), r2)
+        )
     }
 
-    /// Validate that the ZKP parameters are cryptographically sound
-    pub fn validate_parameters(&self) -> ZkpResult<()> {
-        // Basic parameter validation
-        if self.p <= BigUint::from(1u32) || self.q <= BigUint::from(1u32) {
-            return Err(ZkpError::InvalidInput(
-                "p and q must be greater than 1".to_string(),
-            ));
-        }
+    /// Digest of a public commitment `(y1, y2)` for out-of-band pinning
+    ///
+    /// SHA-256 over `y1` then `y2`, each written as a big-endian `u64` byte
+    /// length followed by its minimal big-endian bytes.
+    pub fn commitment_digest(y1: &BigUint, y2: &BigUint) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
 
-        if self.alpha >= self.p || self.beta >= self.p {
-            return Err(ZkpError::InvalidInput(
-                "Generators must be less than p".to_string(),
-            ));
+        let mut hasher = Sha256::new();
+        for value in [y1, y2] {
+            let bytes = value.to_bytes_be();
+            hasher.update((bytes.len() as u64).to_be_bytes());
+            hasher.update(&bytes);
         }
+        hasher.finalize().into()
+    }
 
-        if self.alpha <= BigUint::from(1u32) || self.beta <= BigUint::from(1u32) {
+    /// Verify a proof for a commitment known only by its digest
+    ///
+    /// The claimed `(y1, y2)` must hash to `commitment_hash` (see
+    /// `commitment_digest`); otherwise the proof is rejected with an error
+    /// before any modular exponentiation is done.
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, commitment_hash, r1, r2, c, s, claimed_y1, claimed_y2))]
+    pub fn verify_against_commitment(
+        &self,
+        commitment_hash: &[u8; 32],
+        r1: &BigUint,
+        r2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+        claimed_y1: &BigUint,
+        claimed_y2: &BigUint,
+    ) -> ZkpResult<bool> {
+        if Self::commitment_digest(claimed_y1, claimed_y2) != *commitment_hash {
+            warn!("Claimed commitment does not match the committed digest");
             return Err(ZkpError::InvalidInput(
-                "Generators must be greater than 1".to_string(),
+                "Claimed commitment does not match the committed digest".to_string(),
             ));
         }
 
-        info!("ZKP parameters validated successfully");
-        Ok(())
+        self.verify(r1, r2, claimed_y1, claimed_y2, c, s)
     }
-}
 
-#[cfg(test)]
+    /// Verify many `(r1, r2, y1, y2, c, s)` proofs together, one result per proof
+    ///
+    /// Checks a random combination of all the equations, weighted by random
+    /// 64-bit factors, so `alpha` and `beta` are exponentiated once for the
+    /// whole batch. Only when that check fails is each proof verified on its
+    /// own to find the bad ones. Out-of-range proofs, and proofs with values
+    /// outside the order-q subgroup, count as invalid rather than failing the
+    /// whole batch.
+    #[instrument(skip(self, proofs), fields(batch_size = proofs.len()))]
+    pub fn verify_batch(
+        &self,
+        proofs: &[(BigUint, BigUint, BigUint, BigUint, BigUint, BigUint)],
+    ) -> ZkpResult<Vec<bool>> {
+        let proofs: Vec<[&BigUint; 6]> = proofs
+            .iter()
+            .map(|(r1, r2, y1, y2, c, s)| [r1, r2, y1, y2, c, s])
+            .collect();
+        Ok(self.verify_many(&proofs))
+    }
+
+    /// `verify_batch` over transcripts, as queued by the server's batcher
+    #[instrument(skip(self, transcripts), fields(batch_size = transcripts.len()))]
+    pub fn verify_transcripts(&self, transcripts: &[ProofTranscript]) -> Vec<bool> {
+        let proofs: Vec<[&BigUint; 6]> = transcripts
+            .iter()
+            .map(|t| [&t.r1, &t.r2, &t.y1, &t.y2, &t.c, &t.s])
+            .collect();
+        self.verify_many(&proofs)
+    }
+
+    fn verify_many(&self, proofs: &[[&BigUint; 6]]) -> Vec<bool> {
+        // Random weights cannot catch a value off the subgroup: `(p - r)^w ==
+        // r^w` for every even `w`, so such proofs never reach the combined check
+        let in_range: Vec<bool> = proofs
+            .iter()
+            .map(|[r1, r2, y1, y2, c, s]| {
+                self.check_inputs(r1, r2, y1, y2, c, s).is_ok()
+                    && [r1, r2, y1, y2]
+                        .iter()
+                        .all(|value| self.is_subgroup_member(value))
+            })
+            .collect();
+
+        if in_range.iter().all(|ok| *ok) && self.check_combined(proofs) {
+            info!("Batch of {} proofs verified", proofs.len());
+            return vec![true; proofs.len()];
+        }
+
+        warn!("Batch verification failed, checking proofs individually");
+        proofs
+            .iter()
+            .zip(in_range)
+            .map(|([r1, r2, y1, y2, c, s], ok)| {
+                ok && matches!(self.verify(r1, r2, y1, y2, c, s), Ok(true))
+            })
+            .collect()
+    }
+
+    /// Check `prod r^w == g^(sum w*s) * prod y^(w*c) mod p` for both generators
+    fn check_combined(&self, proofs: &[[&BigUint; 6]]) -> bool {
+        let mut rng = rand::thread_rng();
+        let one = BigUint::from(1u32);
+        let mut s_sum = BigUint::from(0u32);
+        let (mut lhs1, mut lhs2) = (one.clone(), one.clone());
+        let (mut rhs1, mut rhs2) = (one.clone(), one);
+
+        for [r1, r2, y1, y2, c, s] in proofs {
+            let w = BigUint::from(rng.gen_range(1..=u64::MAX));
+            let wc = &w * *c;
+            s_sum += &w * *s;
+
+            lhs1 = lhs1 * r1.modpow(&w, &self.p) % &self.p;
+            lhs2 = lhs2 * r2.modpow(&w, &self.p) % &self.p;
+            rhs1 = rhs1 * y1.modpow(&wc, &self.p) % &self.p;
+            rhs2 = rhs2 * y2.modpow(&wc, &self.p) % &self.p;
+        }
+
+        rhs1 = rhs1 * self.alpha.modpow(&s_sum, &self.p) % &self.p;
+        rhs2 = rhs2 * self.beta.modpow(&s_sum, &self.p) % &self.p;
+        lhs1 == rhs1 && lhs2 == rhs2
+    }
+
+    /// Reject challenges and solutions outside `[0, q)`, commitments outside
+    /// `[0, p)` and identity public values
+    ///
+    /// `y^c == 1` for every c, so a proof against `y1 == 1` or `y2 == 1`
+    /// says nothing about the secret. `EcZkp::verify` rejects the identity
+    /// the same way.
+    fn check_inputs(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> ZkpResult<()> {
+        if c >= &self.q || s >= &self.q {
+            return Err(ZkpError::ChallengeOutOfRange);
+        }
+
+        if r1 >= &self.p || r2 >= &self.p || y1 >= &self.p || y2 >= &self.p {
+            return Err(ZkpError::CommitmentOutOfRange);
+        }
+
+        let one = BigUint::from(1u32);
+        if *y1 == one || *y2 == one {
+            return Err(ZkpError::IdentityPublicValue);
+        }
+
+        Ok(())
+    }
+
+    /// Whether `value` lies in the order-q subgroup: `0 < value < p` and `value^q == 1`
+    pub fn is_subgroup_member(&self, value: &BigUint) -> bool {
+        let one = BigUint::from(1u32);
+        *value >= one && *value < self.p && value.modpow(&self.q, &self.p) == one
+    }
+
+    /// Check `r == g^s * y^c mod p`
+    fn check_equation(
+        &self,
+        r: &BigUint,
+        g: &BigUint,
+        y: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> bool {
+        self.recompute_commitment(g, y, c, s) == *r
+    }
+
+    /// `g^s * y^c mod p`
+    fn recompute_commitment(&self, g: &BigUint, y: &BigUint, c: &BigUint, s: &BigUint) -> BigUint {
+        let mut lhs = g.modpow(s, &self.p);
+        lhs *= y.modpow(c, &self.p);
+        lhs %= &self.p;
+        lhs
+    }
+
+    /// Canonical encoding of a group element: big-endian, left-padded with
+    /// zeros to the byte length of `p`
+    ///
+    /// Every element below `p` encodes to the same width. Challenge
+    /// derivations and the group checksum hash elements in this form; those
+    /// that switched to it from the minimal encoding carry a `-v2` domain tag.
+    pub fn canonical_bytes(&self, x: &BigUint) -> Vec<u8> {
+        let width = self.p.bits().div_ceil(8) as usize;
+        let bytes = x.to_bytes_be();
+        let mut out = vec![0u8; width.saturating_sub(bytes.len())];
+        out.extend(bytes);
+        out
+    }
+
+    /// Compare `a` and `b` as fixed-width encodings without an early exit
+    fn ct_eq(&self, a: &BigUint, b: &BigUint) -> bool {
+        let (a, b) = (self.canonical_bytes(a), self.canonical_bytes(b));
+        let diff = a.iter().zip(&b).fold(0u8, |diff, (x, y)| diff | (x ^ y));
+        std::hint::black_box(diff) == 0
+    }
+
+    /// Generate a cryptographically secure random number below the given bound
+    #[instrument(skip(bound))]
+    pub fn generate_random_number_below(bound: &BigUint) -> ZkpResult<BigUint> {
+        Self::generate_random_number_below_with(bound, &mut rand::thread_rng())
+    }
+
+    /// `generate_random_number_below` drawing from `rng`
+    ///
+    /// Pass a seeded RNG to make proofs reproducible in tests.
+    pub fn generate_random_number_below_with<R: RngCore + CryptoRng>(
+        bound: &BigUint,
+        rng: &mut R,
+    ) -> ZkpResult<BigUint> {
+        if *bound == BigUint::from(0u32) {
+            return Err(ZkpError::InvalidInput("Bound cannot be zero".to_string()));
+        }
+
+        let random_num = rng.gen_biguint_below(bound);
+
+        info!("Generated random number");
+        Ok(random_num)
+    }
+
+    /// Generate a cryptographically secure random string of specified length
+    #[instrument]
+    pub fn generate_random_string(size: usize) -> ZkpResult<String> {
+        if size == 0 {
+            return Err(ZkpError::InvalidInput("Size cannot be zero".to_string()));
+        }
+
+        let random_string: String = rand::thread_rng()
+            .sample_iter(rand::distributions::Alphanumeric)
+            .take(size)
+            .map(char::from)
+            .collect();
+
+        info!("Generated random string of length {}", size);
+        Ok(random_string)
+    }
+
+    /// Derive a challenge bound to the prover's commitments
+    ///
+    /// Computes `c = H(r1 || r2 || user || secret) mod q` via `hash_to_scalar`,
+    /// where each part is length-prefixed so distinct inputs cannot collide by
+    /// shifting bytes between fields.
+    #[instrument(skip(self, r1, r2, secret))]
+    pub fn binding_challenge(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        user: &str,
+        secret: &[u8],
+    ) -> BigUint {
+        let c = self.hash_parts_to_scalar(&[
+            b"zkp-binding-challenge-v2",
+            self.canonical_bytes(r1).as_slice(),
+            self.canonical_bytes(r2).as_slice(),
+            user.as_bytes(),
+            secret,
+        ]);
+        info!("Computed binding challenge");
+        c
+    }
+
+    /// Bind a challenge nonce to a user's public commitment
+    ///
+    /// Computes `c = H(nonce || y1 || y2) mod q`. A response solved for one
+    /// user's challenge then cannot verify under another user's commitment,
+    /// even if the server hands it the wrong user's nonce.
+    #[instrument(skip(self, nonce, y1, y2))]
+    pub fn commitment_challenge(&self, nonce: &BigUint, y1: &BigUint, y2: &BigUint) -> BigUint {
+        self.hash_parts_to_scalar(&[
+            b"zkp-commitment-challenge-v2",
+            self.canonical_bytes(nonce).as_slice(),
+            self.canonical_bytes(y1).as_slice(),
+            self.canonical_bytes(y2).as_slice(),
+        ])
+    }
+
+    /// Derive the Fiat-Shamir challenge for a non-interactive proof
+    ///
+    /// Computes `c = SHA-256(alpha || beta || y1 || y2 || r1 || r2) mod q`
+    /// over a domain tag and the values' `canonical_bytes`, each prefixed
+    /// with its length as a `u64`, so any machine derives the same `c`.
+    pub fn fiat_shamir_challenge(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> BigUint {
+        self.hash_values_to_challenge(b"zkp-fiat-shamir-v2", [y1, y2, r1, r2])
+    }
+
+    /// `SHA-256(domain || alpha || beta || values...) mod q`, each value
+    /// encoded as in `fiat_shamir_challenge`
+    fn hash_values_to_challenge<'a>(
+        &self,
+        domain: &[u8],
+        values: impl IntoIterator<Item = &'a BigUint>,
+    ) -> BigUint {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(domain);
+        self.hash_canonical(&mut hasher, [&self.alpha, &self.beta]);
+        self.hash_canonical(&mut hasher, values);
+        BigUint::from_bytes_be(&hasher.finalize()) % &self.q
+    }
+
+    /// Feed each value's `canonical_bytes` to `hasher`, prefixed with its
+    /// length as a `u64`
+    ///
+    /// The prefix keeps the encoding unambiguous even for a value that is
+    /// not below `p` and so is wider than the rest.
+    fn hash_canonical<'a>(
+        &self,
+        hasher: &mut sha2::Sha256,
+        values: impl IntoIterator<Item = &'a BigUint>,
+    ) {
+        use sha2::Digest;
+
+        for value in values {
+            let bytes = self.canonical_bytes(value);
+            hasher.update((bytes.len() as u64).to_be_bytes());
+            hasher.update(&bytes);
+        }
+    }
+
+    /// Derive the nonce `k` from `secret` and `message` as in RFC 6979
+    ///
+    /// Runs the RFC 6979 section 3.2 HMAC-SHA256 generator keyed by the
+    /// secret and `SHA-256(message)`, so the same inputs always give the same
+    /// `k` in `[1, q)` and no entropy is needed at proof time. Use a distinct
+    /// message per proof: reusing a `k` across challenges reveals the secret.
+    pub fn deterministic_nonce(&self, secret: &BigUint, message: &[u8]) -> BigUint {
+        use hmac::{Hmac, Mac};
+        use sha2::{Digest, Sha256};
+
+        let qlen = self.q.bits();
+        let rlen = qlen.div_ceil(8) as usize;
+        // Leftmost qlen bits of `bytes` as an integer
+        let bits2int = |bytes: &[u8]| {
+            let value = BigUint::from_bytes_be(bytes);
+            let blen = 8 * bytes.len() as u64;
+            if blen > qlen {
+                value >> (blen - qlen)
+            } else {
+                value
+            }
+        };
+        let int2octets = |value: &BigUint| {
+            let bytes = value.to_bytes_be();
+            let mut padded = vec![0u8; rlen.saturating_sub(bytes.len())];
+            padded.extend_from_slice(&bytes);
+            padded
+        };
+        let hmac = |key: &[u8], parts: &[&[u8]]| {
+            let mut mac =
+                Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+            for part in parts {
+                mac.update(part);
+            }
+            mac.finalize().into_bytes().to_vec()
+        };
+
+        let x = int2octets(&(secret % &self.q));
+        let h1 = int2octets(&(bits2int(&Sha256::digest(message)) % &self.q));
+
+        let mut v = vec![0x01u8; 32];
+        let mut k = vec![0x00u8; 32];
+        k = hmac(&k, &[&v, &[0x00], &x, &h1]);
+        v = hmac(&k, &[&v]);
+        k = hmac(&k, &[&v, &[0x01], &x, &h1]);
+        v = hmac(&k, &[&v]);
+
+        loop {
+            let mut t = Vec::with_capacity(rlen);
+            while t.len() < rlen {
+                v = hmac(&k, &[&v]);
+                t.extend_from_slice(&v);
+            }
+            let nonce = bits2int(&t[..rlen]);
+            if nonce >= BigUint::from(1u32) && nonce < self.q {
+                return nonce;
+            }
+            k = hmac(&k, &[&v, &[0x00]]);
+            v = hmac(&k, &[&v]);
+        }
+    }
+
+    /// Prove knowledge of `x` without a round trip to the verifier
+    ///
+    /// `k` must be fresh for every proof: two proofs sharing a `k` reveal `x`.
+    #[instrument(skip(self, x, k))]
+    pub fn prove_noninteractive(&self, x: &BigUint, k: &BigUint) -> ZkpResult<NonInteractiveProof> {
+        let (y1, y2) = self.public_key(x)?;
+        let (r1, r2) = self.commitment(k)?;
+        let c = self.fiat_shamir_challenge(&y1, &y2, &r1, &r2);
+        let s = self.solve(k, &c, x)?;
+        Ok(NonInteractiveProof { r1, r2, c, s })
+    }
+
+    /// Verify a non-interactive proof against the commitment `(y1, y2)`
+    ///
+    /// Rejects the proof if its `c` is not the challenge derived from its
+    /// own commitments, then checks the usual verification equations.
+    #[instrument(skip(self, y1, y2, proof))]
+    pub fn verify_noninteractive(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        proof: &NonInteractiveProof,
+    ) -> ZkpResult<bool> {
+        if proof.c != self.fiat_shamir_challenge(y1, y2, &proof.r1, &proof.r2) {
+            return Ok(false);
+        }
+        self.verify(&proof.r1, &proof.r2, y1, y2, &proof.c, &proof.s)
+    }
+
+    /// Prove knowledge of the secret behind one of `public_keys` without
+    /// revealing which
+    ///
+    /// A non-interactive OR-composition (Cramer-Damgård-Schoenmakers) of the
+    /// usual proof: every other branch is simulated by picking its `c` and
+    /// `s` first, and the branch for `public_keys[known]` gets whatever
+    /// challenge makes all of them add up to the Fiat-Shamir challenge.
+    /// Every branch looks alike, so the proof does not reveal `known`.
+    #[instrument(skip_all, fields(branches = public_keys.len()))]
+    pub fn prove_or(
+        &self,
+        public_keys: &[(BigUint, BigUint)],
+        known: usize,
+        x: &BigUint,
+    ) -> ZkpResult<OrProof> {
+        if known >= public_keys.len() {
+            return Err(ZkpError::InvalidInput(
+                "Known key index is out of range".to_string(),
+            ));
+        }
+
+        let k = Self::generate_random_number_below(&self.q)?;
+        let mut branches = Vec::with_capacity(public_keys.len());
+        let mut commitments = Vec::with_capacity(public_keys.len());
+        for (i, (y1, y2)) in public_keys.iter().enumerate() {
+            if i == known {
+                branches.push(OrProofBranch::default());
+                commitments.push(self.commitment(&k)?);
+            } else {
+                let c = Self::generate_random_number_below(&self.q)?;
+                let s = Self::generate_random_number_below(&self.q)?;
+                commitments.push(self.simulated_commitment(y1, y2, &c, &s));
+                branches.push(OrProofBranch { c, s });
+            }
+        }
+
+        let c = self.or_challenge(public_keys, &commitments);
+        let simulated: BigUint = branches.iter().map(|branch| &branch.c).sum();
+        let c_known = (c + &self.q - simulated % &self.q) % &self.q;
+        branches[known].s = self.solve(&k, &c_known, x)?;
+        branches[known].c = c_known;
+
+        Ok(OrProof { branches })
+    }
+
+    /// Verify an OR-proof from `prove_or` against the same `public_keys`
+    ///
+    /// Does the same work for every branch whichever one the prover knew.
+    #[instrument(skip_all, fields(branches = public_keys.len()))]
+    pub fn verify_or(
+        &self,
+        public_keys: &[(BigUint, BigUint)],
+        proof: &OrProof,
+    ) -> ZkpResult<bool> {
+        if public_keys.is_empty() || proof.branches.len() != public_keys.len() {
+            return Err(ZkpError::InvalidInput(
+                "OR-proof needs one branch per public key".to_string(),
+            ));
+        }
+
+        let mut commitments = Vec::with_capacity(public_keys.len());
+        let mut c_sum = BigUint::from(0u32);
+        for ((y1, y2), branch) in public_keys.iter().zip(&proof.branches) {
+            // r values are recomputed, so y1/y2 stand in for them in the input check
+            self.check_inputs(y1, y2, y1, y2, &branch.c, &branch.s)?;
+            commitments.push(self.simulated_commitment(y1, y2, &branch.c, &branch.s));
+            c_sum += &branch.c;
+        }
+
+        Ok(c_sum % &self.q == self.or_challenge(public_keys, &commitments))
+    }
+
+    /// `(alpha^s * y1^c mod p, beta^s * y2^c mod p)`, the commitment that
+    /// makes `(c, s)` a valid answer for `(y1, y2)`
+    fn simulated_commitment(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> (BigUint, BigUint) {
+        let recompute =
+            |g: &BigUint, y: &BigUint| g.modpow(s, &self.p) * y.modpow(c, &self.p) % &self.p;
+        (recompute(&self.alpha, y1), recompute(&self.beta, y2))
+    }
+
+    /// Fiat-Shamir challenge over every public key and commitment of an OR-proof
+    fn or_challenge(
+        &self,
+        public_keys: &[(BigUint, BigUint)],
+        commitments: &[(BigUint, BigUint)],
+    ) -> BigUint {
+        let count = BigUint::from(public_keys.len());
+        let values = std::iter::once(&count).chain(
+            public_keys
+                .iter()
+                .chain(commitments)
+                .flat_map(|(a, b)| [a, b]),
+        );
+        self.hash_values_to_challenge(b"zkp-or-proof-v2", values)
+    }
+
+    /// Chance that a prover without the secret passes one round
+    ///
+    /// A cheating prover can only answer the one challenge it guessed before
+    /// committing, so with challenges drawn from `[0, q)` a round is passed
+    /// with probability `1/q`. Underflows to 0 for groups beyond `f64` range.
+    pub fn soundness_error(&self) -> f64 {
+        self.q.to_f64().map_or(0.0, |q| 1.0 / q)
+    }
+
+    /// Chance of passing `rounds` independent rounds, `soundness_error^rounds`
+    pub fn soundness_error_after_rounds(&self, rounds: u32) -> f64 {
+        self.soundness_error()
+            .powi(i32::try_from(rounds).unwrap_or(i32::MAX))
+    }
+
+    /// Whether `c` is a challenge worth avoiding
+    ///
+    /// Zero lets anyone answer with `s = k`. One reduces the check to
+    /// `r = alpha^s * y`, still sound but suspicious if drawn often. A
+    /// divisor of `q` only exists when `q` is misconfigured as composite.
+    pub fn is_weak_challenge(&self, c: &BigUint) -> bool {
+        *c <= BigUint::from(1u32) || (c < &self.q && &self.q % c == BigUint::from(0u32))
+    }
+
+    /// Hash arbitrary data to a scalar in `[0, q)` with negligible bias
+    ///
+    /// Expands `data` to 1024 bits with two domain-separated SHA-512 blocks
+    /// before reducing modulo q, so the bias is about `2^-(1024 - q.bits())`.
+    /// A single SHA-256 reduced modulo q is only as good while q stays well
+    /// under 256 bits.
+    pub fn hash_to_scalar(&self, data: &[u8]) -> BigUint {
+        self.hash_to_scalar_with(PasswordHash::Sha512, data)
+    }
+
+    /// `hash_to_scalar` with the 1024-bit expansion done by `hash`
+    pub fn hash_to_scalar_with(&self, hash: PasswordHash, data: &[u8]) -> BigUint {
+        BigUint::from_bytes_be(&hash.expand(data)) % &self.q
+    }
+
+    /// Secret scalar in `[0, q)` for `password`, optionally salted
+    ///
+    /// Reduces through `hash_to_scalar`, so the result is unbiased even for
+    /// a q much wider than one SHA-256 output. Without a salt this is
+    /// `hash_to_scalar(password)`, matching secrets registered before salts
+    /// existed; with one the salt and password are hashed length-prefixed
+    /// under their own domain tag.
+    pub fn hash_password(&self, password: &[u8], salt: Option<&[u8]>) -> BigUint {
+        self.hash_password_with(PasswordHash::default(), password, salt)
+    }
+
+    /// `hash_password` expanding the password with `hash`
+    pub fn hash_password_with(
+        &self,
+        hash: PasswordHash,
+        password: &[u8],
+        salt: Option<&[u8]>,
+    ) -> BigUint {
+        match salt {
+            Some(salt) => self.hash_parts_to_scalar_with(hash, &[b"zkp-password", salt, password]),
+            None => self.hash_to_scalar_with(hash, password),
+        }
+    }
+
+    /// `hash_to_scalar` over length-prefixed `parts`
+    fn hash_parts_to_scalar(&self, parts: &[&[u8]]) -> BigUint {
+        self.hash_parts_to_scalar_with(PasswordHash::default(), parts)
+    }
+
+    fn hash_parts_to_scalar_with(&self, hash: PasswordHash, parts: &[&[u8]]) -> BigUint {
+        let mut data = Vec::new();
+        for part in parts {
+            data.extend_from_slice(&(part.len() as u64).to_be_bytes());
+            data.extend_from_slice(part);
+        }
+        self.hash_to_scalar_with(hash, &data)
+    }
+
+    /// Derive a reproducible test user for the given index
+    ///
+    /// Returns `(username, x, y1, y2)` where the secret `x` is derived from the
+    /// index by hashing, so load tests and fixtures can register N users and
+    /// later authenticate them without storing their secrets.
+    pub fn deterministic_user(&self, index: u64) -> (String, BigUint, BigUint, BigUint) {
+        use sha2::{Digest, Sha256};
+
+        let username = format!("test_user_{}", index);
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"zkp-deterministic-user");
+        hasher.update(index.to_be_bytes());
+        let x = BigUint::from_bytes_be(&hasher.finalize()) % &self.q;
+
+        let y1 = self.alpha.modpow(&x, &self.p);
+        let y2 = self.beta.modpow(&x, &self.p);
+
+        (username, x, y1, y2)
+    }
+
+    /// Get predefined cryptographic constants (1024-bit parameters)
+    ///
+    /// Decoded and `beta` computed on first use, then cloned from a cache.
+    #[instrument]
+    pub fn get_constants() -> (BigUint, BigUint, BigUint, BigUint) {
+        static CONSTANTS: OnceLock<(BigUint, BigUint, BigUint, BigUint)> = OnceLock::new();
+        CONSTANTS.get_or_init(Self::decode_constants).clone()
+    }
+
+    /// Decode the predefined constants from hex and derive `beta`
+    fn decode_constants() -> (BigUint, BigUint, BigUint, BigUint) {
+        let p = BigUint::from_bytes_be(&hex::decode("B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B616073E28675A23D189838EF1E2EE652C013ECB4AEA906112324975C3CD49B83BFACCBDD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0A151AF5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371").unwrap());
+        let q = BigUint::from_bytes_be(
+            &hex::decode("F518AA8781A8DF278ABA4E7D64B7CB9D49462353").unwrap(),
+        );
+
+        let alpha = BigUint::from_bytes_be(
+            &hex::decode("A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31266FEA1E5C41564B777E690F5504F213160217B4B01B886A5E91547F9E2749F4D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28AD662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5").unwrap(),
+        );
+
+        // beta = alpha^i is also a generator
+        let exp = BigUint::from_bytes_be(&hex::decode("266FEA1E5C41564B777E69").unwrap());
+        let beta = alpha.modpow(&exp, &p);
+
+        (alpha, beta, p, q)
+    }
+
+    /// SHA-256 over the canonical encoding of this group's parameters
+    ///
+    /// After the domain tag `zkp-group-checksum-v2`, each of `p`, `q`,
+    /// `alpha` and `beta` is written, in that order, as a big-endian `u64`
+    /// byte length followed by its `canonical_bytes`.
+    pub fn group_checksum(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"zkp-group-checksum-v2");
+        self.hash_canonical(&mut hasher, [&self.p, &self.q, &self.alpha, &self.beta]);
+        hasher.finalize().into()
+    }
+
+    /// `group_checksum` as computed before the canonical encoding
+    ///
+    /// Only for recognising group ids stored by older versions.
+    pub fn legacy_group_checksum(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        for value in [&self.p, &self.q, &self.alpha, &self.beta] {
+            let bytes = value.to_bytes_be();
+            hasher.update((bytes.len() as u64).to_be_bytes());
+            hasher.update(&bytes);
+        }
+        hasher.finalize().into()
+    }
+
+    /// Whether proofs made under `self` verify under `other`, i.e. the groups are identical
+    pub fn is_compatible_with(&self, other: &ZKP) -> bool {
+        self.p == other.p
+            && self.q == other.q
+            && self.alpha == other.alpha
+            && self.beta == other.beta
+    }
+
+    /// Adopt the group a server advertises through `GetServerInfo`
+    ///
+    /// The parameters are strictly validated, since a client should not
+    /// trust a server to pick a sound group, and must match the advertised
+    /// group id.
+    #[cfg(feature = "grpc")]
+    pub fn from_server_info(resp: &zkp_auth::ServerInfoResponse) -> ZkpResult<ZKP> {
+        let zkp = ZKP {
+            p: serialization::deserialize_biguint(&resp.p)?,
+            q: serialization::deserialize_biguint(&resp.q)?,
+            alpha: serialization::deserialize_biguint(&resp.alpha)?,
+            beta: serialization::deserialize_biguint(&resp.beta)?,
+        };
+        zkp.validate_parameters_strict()?;
+
+        let checksum = hex::encode(zkp.group_checksum());
+        if !checksum.eq_ignore_ascii_case(&resp.group_id) {
+            return Err(ZkpError::InvalidInput(format!(
+                "Advertised group id {} does not match the group parameters ({})",
+                resp.group_id, checksum
+            )));
+        }
+
+        Ok(zkp)
+    }
+
+    /// Checksum of the predefined group returned by `get_constants`
+    pub fn constants_checksum() -> [u8; 32] {
+        let (alpha, beta, p, q) = Self::get_constants();
+        Self { p, q, alpha, beta }.group_checksum()
+    }
+
+    /// Check the predefined constants against their known-good checksum
+    pub fn verify_constants_integrity() -> ZkpResult<()> {
+        let checksum = Self::constants_checksum();
+        if checksum != PREDEFINED_CONSTANTS_CHECKSUM {
+            return Err(ZkpError::InvalidInput(format!(
+                "Predefined constants checksum mismatch: expected {}, got {}",
+                hex::encode(PREDEFINED_CONSTANTS_CHECKSUM),
+                hex::encode(checksum)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check this group's checksum against a hex-encoded pinned value
+    ///
+    /// Passes when nothing (or an empty string) is pinned, so builds without a
+    /// pin are unaffected.
+    pub fn check_pinned_checksum(&self, pinned: Option<&str>) -> ZkpResult<()> {
+        let pinned = match pinned.map(str::trim) {
+            Some(pinned) if !pinned.is_empty() => pinned,
+            _ => return Ok(()),
+        };
+
+        let checksum = hex::encode(self.group_checksum());
+        if !checksum.eq_ignore_ascii_case(pinned) {
+            return Err(ZkpError::InvalidInput(format!(
+                "Group checksum mismatch: pinned {}, got {}",
+                pinned, checksum
+            )));
+        }
+
+        info!("Group checksum matches pinned value");
+        Ok(())
+    }
+
+    /// Validate that the ZKP parameters are cryptographically sound
+    ///
+    /// On failure the error names the check that failed along with the bit
+    /// sizes of every parameter.
+    pub fn validate_parameters(&self) -> ZkpResult<()> {
+        self.validate_parameters_with_max_bits(DEFAULT_MAX_GROUP_BITS)
+    }
+
+    /// `validate_parameters` with a custom cap on the size of `p` and `q`
+    ///
+    /// The cap is checked before any exponentiation, so oversized parameters
+    /// from an untrusted source are rejected cheaply.
+    pub fn validate_parameters_with_max_bits(&self, max_bits: u64) -> ZkpResult<()> {
+        let details = || self.describe_parameters();
+        if self.p.bits() > max_bits || self.q.bits() > max_bits {
+            return Err(ZkpError::GroupTooLarge {
+                max_bits,
+                details: details(),
+            });
+        }
+
+        // Basic parameter validation
+        let one = BigUint::from(1u32);
+        if self.p <= one || self.q <= one {
+            return Err(ZkpError::ModulusOutOfRange { details: details() });
+        }
+
+        if self.alpha >= self.p || self.beta >= self.p || self.alpha <= one || self.beta <= one {
+            return Err(ZkpError::GeneratorOutOfRange { details: details() });
+        }
+
+        // With alpha == beta both verification equations are the same check
+        if self.alpha == self.beta {
+            return Err(ZkpError::GeneratorsNotDistinct { details: details() });
+        }
+
+        if self.alpha.modpow(&self.q, &self.p) != one || self.beta.modpow(&self.q, &self.p) != one {
+            return Err(ZkpError::GeneratorOrder { details: details() });
+        }
+
+        info!("ZKP parameters validated successfully");
+        Ok(())
+    }
+
+    /// `validate_parameters` plus the number-theoretic checks it skips
+    ///
+    /// Requires p and q to be probable primes and `q | p - 1`.
+    pub fn validate_parameters_strict(&self) -> ZkpResult<()> {
+        self.validate_parameters()?;
+
+        if !is_probable_prime(&self.p, PRIMALITY_ROUNDS) {
+            return Err(self.parameter_error("p is not prime"));
+        }
+
+        if !is_probable_prime(&self.q, PRIMALITY_ROUNDS) {
+            return Err(self.parameter_error("q is not prime"));
+        }
+
+        let one = BigUint::from(1u32);
+        if (&self.p - &one) % &self.q != BigUint::from(0u32) {
+            return Err(self.parameter_error("q does not divide p - 1"));
+        }
+
+        Ok(())
+    }
+
+    /// Generate a fresh Schnorr group with a `bits`-bit modulus
+    ///
+    /// Picks a prime q of `min(bits / 2, 256)` bits, searches for a prime
+    /// `p = m*q + 1`, and derives each generator as `h^((p-1)/q)` for a
+    /// random h. The result is not validated; see `generate_validated`.
+    pub fn generate_group(bits: u64) -> ZkpResult<Self> {
+        if bits < MIN_GENERATED_GROUP_BITS {
+            return Err(ZkpError::InvalidInput(format!(
+                "Group size must be at least {} bits",
+                MIN_GENERATED_GROUP_BITS
+            )));
+        }
+
+        let mut rng = rand::thread_rng();
+        let one = BigUint::from(1u32);
+        let q_bits = (bits / 2).min(256);
+
+        let q = loop {
+            let candidate = rng.gen_biguint(q_bits) | (&one << (q_bits - 1)) | &one;
+            if is_probable_prime(&candidate, PRIMALITY_ROUNDS) {
+                break candidate;
+            }
+        };
+
+        let m_bits = bits - q_bits;
+        let p = loop {
+            // m even so that p = m*q + 1 is odd
+            let m = (rng.gen_biguint(m_bits) | (&one << (m_bits - 1))) >> 1u32 << 1u32;
+            let candidate = m * &q + &one;
+            if candidate.bits() == bits && is_probable_prime(&candidate, PRIMALITY_ROUNDS) {
+                break candidate;
+            }
+        };
+
+        let cofactor = (&p - &one) / &q;
+        let mut generator = || loop {
+            let h = rng.gen_biguint_range(&BigUint::from(2u32), &(&p - &one));
+            let g = h.modpow(&cofactor, &p);
+            if g != one {
+                return g;
+            }
+        };
+        let alpha = generator();
+        let beta = loop {
+            let beta = generator();
+            if beta != alpha {
+                break beta;
+            }
+        };
+
+        Ok(Self { p, q, alpha, beta })
+    }
+
+    /// Generate fresh parameters over a `bits`-bit safe prime `p = 2q + 1`
+    ///
+    /// `alpha` is a random square, which generates the order-q subgroup,
+    /// and `beta = alpha^i` for a random `i`. Gives up with a
+    /// `ComputationError` after `4 * bits^2` candidates for q, several times
+    /// the expected number. Slow for production sizes.
+    pub fn generate_parameters(bits: usize) -> ZkpResult<Self> {
+        if (bits as u64) < MIN_GENERATED_GROUP_BITS {
+            return Err(ZkpError::InvalidInput(format!(
+                "Group size must be at least {} bits",
+                MIN_GENERATED_GROUP_BITS
+            )));
+        }
+
+        let mut rng = rand::thread_rng();
+        let one = BigUint::from(1u32);
+        let two = BigUint::from(2u32);
+        let q_bits = bits as u64 - 1;
+        let small_primes: Vec<u32> = (3u32..1000)
+            .filter(|n| (2..*n).take_while(|d| d * d <= *n).all(|d| n % d != 0))
+            .collect();
+
+        let max_attempts = 4 * bits * bits;
+        let (p, q) = (0..max_attempts)
+            .find_map(|_| {
+                let q = rng.gen_biguint(q_bits) | (&one << (q_bits - 1)) | &one;
+                let p = &q * &two + &one;
+                // Cheap sieve of both before any Miller-Rabin rounds
+                let sieved = small_primes.iter().all(|&prime| {
+                    let prime = BigUint::from(prime);
+                    (q == prime || &q % &prime != BigUint::from(0u32))
+                        && &p % &prime != BigUint::from(0u32)
+                });
+                (sieved
+                    && is_probable_prime(&q, PRIMALITY_ROUNDS)
+                    && is_probable_prime(&p, PRIMALITY_ROUNDS))
+                .then_some((p, q))
+            })
+            .ok_or_else(|| {
+                ZkpError::ComputationError(format!(
+                    "No {}-bit safe prime after {} attempts",
+                    bits, max_attempts
+                ))
+            })?;
+
+        let alpha = loop {
+            let h = rng.gen_biguint_range(&two, &(&p - &one));
+            let alpha = h.modpow(&two, &p);
+            if alpha != one {
+                break alpha;
+            }
+        };
+        let i = rng.gen_biguint_range(&two, &q);
+        let beta = alpha.modpow(&i, &p);
+
+        Ok(Self { p, q, alpha, beta })
+    }
+
+    /// Generate a group and strictly validate it, retrying failed candidates
+    ///
+    /// Returns the first group to pass `validate_parameters_strict`, or a
+    /// `ComputationError` once `max_attempts` candidates have failed.
+    pub fn generate_validated(bits: u64, max_attempts: u32) -> ZkpResult<Self> {
+        for attempt in 1..=max_attempts {
+            let zkp = Self::generate_group(bits)?;
+            match zkp.validate_parameters_strict() {
+                Ok(()) => return Ok(zkp),
+                Err(e) => warn!(
+                    "Generated group failed validation (attempt {}/{}): {}",
+                    attempt, max_attempts, e
+                ),
+            }
+        }
+
+        Err(ZkpError::ComputationError(format!(
+            "No valid {}-bit group after {} attempts",
+            bits, max_attempts
+        )))
+    }
+
+    /// Rough estimate of the group's security level in bits
+    ///
+    /// The weaker of the generic attack on the order-q subgroup (Pollard's
+    /// rho, `q.bits() / 2`) and the number field sieve modulo `p`. The
+    /// predefined 1024-bit group comes out at about 80 bits.
+    pub fn security_level_bits(&self) -> usize {
+        let rho_bits = self.q.bits() as usize / 2;
+        rho_bits.min(nfs_security_bits(self.p.bits()))
+    }
+
+    /// Summarize the bit sizes of the group parameters for diagnostics
+    pub fn describe_parameters(&self) -> String {
+        format!(
+            "p: {} bits, q: {} bits, alpha: {} bits, beta: {} bits",
+            self.p.bits(),
+            self.q.bits(),
+            self.alpha.bits(),
+            self.beta.bits()
+        )
+    }
+
+    fn parameter_error(&self, check: &str) -> ZkpError {
+        ZkpError::InvalidInput(format!("{} ({})", check, self.describe_parameters()))
+    }
+}
+
+/// Decimal form of `value`, shortened to `first…last (N digits)` past 20 digits
+fn abbreviate(value: &BigUint) -> String {
+    let digits = value.to_string();
+    if digits.len() <= 20 {
+        return digits;
+    }
+    format!(
+        "{}…{} ({} digits)",
+        &digits[..8],
+        &digits[digits.len() - 8..],
+        digits.len()
+    )
+}
+
+/// Miller-Rabin rounds used for primality checks (error below 2^-80)
+const PRIMALITY_ROUNDS: usize = 40;
+
+/// Smallest modulus `ZKP::generate_group` will produce
+const MIN_GENERATED_GROUP_BITS: u64 = 16;
+
+/// Largest `p` and `q` that `ZKP::validate_parameters` accepts
+pub const DEFAULT_MAX_GROUP_BITS: u64 = 8192;
+
+/// Miller-Rabin probabilistic primality test with `rounds` random bases
+fn is_probable_prime(n: &BigUint, rounds: usize) -> bool {
+    let one = BigUint::from(1u32);
+    let two = BigUint::from(2u32);
+
+    for small in [2u32, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37] {
+        let small = BigUint::from(small);
+        if *n == small {
+            return true;
+        }
+        if n % &small == BigUint::from(0u32) {
+            return false;
+        }
+    }
+    if *n < two {
+        return false;
+    }
+
+    let n_minus_one = n - &one;
+    let twos = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> twos;
+
+    let mut rng = rand::thread_rng();
+    'witness: for _ in 0..rounds {
+        let a = rng.gen_biguint_range(&two, &n_minus_one);
+        let mut x = a.modpow(&d, n);
+        if x == one || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..twos {
+            x = x.modpow(&two, n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Work, in bits, for the general number field sieve against a prime modulus
+///
+/// Evaluates `L_p[1/3, (64/9)^(1/3)]` without its o(1) term, which slightly
+/// overstates the cost (about 87 bits at 1024 bits, 117 at 2048).
+fn nfs_security_bits(modulus_bits: u64) -> usize {
+    if modulus_bits < 2 {
+        return 0;
+    }
+
+    let ln_p = modulus_bits as f64 * std::f64::consts::LN_2;
+    let work = (64.0f64 / 9.0).cbrt() * ln_p.cbrt() * ln_p.ln().powf(2.0 / 3.0);
+    (work / std::f64::consts::LN_2) as usize
+}
+
+/// Hash that expands a password before it is reduced to a secret
+///
+/// Stored with each registration so login derives the secret the same way.
+/// Written as `sha256`, `sha512` or `blake3`; `sha512` is the mapping used
+/// before the choice existed, and an empty string on the wire means it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PasswordHash {
+    Sha256,
+    #[default]
+    Sha512,
+    Blake3,
+}
+
+impl PasswordHash {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PasswordHash::Sha256 => "sha256",
+            PasswordHash::Sha512 => "sha512",
+            PasswordHash::Blake3 => "blake3",
+        }
+    }
+
+    /// Parse the protobuf field, where an empty string is the default
+    pub fn from_wire(s: &str) -> ZkpResult<Self> {
+        if s.is_empty() {
+            return Ok(PasswordHash::default());
+        }
+        s.parse()
+    }
+
+    /// 1024 bits derived from `data`, in domain-separated blocks or as XOF output
+    fn expand(&self, data: &[u8]) -> [u8; 128] {
+        use sha2::{Digest, Sha256, Sha512};
+
+        let mut wide = [0u8; 128];
+        match self {
+            PasswordHash::Sha256 => {
+                for (block, chunk) in wide.chunks_mut(32).enumerate() {
+                    chunk.copy_from_slice(
+                        &Sha256::new()
+                            .chain_update([block as u8])
+                            .chain_update(data)
+                            .finalize(),
+                    );
+                }
+            }
+            PasswordHash::Sha512 => {
+                for (block, chunk) in wide.chunks_mut(64).enumerate() {
+                    chunk.copy_from_slice(
+                        &Sha512::new()
+                            .chain_update([block as u8])
+                            .chain_update(data)
+                            .finalize(),
+                    );
+                }
+            }
+            PasswordHash::Blake3 => {
+                blake3::Hasher::new()
+                    .update(data)
+                    .finalize_xof()
+                    .fill(&mut wide);
+            }
+        }
+        wide
+    }
+}
+
+impl std::fmt::Display for PasswordHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for PasswordHash {
+    type Err = ZkpError;
+
+    fn from_str(s: &str) -> ZkpResult<Self> {
+        match s {
+            "sha256" => Ok(PasswordHash::Sha256),
+            "sha512" => Ok(PasswordHash::Sha512),
+            "blake3" => Ok(PasswordHash::Blake3),
+            _ => Err(ZkpError::InvalidInput(format!(
+                "Unknown password hash: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for PasswordHash {
+    type Error = ZkpError;
+
+    fn try_from(s: String) -> ZkpResult<Self> {
+        s.parse()
+    }
+}
+
+impl From<PasswordHash> for String {
+    fn from(hash: PasswordHash) -> String {
+        hash.to_string()
+    }
+}
+
+/// Group parameters supplied by a caller, validated once on construction
+///
+/// Keep one per group and reuse it across `verify_with_group` calls to avoid
+/// re-validating the parameters for every proof.
+#[derive(Debug)]
+pub struct GroupParams {
+    zkp: ZKP,
+}
+
+impl GroupParams {
+    /// Validate `p`, `q`, `alpha` and `beta` as a group
+    pub fn new(p: BigUint, q: BigUint, alpha: BigUint, beta: BigUint) -> ZkpResult<Self> {
+        Self::try_from(ZKP { p, q, alpha, beta })
+    }
+
+    /// The validated group
+    pub fn group(&self) -> &ZKP {
+        &self.zkp
+    }
+}
+
+impl TryFrom<ZKP> for GroupParams {
+    type Error = ZkpError;
+
+    fn try_from(zkp: ZKP) -> ZkpResult<Self> {
+        zkp.validate_parameters()?;
+        Ok(Self { zkp })
+    }
+}
+
+/// A proof from `ZKP::prove_noninteractive`
+///
+/// Serializes to JSON with every value as a hex string.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NonInteractiveProof {
+    #[serde(with = "serialization::hex_biguint")]
+    pub r1: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub r2: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub c: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub s: BigUint,
+}
+
+/// Proof from `ZKP::prove_or`, one branch per public key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrProof {
+    pub branches: Vec<OrProofBranch>,
+}
+
+/// Challenge share and answer for one public key of an `OrProof`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrProofBranch {
+    #[serde(with = "serialization::hex_biguint")]
+    pub c: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub s: BigUint,
+}
+
+/// Public values exchanged during one authentication round
+///
+/// Serializes to JSON with every value as a hex string. The timestamps are
+/// optional so transcripts recorded without them still parse.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProofTranscript {
+    #[serde(with = "serialization::hex_biguint")]
+    pub r1: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub r2: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub y1: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub y2: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub c: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub s: BigUint,
+    /// When the challenge was issued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenged_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// When the answer was accepted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ProofTranscript {
+    /// Re-run the verification equations for this round in `zkp`
+    pub fn verify(&self, zkp: &ZKP) -> ZkpResult<bool> {
+        zkp.verify(&self.r1, &self.r2, &self.y1, &self.y2, &self.c, &self.s)
+    }
+
+    pub fn to_json(&self) -> ZkpResult<String> {
+        serde_json::to_string_pretty(self).map_err(|e| ZkpError::SerializationError(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> ZkpResult<Self> {
+        serde_json::from_str(json).map_err(|e| ZkpError::SerializationError(e.to_string()))
+    }
+}
+
+/// Verify `transcript` against caller-supplied group parameters
+pub fn verify_with_group(params: &GroupParams, transcript: &ProofTranscript) -> ZkpResult<bool> {
+    transcript.verify(params.group())
+}
+
+/// Known-answer vector: inputs `(group, x, k, c)` and the outputs they give
+///
+/// Serializes to JSON with every value as a hex string, the outputs in the
+/// `ProofTranscript` layout, for checking other implementations against.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestVector {
+    #[serde(with = "serialization::hex_biguint")]
+    pub p: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub q: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub alpha: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub beta: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub x: BigUint,
+    #[serde(with = "serialization::hex_biguint")]
+    pub k: BigUint,
+    #[serde(flatten)]
+    pub transcript: ProofTranscript,
+}
+
+impl TestVector {
+    /// Compute the outputs for `x`, `k` and `c` in `zkp`
+    pub fn compute(zkp: &ZKP, x: BigUint, k: BigUint, c: BigUint) -> ZkpResult<Self> {
+        let (y1, y2) = zkp.public_key(&x)?;
+        let (r1, r2) = zkp.commitment(&k)?;
+        let s = zkp.solve(&k, &c, &x)?;
+        Ok(Self {
+            p: zkp.p.clone(),
+            q: zkp.q.clone(),
+            alpha: zkp.alpha.clone(),
+            beta: zkp.beta.clone(),
+            x,
+            k,
+            transcript: ProofTranscript {
+                r1,
+                r2,
+                y1,
+                y2,
+                c,
+                s,
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Whether the outputs match a recomputation and the transcript verifies
+    pub fn verify(&self) -> ZkpResult<bool> {
+        let zkp = ZKP {
+            p: self.p.clone(),
+            q: self.q.clone(),
+            alpha: self.alpha.clone(),
+            beta: self.beta.clone(),
+        };
+        let expected = Self::compute(
+            &zkp,
+            self.x.clone(),
+            self.k.clone(),
+            self.transcript.c.clone(),
+        )?;
+        Ok(expected == *self && self.transcript.verify(&zkp)?)
+    }
+}
+
+/// `count` known-answer vectors in each of a toy group and the default group
+///
+/// The toy group (p = 23) is small enough to check by hand. Inputs are
+/// hashed from their index, so the same `count` always gives the same file.
+pub fn generate_test_vectors(count: usize) -> ZkpResult<Vec<TestVector>> {
+    let toy = ZKP {
+        p: BigUint::from(23u32),
+        q: BigUint::from(11u32),
+        alpha: BigUint::from(4u32),
+        beta: BigUint::from(9u32),
+    };
+    let groups = [toy, ZKP::new(None)?];
+
+    let mut vectors = Vec::with_capacity(groups.len() * count);
+    for zkp in &groups {
+        for index in 0..count {
+            let input = |name: &str| {
+                zkp.hash_to_scalar(format!("zkp-test-vector-{}-{}", name, index).as_bytes())
+            };
+            // x = 0 would make y1 = y2 = 1, which `verify` rejects
+            let x = input("x").max(BigUint::from(1u32));
+            vectors.push(TestVector::compute(zkp, x, input("k"), input("c"))?);
+        }
+    }
+    Ok(vectors)
+}
+
+/// Chaum-Pedersen operations over a prime-order group
+///
+/// Implemented by `ZKP` for the multiplicative group mod `p` and by
+/// `ec::EcZkp` for Ristretto255, so code generic over the group runs on
+/// either backend.
+pub trait ZkpGroup {
+    /// Exponents: secrets, nonces, challenges and answers
+    type Scalar;
+    /// Group elements: public keys and commitments
+    type Element;
+
+    /// Uniformly random scalar, for nonces and challenges
+    fn random_scalar(&self) -> ZkpResult<Self::Scalar>;
+
+    /// `(alpha^exp, beta^exp)` in the group's two generators
+    fn compute_pair(&self, exp: &Self::Scalar) -> ZkpResult<(Self::Element, Self::Element)>;
+
+    /// Answer `s = k - c * x` to the challenge `c`
+    fn solve(
+        &self,
+        k: &Self::Scalar,
+        c: &Self::Scalar,
+        x: &Self::Scalar,
+    ) -> ZkpResult<Self::Scalar>;
+
+    /// Check `r1 = alpha^s * y1^c` and `r2 = beta^s * y2^c`
+    fn verify(
+        &self,
+        r1: &Self::Element,
+        r2: &Self::Element,
+        y1: &Self::Element,
+        y2: &Self::Element,
+        c: &Self::Scalar,
+        s: &Self::Scalar,
+    ) -> ZkpResult<bool>;
+}
+
+impl ZkpGroup for ZKP {
+    type Scalar = BigUint;
+    type Element = BigUint;
+
+    fn random_scalar(&self) -> ZkpResult<BigUint> {
+        Self::generate_random_number_below(&self.q)
+    }
+
+    fn compute_pair(&self, exp: &BigUint) -> ZkpResult<(BigUint, BigUint)> {
+        ZKP::compute_pair(self, exp)
+    }
+
+    fn solve(&self, k: &BigUint, c: &BigUint, x: &BigUint) -> ZkpResult<BigUint> {
+        ZKP::solve(self, k, c, x)
+    }
+
+    fn verify(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> ZkpResult<bool> {
+        ZKP::verify(self, r1, r2, y1, y2, c, s)
+    }
+}
+
+/// Chaum-Pedersen over the Ristretto255 elliptic-curve group
+pub mod ec {
+    use curve25519_dalek::{
+        constants::RISTRETTO_BASEPOINT_TABLE,
+        ristretto::{CompressedRistretto, RistrettoPoint},
+        traits::IsIdentity,
+        Scalar,
+    };
+    use rand::RngCore;
+    use sha2::{Digest, Sha512};
+
+    use super::{ZkpError, ZkpGroup, ZkpResult};
+
+    /// Domain separator hashed to the second generator
+    const BETA_DOMAIN: &[u8] = b"zkp-ristretto255-beta";
+
+    /// Ristretto255 backend: 32-byte elements and scalars
+    ///
+    /// `alpha` is always the standard basepoint, multiplied through its
+    /// precomputed table. `beta` is hashed to the curve from a fixed domain
+    /// string, so nobody knows `log_alpha(beta)`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct EcZkp {
+        pub beta: RistrettoPoint,
+    }
+
+    impl Default for EcZkp {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl EcZkp {
+        pub fn new() -> Self {
+            let digest: [u8; 64] = Sha512::digest(BETA_DOMAIN).into();
+            Self {
+                beta: RistrettoPoint::from_uniform_bytes(&digest),
+            }
+        }
+
+        /// Scalar derived from `data`, e.g. a password
+        pub fn hash_to_scalar(data: &[u8]) -> Scalar {
+            Scalar::from_bytes_mod_order_wide(&Sha512::digest(data).into())
+        }
+
+        /// Canonical 32-byte encoding of `element`
+        pub fn serialize_element(element: &RistrettoPoint) -> [u8; 32] {
+            element.compress().to_bytes()
+        }
+
+        /// Decode a 32-byte element, rejecting non-canonical encodings
+        pub fn deserialize_element(bytes: &[u8]) -> ZkpResult<RistrettoPoint> {
+            CompressedRistretto::from_slice(bytes)
+                .ok()
+                .and_then(|compressed| compressed.decompress())
+                .ok_or_else(|| {
+                    ZkpError::SerializationError("Invalid Ristretto255 element".to_string())
+                })
+        }
+
+        /// Decode a 32-byte scalar, rejecting values not reduced mod the group order
+        pub fn deserialize_scalar(bytes: &[u8]) -> ZkpResult<Scalar> {
+            let bytes: [u8; 32] = bytes
+                .try_into()
+                .map_err(|_| ZkpError::SerializationError("Scalar must be 32 bytes".to_string()))?;
+            Option::from(Scalar::from_canonical_bytes(bytes))
+                .ok_or_else(|| ZkpError::SerializationError("Scalar is not reduced".to_string()))
+        }
+    }
+
+    impl ZkpGroup for EcZkp {
+        type Scalar = Scalar;
+        type Element = RistrettoPoint;
+
+        fn random_scalar(&self) -> ZkpResult<Scalar> {
+            let mut bytes = [0u8; 64];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            Ok(Scalar::from_bytes_mod_order_wide(&bytes))
+        }
+
+        fn compute_pair(&self, exp: &Scalar) -> ZkpResult<(RistrettoPoint, RistrettoPoint)> {
+            Ok((exp * RISTRETTO_BASEPOINT_TABLE, self.beta * exp))
+        }
+
+        fn solve(&self, k: &Scalar, c: &Scalar, x: &Scalar) -> ZkpResult<Scalar> {
+            Ok(k - c * x)
+        }
+
+        fn verify(
+            &self,
+            r1: &RistrettoPoint,
+            r2: &RistrettoPoint,
+            y1: &RistrettoPoint,
+            y2: &RistrettoPoint,
+            c: &Scalar,
+            s: &Scalar,
+        ) -> ZkpResult<bool> {
+            // The identity as a public key would accept any answer
+            if y1.is_identity() || y2.is_identity() {
+                return Err(ZkpError::IdentityPublicValue);
+            }
+
+            let lhs1 = RistrettoPoint::vartime_double_scalar_mul_basepoint(c, y1, s);
+            let lhs2 = self.beta * s + y2 * c;
+            Ok(*r1 == lhs1 && *r2 == lhs2)
+        }
+    }
+}
+
+/// Prover and verifier for JavaScript, taking and returning hex strings
+///
+/// The free functions use the default group; `Verifier` checks proofs in any
+/// validated group. The `*_hex` functions hold the logic and are usable from
+/// Rust; the `wasm_bindgen` exports wrap them and surface errors as JS
+/// exceptions.
+#[cfg(feature = "wasm")]
+pub mod wasm {
+    use num_bigint::BigUint;
+    use wasm_bindgen::prelude::*;
+
+    use super::{serialization, ZkpResult, ZKP};
+
+    fn parse(hex: &str) -> ZkpResult<BigUint> {
+        serialization::deserialize_biguint_hex(hex)
+    }
+
+    /// Commitment pair `(alpha^k, beta^k)` for the hex nonce `k`
+    pub fn compute_pair_hex(k: &str) -> ZkpResult<(String, String)> {
+        let (r1, r2) = ZKP::new(None)?.compute_pair(&parse(k)?)?;
+        Ok((
+            serialization::serialize_biguint_hex(&r1),
+            serialization::serialize_biguint_hex(&r2),
+        ))
+    }
+
+    /// Response `s = k - c * x mod q` as hex
+    pub fn solve_hex(k: &str, c: &str, x: &str) -> ZkpResult<String> {
+        let s = ZKP::new(None)?.solve(&parse(k)?, &parse(c)?, &parse(x)?)?;
+        Ok(serialization::serialize_biguint_hex(&s))
+    }
+
+    /// Check a proof given as hex values against the default group
+    pub fn verify_hex(r1: &str, r2: &str, y1: &str, y2: &str, c: &str, s: &str) -> ZkpResult<bool> {
+        Verifier {
+            zkp: ZKP::new(None)?,
+        }
+        .verify_hex(r1, r2, y1, y2, c, s)
+    }
+
+    /// Verifier for proofs in a known group
+    #[wasm_bindgen]
+    #[derive(Debug)]
+    pub struct Verifier {
+        zkp: ZKP,
+    }
+
+    impl Verifier {
+        /// Verifier for the hex group `(p, q, alpha, beta)`, validated up front
+        pub fn from_hex(p: &str, q: &str, alpha: &str, beta: &str) -> ZkpResult<Self> {
+            let zkp = ZKP::from_parameters(parse(p)?, parse(q)?, parse(alpha)?, parse(beta)?)?;
+            Ok(Self { zkp })
+        }
+
+        /// Check a proof given as hex values
+        pub fn verify_hex(
+            &self,
+            r1: &str,
+            r2: &str,
+            y1: &str,
+            y2: &str,
+            c: &str,
+            s: &str,
+        ) -> ZkpResult<bool> {
+            self.zkp.verify(
+                &parse(r1)?,
+                &parse(r2)?,
+                &parse(y1)?,
+                &parse(y2)?,
+                &parse(c)?,
+                &parse(s)?,
+            )
+        }
+    }
+
+    #[wasm_bindgen]
+    impl Verifier {
+        /// `new Verifier(p, q, alpha, beta)` with hex group parameters
+        #[wasm_bindgen(constructor)]
+        pub fn new(p: &str, q: &str, alpha: &str, beta: &str) -> Result<Verifier, JsError> {
+            Ok(Self::from_hex(p, q, alpha, beta)?)
+        }
+
+        /// Verifier for the default group
+        #[wasm_bindgen(js_name = defaultGroup)]
+        pub fn default_group() -> Result<Verifier, JsError> {
+            Ok(Self {
+                zkp: ZKP::new(None)?,
+            })
+        }
+
+        /// Whether the hex proof verifies in this group
+        pub fn verify(
+            &self,
+            r1: &str,
+            r2: &str,
+            y1: &str,
+            y2: &str,
+            c: &str,
+            s: &str,
+        ) -> Result<bool, JsError> {
+            Ok(self.verify_hex(r1, r2, y1, y2, c, s)?)
+        }
+    }
+
+    /// `[r1, r2]` for the hex nonce `k`
+    #[wasm_bindgen(js_name = computePair)]
+    pub fn compute_pair(k: &str) -> Result<Vec<String>, JsError> {
+        let (r1, r2) = compute_pair_hex(k)?;
+        Ok(vec![r1, r2])
+    }
+
+    /// Response `s` for the hex nonce, challenge and secret
+    #[wasm_bindgen]
+    pub fn solve(k: &str, c: &str, x: &str) -> Result<String, JsError> {
+        Ok(solve_hex(k, c, x)?)
+    }
+
+    /// Whether the hex proof verifies
+    #[wasm_bindgen]
+    pub fn verify(
+        r1: &str,
+        r2: &str,
+        y1: &str,
+        y2: &str,
+        c: &str,
+        s: &str,
+    ) -> Result<bool, JsError> {
+        Ok(verify_hex(r1, r2, y1, y2, c, s)?)
+    }
+}
+
+/// Generated gRPC types for the authentication service
+#[cfg(feature = "grpc")]
+pub mod zkp_auth {
+    include!("./zkp_auth.rs");
+}
+
+/// Embeddable client for the authentication service
+#[cfg(feature = "grpc")]
+pub mod client {
+    use std::fs::{self, OpenOptions};
+    use std::future::Future;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use rayon::prelude::*;
+    use tonic::{transport::Channel, Code, Status};
+
+    use super::*;
+    use crate::zkp_auth::{
+        auth_client::AuthClient, AuthenticationAnswerRequest, AuthenticationAnswerResponse,
+        AuthenticationChallengeRequest, RegisterRequest, UpdateCredentialsRequest,
+    };
+
+    /// Length of the random salt drawn when rotating credentials
+    pub const SALT_LEN: usize = 16;
+
+    /// Why a client call failed, classified by the server's status code
+    ///
+    /// Statuses are boxed to keep the error small.
+    #[derive(Error, Debug)]
+    pub enum ClientError {
+        /// The server could not be reached or did not answer in time
+        #[error("Server unreachable: {}", .0.message())]
+        Transport(Box<Status>),
+        /// The server refused the credentials or proof
+        #[error("Authentication rejected: {}", .0.message())]
+        AuthRejected(Box<Status>),
+        /// Too many requests; worth retrying later
+        #[error("Rate limited: {}", .0.message())]
+        RateLimited(Box<Status>),
+        /// The server rejected the request itself, e.g. an unknown user or bad input
+        #[error("Request rejected ({:?}): {}", .0.code(), .0.message())]
+        Protocol(Box<Status>),
+        #[error(transparent)]
+        Zkp(#[from] ZkpError),
+    }
+
+    impl From<Status> for ClientError {
+        fn from(status: Status) -> Self {
+            let status = Box::new(status);
+            match status.code() {
+                Code::Unavailable | Code::DeadlineExceeded | Code::Cancelled => {
+                    Self::Transport(status)
+                }
+                Code::Unauthenticated | Code::PermissionDenied => Self::AuthRejected(status),
+                Code::ResourceExhausted => Self::RateLimited(status),
+                _ => Self::Protocol(status),
+            }
+        }
+    }
+
+    /// Result type for client calls
+    pub type ClientResult<T> = Result<T, ClientError>;
+
+    /// Bounded retry policy for transient server errors
+    ///
+    /// The default policy never retries.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct RetryPolicy {
+        pub max_retries: u32,
+        pub initial_backoff: Duration,
+    }
+
+    impl RetryPolicy {
+        /// Whether a failed call with this status is worth retrying
+        pub fn is_transient(status: &Status) -> bool {
+            matches!(status.code(), Code::ResourceExhausted | Code::Unavailable)
+        }
+
+        /// Run `op`, retrying transient failures with exponential backoff
+        pub async fn run<T, F, Fut>(&self, mut op: F) -> Result<T, Status>
+        where
+            F: FnMut() -> Fut,
+            Fut: Future<Output = Result<T, Status>>,
+        {
+            let mut backoff = self.initial_backoff;
+            let mut attempt = 0;
+
+            loop {
+                match op().await {
+                    Err(status) if attempt < self.max_retries && Self::is_transient(&status) => {
+                        attempt += 1;
+                        warn!(
+                            "Transient server error ({}), retry {}/{} in {:?}",
+                            status.message(),
+                            attempt,
+                            self.max_retries,
+                            backoff
+                        );
+                        tokio::time::sleep(backoff).await;
+                        backoff *= 2;
+                    }
+                    result => return result,
+                }
+            }
+        }
+    }
+
+    /// Nonce `k` with its commitment `(r1, r2) = (alpha^k, beta^k)`
+    #[derive(Clone, PartialEq)]
+    pub struct Commitment {
+        pub k: BigUint,
+        pub r1: BigUint,
+        pub r2: BigUint,
+    }
+
+    impl std::fmt::Debug for Commitment {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Commitment")
+                .field("k", &Redacted(&self.k))
+                .field("r1", &self.r1)
+                .field("r2", &self.r2)
+                .finish()
+        }
+    }
+
+    /// A challenge the client has answered, kept until the answer is accepted
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SolvedChallenge {
+        pub auth_id: String,
+        pub commitment: Commitment,
+        pub c: BigUint,
+        pub s: BigUint,
+    }
+
+    impl SolvedChallenge {
+        /// The answer to (re)send for this challenge
+        pub fn answer_request(&self) -> AuthenticationAnswerRequest {
+            AuthenticationAnswerRequest {
+                auth_id: self.auth_id.clone(),
+                s: serialization::serialize_biguint(&self.s),
+            }
+        }
+    }
+
+    impl Commitment {
+        /// Draw a fresh nonce and compute its commitment
+        pub fn generate(zkp: &ZKP) -> ZkpResult<Self> {
+            let k = ZKP::generate_random_number_below(&zkp.q)?;
+            let (r1, r2) = zkp.commitment(&k)?;
+            Ok(Self { k, r1, r2 })
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct PoolEntry {
+        masked_k: BigUint,
+        r1: BigUint,
+        r2: BigUint,
+    }
+
+    /// File of commitments precomputed ahead of time, each used for one login
+    ///
+    /// Nonces are stored as `k + H(password, r1, r2) mod q`, so the file is
+    /// useless without the password and a wrong password is detected before
+    /// anything is consumed. Taking an entry rewrites the file without it
+    /// under an exclusive lock file, so a nonce is never handed out twice.
+    #[derive(Debug, Clone)]
+    pub struct CommitmentPool {
+        path: PathBuf,
+    }
+
+    impl CommitmentPool {
+        pub fn new(path: impl AsRef<Path>) -> Self {
+            Self {
+                path: path.as_ref().to_path_buf(),
+            }
+        }
+
+        /// Number of unused entries; a missing file is an empty pool
+        pub fn len(&self) -> ZkpResult<usize> {
+            Ok(self.load()?.len())
+        }
+
+        pub fn is_empty(&self) -> ZkpResult<bool> {
+            Ok(self.len()? == 0)
+        }
+
+        /// Append `count` fresh commitments, returning the new pool size
+        pub fn fill(&self, zkp: &ZKP, password: &str, count: usize) -> ZkpResult<usize> {
+            let _lock = PoolLock::acquire(&self.path)?;
+            let mut entries = self.load()?;
+            for _ in 0..count {
+                let Commitment { k, r1, r2 } = Commitment::generate(zkp)?;
+                let masked_k = (k + Self::mask(zkp, password, &r1, &r2)) % &zkp.q;
+                entries.push(PoolEntry { masked_k, r1, r2 });
+            }
+            self.save(&entries)?;
+            Ok(entries.len())
+        }
+
+        /// Remove and return the oldest commitment, or `None` if the pool is empty
+        pub fn take(&self, zkp: &ZKP, password: &str) -> ZkpResult<Option<Commitment>> {
+            let _lock = PoolLock::acquire(&self.path)?;
+            let mut entries = self.load()?;
+            if entries.is_empty() {
+                return Ok(None);
+            }
+
+            let PoolEntry { masked_k, r1, r2 } = entries.remove(0);
+            let k = (masked_k + &zkp.q - Self::mask(zkp, password, &r1, &r2)) % &zkp.q;
+            if zkp.commitment(&k)? != (r1.clone(), r2.clone()) {
+                return Err(ZkpError::InvalidInput(
+                    "Commitment pool does not match this password or group".to_string(),
+                ));
+            }
+
+            self.save(&entries)?;
+            Ok(Some(Commitment { k, r1, r2 }))
+        }
+
+        fn mask(zkp: &ZKP, password: &str, r1: &BigUint, r2: &BigUint) -> BigUint {
+            zkp.hash_parts_to_scalar(&[
+                b"zkp-commitment-pool-v2",
+                password.as_bytes(),
+                zkp.canonical_bytes(r1).as_slice(),
+                zkp.canonical_bytes(r2).as_slice(),
+            ])
+        }
+
+        fn load(&self) -> ZkpResult<Vec<PoolEntry>> {
+            let json = match fs::read_to_string(&self.path) {
+                Ok(json) => json,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => return Err(ZkpError::SerializationError(e.to_string())),
+            };
+            serde_json::from_str(&json).map_err(|e| ZkpError::SerializationError(e.to_string()))
+        }
+
+        fn save(&self, entries: &[PoolEntry]) -> ZkpResult<()> {
+            let json = serde_json::to_string(entries)
+                .map_err(|e| ZkpError::SerializationError(e.to_string()))?;
+
+            let tmp_path = self.path.with_extension("tmp");
+            fs::write(&tmp_path, json)
+                .and_then(|()| fs::rename(&tmp_path, &self.path))
+                .map_err(|e| ZkpError::SerializationError(e.to_string()))
+        }
+    }
+
+    /// Lock file held while a pool is read and rewritten, removed on drop
+    struct PoolLock {
+        path: PathBuf,
+    }
+
+    impl PoolLock {
+        const ATTEMPTS: u32 = 50;
+
+        fn acquire(pool_path: &Path) -> ZkpResult<Self> {
+            let path = pool_path.with_extension("lock");
+            for _ in 0..Self::ATTEMPTS {
+                match OpenOptions::new().write(true).create_new(true).open(&path) {
+                    Ok(_) => return Ok(Self { path }),
+                    Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                        std::thread::sleep(Duration::from_millis(20));
+                    }
+                    Err(e) => return Err(ZkpError::SerializationError(e.to_string())),
+                }
+            }
+            Err(ZkpError::ComputationError(format!(
+                "Commitment pool is locked; remove {} if no other client is running",
+                path.display()
+            )))
+        }
+    }
+
+    impl Drop for PoolLock {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    /// Algorithm, difficulty and seed of the proof of work a rejected challenge request asked for
+    fn pow_requirement(status: &Status) -> Option<(pow::Algorithm, u32, Vec<u8>)> {
+        if status.code() != Code::FailedPrecondition {
+            return None;
+        }
+
+        let metadata = status.metadata();
+        let difficulty = metadata
+            .get(pow::DIFFICULTY_METADATA_KEY)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok()?;
+        let seed = hex::decode(metadata.get(pow::SEED_METADATA_KEY)?.to_str().ok()?).ok()?;
+        let algorithm = match metadata.get(pow::ALGORITHM_METADATA_KEY) {
+            Some(value) => value.to_str().ok()?.parse().ok()?,
+            None => pow::Algorithm::Sha256,
+        };
+        Some((algorithm, difficulty, seed))
+    }
+
+    /// Convert password string to BigUint deterministically (unsalted `ZKP::hash_password`)
+    pub fn password_to_biguint(password: &str, zkp: &ZKP) -> BigUint {
+        zkp.hash_password(password.as_bytes(), None)
+    }
+
+    /// Secret for `username`'s password at relying party `rp_id`
+    ///
+    /// Computes `H(rp_id, username, password)`, the username acting as the
+    /// salt, so one password yields unrelated public values at different
+    /// relying parties and a proof made for one never verifies at another.
+    /// An empty `rp_id` falls back to the unbound `password_to_biguint`.
+    pub fn derive_secret(password: &str, username: &str, rp_id: &str, zkp: &ZKP) -> BigUint {
+        derive_secret_with_hash(password, username, rp_id, PasswordHash::default(), zkp)
+    }
+
+    /// `derive_secret` expanding the password with `hash` instead of SHA-512
+    pub fn derive_secret_with_hash(
+        password: &str,
+        username: &str,
+        rp_id: &str,
+        hash: PasswordHash,
+        zkp: &ZKP,
+    ) -> BigUint {
+        if rp_id.is_empty() {
+            return zkp.hash_password_with(hash, password.as_bytes(), None);
+        }
+        zkp.hash_parts_to_scalar_with(
+            hash,
+            &[
+                b"zkp-rp-binding",
+                rp_id.as_bytes(),
+                username.as_bytes(),
+                password.as_bytes(),
+            ],
+        )
+    }
+
+    /// `derive_secret_with_hash` with a random per-user `salt` mixed in
+    ///
+    /// An empty salt gives the unsalted `derive_secret_with_hash` secret, so
+    /// users registered before salts existed keep logging in.
+    pub fn derive_salted_secret(
+        password: &str,
+        username: &str,
+        rp_id: &str,
+        hash: PasswordHash,
+        salt: &[u8],
+        zkp: &ZKP,
+    ) -> BigUint {
+        if salt.is_empty() {
+            return derive_secret_with_hash(password, username, rp_id, hash, zkp);
+        }
+        zkp.hash_parts_to_scalar_with(
+            hash,
+            &[
+                b"zkp-salted-secret",
+                rp_id.as_bytes(),
+                username.as_bytes(),
+                salt,
+                password.as_bytes(),
+            ],
+        )
+    }
+
+    /// Perform user registration, returning the server-assigned user id
+    pub async fn register_user(
+        client: &mut AuthClient<Channel>,
+        zkp: &ZKP,
+        username: &str,
+        password: &str,
+    ) -> ClientResult<String> {
+        register_user_for_rp(client, zkp, "", username, password).await
+    }
+
+    /// Register with the secret bound to relying party `rp_id` (see `derive_secret`)
+    pub async fn register_user_for_rp(
+        client: &mut AuthClient<Channel>,
+        zkp: &ZKP,
+        rp_id: &str,
+        username: &str,
+        password: &str,
+    ) -> ClientResult<String> {
+        register_user_with_hash(
+            client,
+            zkp,
+            rp_id,
+            PasswordHash::default(),
+            username,
+            password,
+        )
+        .await
+    }
+
+    /// Register with the password expanded by `hash`
+    ///
+    /// The server stores the choice and returns it with every challenge, so
+    /// logins need no extra configuration.
+    #[instrument(skip(client, zkp, password))]
+    pub async fn register_user_with_hash(
+        client: &mut AuthClient<Channel>,
+        zkp: &ZKP,
+        rp_id: &str,
+        hash: PasswordHash,
+        username: &str,
+        password: &str,
+    ) -> ClientResult<String> {
+        info!("Starting registration for user: {}", username);
+
+        let password_biguint = derive_secret_with_hash(password, username, rp_id, hash, zkp);
+        let (y1, y2) = zkp.public_key(&password_biguint)?;
+
+        let request = RegisterRequest {
+            user: username.to_string(),
+            y1: serialization::serialize_biguint(&y1),
+            y2: serialization::serialize_biguint(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            rp_id: rp_id.to_string(),
+            password_hash: hash.to_string(),
+            salt: Vec::new(),
+        };
+
+        let response = client.register(request).await?.into_inner();
+
+        info!(
+            "✅ Registration successful for user: {} (id: {})",
+            username, response.user_id
+        );
+        Ok(response.user_id)
+    }
+
+    /// Registration requests for many `(username, password)` pairs
+    ///
+    /// The exponentiations dominate, so the packages are computed in
+    /// parallel on rayon's thread pool. Requests come back in input order.
+    pub fn registration_packages(
+        zkp: &ZKP,
+        users: &[(String, String)],
+    ) -> ZkpResult<Vec<RegisterRequest>> {
+        users
+            .par_iter()
+            .map(|(username, password)| {
+                let (y1, y2) = zkp.public_key(&password_to_biguint(password, zkp))?;
+                Ok(RegisterRequest {
+                    user: username.clone(),
+                    y1: serialization::serialize_biguint(&y1),
+                    y2: serialization::serialize_biguint(&y2),
+                    protocol_version: PROTOCOL_VERSION,
+                    rp_id: String::new(),
+                    password_hash: String::new(),
+                    salt: Vec::new(),
+                })
+            })
+            .collect()
+    }
+
+    /// Register many users at once, returning their user ids in input order
+    ///
+    /// Packages are built with `registration_packages`, then all submitted
+    /// concurrently over the client's channel. Stops at the first failure;
+    /// users registered before it stay registered.
+    #[instrument(skip_all, fields(users = users.len()))]
+    pub async fn register_users_bulk(
+        client: &AuthClient<Channel>,
+        zkp: &ZKP,
+        users: &[(String, String)],
+    ) -> ClientResult<Vec<String>> {
+        let packages = registration_packages(zkp, users)?;
+        let responses = futures::future::try_join_all(packages.into_iter().map(|request| {
+            let mut client = client.clone();
+            async move { client.register(request).await }
+        }))
+        .await?;
+
+        info!("✅ Registered {} users", responses.len());
+        Ok(responses
+            .into_iter()
+            .map(|response| response.into_inner().user_id)
+            .collect())
+    }
+
+    /// Perform user authentication, returning the session id
+    pub async fn authenticate_user(
+        client: &mut AuthClient<Channel>,
+        zkp: &ZKP,
+        username: &str,
+        password: &str,
+        retry: &RetryPolicy,
+    ) -> ClientResult<String> {
+        let commitment = Commitment::generate(zkp)?;
+        authenticate_with_commitment(client, zkp, username, password, commitment, retry).await
+    }
+
+    /// Perform user authentication with a commitment computed ahead of time
+    ///
+    /// `commitment` must never have been used for another login.
+    pub async fn authenticate_with_commitment(
+        client: &mut AuthClient<Channel>,
+        zkp: &ZKP,
+        username: &str,
+        password: &str,
+        commitment: Commitment,
+        retry: &RetryPolicy,
+    ) -> ClientResult<String> {
+        authenticate_with_commitment_for_rp(client, zkp, "", username, password, commitment, retry)
+            .await
+    }
+
+    /// `authenticate_with_commitment` for a secret bound to relying party `rp_id`
+    #[instrument(skip(client, zkp, password, commitment, retry))]
+    pub async fn authenticate_with_commitment_for_rp(
+        client: &mut AuthClient<Channel>,
+        zkp: &ZKP,
+        rp_id: &str,
+        username: &str,
+        password: &str,
+        commitment: Commitment,
+        retry: &RetryPolicy,
+    ) -> ClientResult<String> {
+        info!("Starting authentication for user: {}", username);
+
+        let Commitment { k, r1, r2 } = commitment;
+
+        // Request challenge, solving a proof of work if the server asks for one
+        let mut challenge_request = AuthenticationChallengeRequest {
+            user: username.to_string(),
+            r1: serialization::serialize_biguint(&r1),
+            r2: serialization::serialize_biguint(&r2),
+            protocol_version: PROTOCOL_VERSION,
+            pow_nonce: 0,
+            rp_id: rp_id.to_string(),
+        };
+
+        let mut pow_solved = false;
+        let challenge_response = loop {
+            let result = retry
+                .run(|| {
+                    let mut client = client.clone();
+                    let challenge_request = challenge_request.clone();
+                    async move {
+                        client
+                            .create_authentication_challenge(challenge_request)
+                            .await
+                    }
+                })
+                .await;
+
+            match result {
+                Err(status) if !pow_solved => match pow_requirement(&status) {
+                    Some((algorithm, difficulty, seed)) => {
+                        info!(
+                            "Server requires a {}-bit {} proof of work",
+                            difficulty, algorithm
+                        );
+                        if difficulty > pow::MAX_CLIENT_DIFFICULTY {
+                            return Err(ZkpError::InvalidInput(format!(
+                                "Server requires a {}-bit proof of work, over the client cap of {}",
+                                difficulty,
+                                pow::MAX_CLIENT_DIFFICULTY
+                            ))
+                            .into());
+                        }
+                        // Solving takes seconds at high difficulties; keep it off the runtime
+                        let context = pow::context(username, &r1, &r2, &seed);
+                        challenge_request.pow_nonce = tokio::task::spawn_blocking(move || {
+                            algorithm.solve(&context, difficulty)
+                        })
+                        .await
+                        .map_err(|e| {
+                            ZkpError::ComputationError(format!("Proof-of-work task failed: {}", e))
+                        })??;
+                        pow_solved = true;
+                    }
+                    None => break Err(status),
+                },
+                result => break result,
+            }
+        }?
+        .into_inner();
+
+        // The secret depends on the hash and salt the user registered with
+        let hash = PasswordHash::from_wire(&challenge_response.password_hash)?;
+        let password_biguint = derive_salted_secret(
+            password,
+            username,
+            rp_id,
+            hash,
+            &challenge_response.salt,
+            zkp,
+        );
+        let c = serialization::deserialize_biguint(&challenge_response.c)?;
+        let s = zkp.solve(&k, &c, &password_biguint)?;
+        let solved = SolvedChallenge {
+            auth_id: challenge_response.auth_id,
+            commitment: Commitment { k, r1, r2 },
+            c,
+            s,
+        };
+
+        let answer_response = submit_answer_for_response(client, &solved, retry).await?;
+        info!("✅ Authentication successful for user: {}", username);
+
+        // Logging in succeeded either way; a failed rotation is retried next time
+        if answer_response.rotate_salt {
+            info!("Server asked to rotate the salt for user: {}", username);
+            if let Err(e) = update_credentials(
+                client,
+                zkp,
+                rp_id,
+                hash,
+                &answer_response.session_id,
+                &answer_response.rotation_token,
+                username,
+                password,
+            )
+            .await
+            {
+                warn!("Failed to rotate the salt for user {}: {}", username, e);
+            }
+        }
+        Ok(answer_response.session_id)
+    }
+
+    /// Re-register the logged-in user's public values under a fresh random salt
+    ///
+    /// `rotation_token` is the one-shot token of the login that set
+    /// `rotate_salt`. Returns the new salt.
+    #[instrument(skip(client, zkp, session_id, rotation_token, password))]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_credentials(
+        client: &mut AuthClient<Channel>,
+        zkp: &ZKP,
+        rp_id: &str,
+        hash: PasswordHash,
+        session_id: &str,
+        rotation_token: &str,
+        username: &str,
+        password: &str,
+    ) -> ClientResult<Vec<u8>> {
+        let mut salt = vec![0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        let secret = derive_salted_secret(password, username, rp_id, hash, &salt, zkp);
+        let (y1, y2) = zkp.public_key(&secret)?;
+        client
+            .update_credentials(UpdateCredentialsRequest {
+                session_id: session_id.to_string(),
+                y1: serialization::serialize_biguint(&y1),
+                y2: serialization::serialize_biguint(&y2),
+                salt: salt.clone(),
+                password_hash: hash.to_string(),
+                rotation_token: rotation_token.to_string(),
+            })
+            .await?;
+
+        info!("🔑 Updated credentials for user: {}", username);
+        Ok(salt)
+    }
+
+    /// Submit the cached answer to an in-flight challenge, retrying transient failures
+    ///
+    /// Every attempt resends the same `s` for the same `auth_id`, so a
+    /// network blip does not cost a fresh commitment and challenge. Once the
+    /// server has consumed the `auth_id` a resend fails for good.
+    pub async fn submit_answer(
+        client: &mut AuthClient<Channel>,
+        solved: &SolvedChallenge,
+        retry: &RetryPolicy,
+    ) -> ClientResult<String> {
+        Ok(submit_answer_for_response(client, solved, retry)
+            .await?
+            .session_id)
+    }
+
+    /// `submit_answer` returning the whole response
+    async fn submit_answer_for_response(
+        client: &mut AuthClient<Channel>,
+        solved: &SolvedChallenge,
+        retry: &RetryPolicy,
+    ) -> ClientResult<AuthenticationAnswerResponse> {
+        let answer_response = retry
+            .run(|| {
+                let mut client = client.clone();
+                let answer_request = solved.answer_request();
+                async move { client.verify_authentication(answer_request).await }
+            })
+            .await?
+            .into_inner();
+
+        Ok(answer_response)
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use std::cell::Cell;
+
+        #[test]
+        fn test_client_error_from_status_code() {
+            let classify = |status: Status| match ClientError::from(status) {
+                ClientError::Transport(_) => "transport",
+                ClientError::AuthRejected(_) => "auth_rejected",
+                ClientError::RateLimited(_) => "rate_limited",
+                ClientError::Protocol(_) => "protocol",
+                ClientError::Zkp(_) => "zkp",
+            };
+
+            assert_eq!(
+                classify(Status::unavailable("connection refused")),
+                "transport"
+            );
+            assert_eq!(classify(Status::deadline_exceeded("timeout")), "transport");
+            assert_eq!(
+                classify(Status::permission_denied("Authentication failed")),
+                "auth_rejected"
+            );
+            assert_eq!(
+                classify(Status::unauthenticated("no session")),
+                "auth_rejected"
+            );
+            assert_eq!(
+                classify(Status::resource_exhausted("Too many challenge requests")),
+                "rate_limited"
+            );
+            assert_eq!(
+                classify(Status::not_found("User alice not found")),
+                "protocol"
+            );
+            assert_eq!(classify(Status::invalid_argument("bad r1")), "protocol");
+
+            let error = ClientError::from(Status::permission_denied("account locked"));
+            assert_eq!(error.to_string(), "Authentication rejected: account locked");
+        }
+
+        #[tokio::test]
+        async fn test_retry_recovers_from_single_resource_exhausted() {
+            let policy = RetryPolicy {
+                max_retries: 3,
+                initial_backoff: Duration::from_millis(1),
+            };
+            let calls = Cell::new(0);
+
+            let result = policy
+                .run(|| {
+                    calls.set(calls.get() + 1);
+                    let call = calls.get();
+                    async move {
+                        if call == 1 {
+                            Err(Status::resource_exhausted("Too many challenge requests"))
+                        } else {
+                            Ok(call)
+                        }
+                    }
+                })
+                .await;
+
+            assert_eq!(result.unwrap(), 2);
+            assert_eq!(calls.get(), 2);
+        }
+
+        #[tokio::test]
+        async fn test_retry_is_bounded_and_skips_permanent_errors() {
+            let policy = RetryPolicy {
+                max_retries: 2,
+                initial_backoff: Duration::from_millis(1),
+            };
+            let calls = Cell::new(0);
+            let result: Result<(), Status> = policy
+                .run(|| {
+                    calls.set(calls.get() + 1);
+                    async { Err(Status::unavailable("down")) }
+                })
+                .await;
+            assert_eq!(result.unwrap_err().code(), Code::Unavailable);
+            assert_eq!(calls.get(), 3);
+
+            calls.set(0);
+            let result: Result<(), Status> = RetryPolicy::default()
+                .run(|| {
+                    calls.set(calls.get() + 1);
+                    async { Err(Status::resource_exhausted("busy")) }
+                })
+                .await;
+            assert_eq!(result.unwrap_err().code(), Code::ResourceExhausted);
+            assert_eq!(calls.get(), 1);
+        }
+
+        #[test]
+        fn test_commitment_pool_entries_are_used_once() {
+            let zkp = ZKP::new(None).unwrap();
+            let path = std::env::temp_dir().join(format!("zkp-pool-{}.json", uuid::Uuid::new_v4()));
+            let pool = CommitmentPool::new(&path);
+
+            assert_eq!(pool.fill(&zkp, "password", 3).unwrap(), 3);
+            assert_eq!(pool.fill(&zkp, "password", 2).unwrap(), 5);
+
+            // A wrong password is rejected without consuming anything
+            assert!(pool.take(&zkp, "wrong").is_err());
+            assert_eq!(pool.len().unwrap(), 5);
+
+            let mut taken = Vec::new();
+            while let Some(commitment) = pool.take(&zkp, "password").unwrap() {
+                assert_eq!(
+                    zkp.compute_pair(&commitment.k).unwrap(),
+                    (commitment.r1.clone(), commitment.r2.clone())
+                );
+                assert!(!taken.contains(&commitment));
+                taken.push(commitment);
+            }
+            assert_eq!(taken.len(), 5);
+            assert!(pool.is_empty().unwrap());
+
+            fs::remove_file(&path).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
-    fn test_toy_example() {
-        let alpha = BigUint::from(4u32);
-        let beta = BigUint::from(9u32);
-        let p = BigUint::from(23u32);
-        let q = BigUint::from(11u32);
-        let zkp = ZKP {
-            p: p.clone(),
-            q,
-            alpha: alpha.clone(),
-            beta: beta.clone(),
+    fn test_toy_example() {
+        let alpha = BigUint::from(4u32);
+        let beta = BigUint::from(9u32);
+        let p = BigUint::from(23u32);
+        let q = BigUint::from(11u32);
+        let zkp = ZKP {
+            p: p.clone(),
+            q,
+            alpha: alpha.clone(),
+            beta: beta.clone(),
+        };
+
+        let x = BigUint::from(6u32);
+        let k = BigUint::from(7u32);
+        let c = BigUint::from(4u32);
+
+        let (y1, y2) = zkp.compute_pair(&x).unwrap();
+        assert_eq!(y1, BigUint::from(2u32));
+        assert_eq!(y2, BigUint::from(3u32));
+
+        let (r1, r2) = zkp.compute_pair(&k).unwrap();
+        assert_eq!(r1, BigUint::from(8u32));
+        assert_eq!(r2, BigUint::from(4u32));
+
+        let s = zkp.solve(&k, &c, &x).unwrap();
+        assert_eq!(s, BigUint::from(5u32));
+
+        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap();
+        assert!(result);
+
+        // fake secret
+        let x_fake = BigUint::from(7u32);
+        let s_fake = zkp.solve(&k, &c, &x_fake).unwrap();
+
+        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s_fake).unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_generated_vectors_reverify() {
+        let vectors = generate_test_vectors(4).unwrap();
+        assert_eq!(vectors.len(), 8);
+        assert_eq!(vectors, generate_test_vectors(4).unwrap());
+
+        let json = serde_json::to_string_pretty(&vectors).unwrap();
+        let parsed: Vec<TestVector> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, vectors);
+
+        for vector in &parsed {
+            assert!(vector.verify().unwrap());
+            let zkp = ZKP {
+                p: vector.p.clone(),
+                q: vector.q.clone(),
+                alpha: vector.alpha.clone(),
+                beta: vector.beta.clone(),
+            };
+            assert!(vector.transcript.verify(&zkp).unwrap());
+
+            // A wrong expected output is caught
+            let mut tampered = vector.clone();
+            tampered.transcript.s = (&tampered.transcript.s + 1u32) % &zkp.q;
+            assert!(!tampered.verify().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_prevalidate_proof() {
+        let element = [0xab; 128];
+        let scalar = [0xcd; 128];
+        let fields = [&element[..], &element, &element, &element, &scalar, &scalar];
+        let check = |fields: &[&[u8]; 6]| {
+            let [r1, r2, y1, y2, c, s] = *fields;
+            prevalidate_proof(r1, r2, y1, y2, c, s, 128, 128)
+        };
+        assert!(check(&fields).is_ok());
+
+        // Empty or overlong, each field is caught and named
+        let too_long = [0u8; 129];
+        for (i, name) in ["r1", "r2", "y1", "y2", "c", "s"].into_iter().enumerate() {
+            for (bad, problem) in [(&[][..], "is empty"), (&too_long[..], "longer than")] {
+                let mut tampered = fields;
+                tampered[i] = bad;
+                let message = check(&tampered).unwrap_err().to_string();
+                assert!(message.contains(&format!("{} ", name)), "{}", message);
+                assert!(message.contains(problem), "{}", message);
+            }
+        }
+
+        // Scalars are held to q's width, elements to p's
+        assert!(prevalidate_proof(
+            &element, &element, &element, &element, &scalar, &scalar, 128, 127
+        )
+        .is_err());
+        assert!(
+            prevalidate_proof(&element, &element, &element, &element, &[1], &[1], 127, 128)
+                .is_err()
+        );
+
+        // The first problem is the one reported
+        let message = check(&[&[], &element, &element, &element, &scalar, &[]])
+            .unwrap_err()
+            .to_string();
+        assert!(message.starts_with("Invalid input: r1 "));
+    }
+
+    #[test]
+    fn test_random_number_from_seeded_rng() {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+
+        let zkp = ZKP::new(None).unwrap();
+        let draw = |seed| {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            ZKP::generate_random_number_below_with(&zkp.q, &mut rng).unwrap()
+        };
+
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+        assert!(draw(7) < zkp.q);
+        assert!(ZKP::generate_random_number_below_with(
+            &BigUint::from(0u32),
+            &mut ChaCha20Rng::seed_from_u64(7)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_password_hash_choices() {
+        let zkp = ZKP::new(None).unwrap();
+        let hashes = [
+            PasswordHash::Sha256,
+            PasswordHash::Sha512,
+            PasswordHash::Blake3,
+        ];
+        let secrets: Vec<BigUint> = hashes
+            .iter()
+            .map(|hash| zkp.hash_password_with(*hash, b"hunter2", None))
+            .collect();
+
+        assert_ne!(secrets[0], secrets[1]);
+        assert_ne!(secrets[0], secrets[2]);
+        assert_ne!(secrets[1], secrets[2]);
+
+        for hash in hashes {
+            assert_eq!(hash.to_string().parse::<PasswordHash>().unwrap(), hash);
+            let json = serde_json::to_string(&hash).unwrap();
+            assert_eq!(serde_json::from_str::<PasswordHash>(&json).unwrap(), hash);
+        }
+        assert_eq!(PasswordHash::from_wire("").unwrap(), PasswordHash::Sha512);
+        assert!(PasswordHash::from_wire("md5").is_err());
+    }
+
+    #[test]
+    fn test_prover_single_pass() {
+        let zkp = ZKP::new(None).unwrap();
+        let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let c = ZKP::generate_random_number_below(&zkp.q).unwrap();
+
+        // Same values as the step-by-step path for the same nonce
+        let (r1, r2) = zkp.commitment(&k).unwrap();
+        let s = zkp.solve(&k, &c, &x).unwrap();
+        assert_eq!(
+            zkp.prover_single_pass_with_nonce(&x, &c, &k).unwrap(),
+            (r1, r2, s)
+        );
+
+        let (y1, y2) = zkp.public_key(&x).unwrap();
+        let (r1, r2, s) = zkp.prover_single_pass(&x, &c).unwrap();
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap());
+
+        assert!(zkp.prover_single_pass(&zkp.q, &c).is_err());
+        assert!(zkp.prover_single_pass(&x, &zkp.q).is_err());
+    }
+
+    /// Honest and dishonest prover round on any backend
+    fn group_round_trip<G: ZkpGroup>(group: &G) {
+        let x = group.random_scalar().unwrap();
+        let k = group.random_scalar().unwrap();
+        let c = group.random_scalar().unwrap();
+
+        let (y1, y2) = group.compute_pair(&x).unwrap();
+        let (r1, r2) = group.compute_pair(&k).unwrap();
+        let s = group.solve(&k, &c, &x).unwrap();
+        assert!(group.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap());
+
+        let x_fake = group.random_scalar().unwrap();
+        let s_fake = group.solve(&k, &c, &x_fake).unwrap();
+        assert!(!group.verify(&r1, &r2, &y1, &y2, &c, &s_fake).unwrap());
+    }
+
+    #[test]
+    fn test_ec_backend_round_trip() {
+        use curve25519_dalek::{traits::Identity, RistrettoPoint};
+        use ec::EcZkp;
+
+        let ec = EcZkp::new();
+        group_round_trip(&ec);
+        group_round_trip(&ZKP::new(None).unwrap());
+
+        // Elements and scalars cross the wire as 32 bytes each
+        let x = EcZkp::hash_to_scalar(b"password");
+        let (y1, _) = ZkpGroup::compute_pair(&ec, &x).unwrap();
+        let bytes = EcZkp::serialize_element(&y1);
+        assert_eq!(EcZkp::deserialize_element(&bytes).unwrap(), y1);
+        assert_eq!(EcZkp::deserialize_scalar(x.as_bytes()).unwrap(), x);
+        assert!(EcZkp::deserialize_element(&[0xff; 32]).is_err());
+        assert!(EcZkp::deserialize_scalar(&[0xff; 32]).is_err());
+
+        let identity = RistrettoPoint::identity();
+        let s = ec.random_scalar().unwrap();
+        assert!(ec
+            .verify(&identity, &identity, &identity, &identity, &s, &s)
+            .is_err());
+    }
+
+    #[test]
+    fn test_or_proof() {
+        let zkp = ZKP::new(None).unwrap();
+        let secret = || ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let (x_a, x_b, x_other) = (secret(), secret(), secret());
+        let public_keys = [zkp.public_key(&x_a).unwrap(), zkp.public_key(&x_b).unwrap()];
+
+        // Knowing either secret is enough
+        for (known, x) in [(0, &x_a), (1, &x_b)] {
+            let proof = zkp.prove_or(&public_keys, known, x).unwrap();
+            assert_eq!(proof.branches.len(), 2);
+            assert!(zkp.verify_or(&public_keys, &proof).unwrap());
+
+            let json = serde_json::to_string(&proof).unwrap();
+            let proof: OrProof = serde_json::from_str(&json).unwrap();
+            assert!(zkp.verify_or(&public_keys, &proof).unwrap());
+        }
+
+        // Knowing neither is not, whichever branch the prover claims
+        for known in 0..2 {
+            let proof = zkp.prove_or(&public_keys, known, &x_other).unwrap();
+            assert!(!zkp.verify_or(&public_keys, &proof).unwrap());
+        }
+
+        // A proof does not carry over to another set of keys
+        let proof = zkp.prove_or(&public_keys, 0, &x_a).unwrap();
+        let other_keys = [public_keys[0].clone(), zkp.public_key(&x_other).unwrap()];
+        assert!(!zkp.verify_or(&other_keys, &proof).unwrap());
+        assert!(zkp.verify_or(&public_keys[..1], &proof).is_err());
+        assert!(zkp.prove_or(&public_keys, 2, &x_a).is_err());
+    }
+
+    #[test]
+    fn test_noninteractive_proof() {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        let value = |v: u32| BigUint::from(v);
+        let (y1, y2) = zkp.compute_pair(&value(6)).unwrap();
+
+        // Known answer, so the challenge derivation cannot drift unnoticed
+        let proof = zkp.prove_noninteractive(&value(6), &value(7)).unwrap();
+        assert_eq!(
+            proof,
+            NonInteractiveProof {
+                r1: value(8),
+                r2: value(4),
+                c: value(10),
+                s: value(2),
+            }
+        );
+        assert!(zkp.verify_noninteractive(&y1, &y2, &proof).unwrap());
+
+        // A proof for another commitment, or with a chosen c, is rejected
+        let (other_y1, other_y2) = zkp.compute_pair(&value(5)).unwrap();
+        assert!(!zkp
+            .verify_noninteractive(&other_y1, &other_y2, &proof)
+            .unwrap());
+        let forged = NonInteractiveProof {
+            c: value(0),
+            s: value(7),
+            ..proof.clone()
+        };
+        assert!(zkp
+            .verify(&forged.r1, &forged.r2, &y1, &y2, &forged.c, &forged.s)
+            .unwrap());
+        assert!(!zkp.verify_noninteractive(&y1, &y2, &forged).unwrap());
+
+        // Round trip with the production group
+        let zkp = ZKP::new(None).unwrap();
+        let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let (y1, y2) = zkp.compute_pair(&x).unwrap();
+        let proof = zkp.prove_noninteractive(&x, &k).unwrap();
+        let json = serde_json::to_string(&proof).unwrap();
+        let proof: NonInteractiveProof = serde_json::from_str(&json).unwrap();
+        assert!(zkp.verify_noninteractive(&y1, &y2, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_explain_verify() {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        let value = |v: u32| BigUint::from(v);
+
+        let explanation = zkp.explain_verify(
+            &value(8),
+            &value(4),
+            &value(2),
+            &value(3),
+            &value(4),
+            &value(5),
+        );
+        assert_eq!(
+            explanation,
+            "r1 =? alpha^s · y1^c mod p → 8 =? 4^5 · 2^4 mod 23 = 8 ✓\n\
+             r2 =? beta^s · y2^c mod p → 4 =? 9^5 · 3^4 mod 23 = 4 ✓"
+        );
+
+        // A wrong answer shows the value actually recomputed
+        let explanation = zkp.explain_verify(
+            &value(8),
+            &value(4),
+            &value(2),
+            &value(3),
+            &value(4),
+            &value(6),
+        );
+        assert!(explanation.contains("8 =? 4^6 · 2^4 mod 23 = 9 ✗"));
+
+        let zkp = ZKP::new(None).unwrap();
+        let t = transcript_for(&zkp);
+        let explanation = zkp.explain_verify(&t.r1, &t.r2, &t.y1, &t.y2, &t.c, &t.s);
+        assert!(explanation.contains("(309 digits)"));
+        assert_eq!(explanation.matches('✓').count(), 2);
+    }
+
+    #[test]
+    fn test_toy_example_with_random_numbers() {
+        let alpha = BigUint::from(4u32);
+        let beta = BigUint::from(9u32);
+        let p = BigUint::from(23u32);
+        let q = BigUint::from(11u32);
+        let zkp = ZKP {
+            p: p.clone(),
+            q: q.clone(),
+            alpha: alpha.clone(),
+            beta: beta.clone(),
+        };
+
+        let x = BigUint::from(6u32);
+        let k = ZKP::generate_random_number_below(&q).unwrap();
+        let c = ZKP::generate_random_number_below(&q).unwrap();
+
+        let (y1, y2) = zkp.compute_pair(&x).unwrap();
+        assert_eq!(y1, BigUint::from(2u32));
+        assert_eq!(y2, BigUint::from(3u32));
+
+        let (r1, r2) = zkp.compute_pair(&k).unwrap();
+        let s = zkp.solve(&k, &c, &x).unwrap();
+        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_1024_bits_constants() {
+        let zkp = ZKP::new(None).unwrap();
+        let q = &zkp.q;
+
+        let x = ZKP::generate_random_number_below(q).unwrap();
+        let k = ZKP::generate_random_number_below(q).unwrap();
+        let c = ZKP::generate_random_number_below(q).unwrap();
+
+        let (y1, y2) = zkp.compute_pair(&x).unwrap();
+        let (r1, r2) = zkp.compute_pair(&k).unwrap();
+        let s = zkp.solve(&k, &c, &x).unwrap();
+        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_redacted_hides_value() {
+        let secret = Redacted(BigUint::from(123456789u32));
+        assert_eq!(format!("{:?}", secret), "***");
+        assert_eq!(format!("{}", secret), "***");
+        assert_eq!(format!("{:?}", Some(secret.clone())), "Some(***)");
+        assert_eq!(*secret, BigUint::from(123456789u32));
+        assert_eq!(
+            serde_json::to_string(&Redacted("token")).unwrap(),
+            "\"token\""
+        );
+    }
+
+    #[test]
+    fn test_serialization() {
+        let value = BigUint::from(12345u32);
+        let serialized = serialization::serialize_biguint(&value);
+        let deserialized = serialization::deserialize_biguint(&serialized).unwrap();
+        assert_eq!(value, deserialized);
+    }
+
+    #[test]
+    fn test_serialization_hex_and_base64() {
+        let value = BigUint::from(0x01ffu32);
+        assert_eq!(serialization::serialize_biguint_hex(&value), "01ff");
+        assert_eq!(serialization::serialize_biguint_base64(&value), "Af8=");
+
+        // Leading zero bytes are accepted and dropped
+        assert_eq!(
+            serialization::deserialize_biguint_hex("000001ff").unwrap(),
+            value
+        );
+        assert_eq!(
+            serialization::deserialize_biguint_base64("AAAB/w==").unwrap(),
+            value
+        );
+
+        let x = ZKP::generate_random_number_below(&ZKP::new(None).unwrap().q).unwrap();
+        for value in [BigUint::from(0u32), BigUint::from(1u32), x] {
+            let hex = serialization::serialize_biguint_hex(&value);
+            assert_eq!(serialization::deserialize_biguint_hex(&hex).unwrap(), value);
+            let base64 = serialization::serialize_biguint_base64(&value);
+            assert_eq!(
+                serialization::deserialize_biguint_base64(&base64).unwrap(),
+                value
+            );
+        }
+
+        assert!(matches!(
+            serialization::deserialize_biguint_hex(""),
+            Err(ZkpError::EmptyInput)
+        ));
+        for malformed in ["0g", "abc"] {
+            assert!(matches!(
+                serialization::deserialize_biguint_hex(malformed),
+                Err(ZkpError::SerializationError(_))
+            ));
+        }
+        for malformed in ["A$8=", "Af8"] {
+            assert!(matches!(
+                serialization::deserialize_biguint_base64(malformed),
+                Err(ZkpError::SerializationError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_canonical_bytes() {
+        let zkp = ZKP::new(None).unwrap();
+        let width = zkp.p.to_bytes_be().len();
+
+        // Wire encodings that differ only in leading zeros share one canonical form
+        let short = serialization::deserialize_biguint(&[0x01, 0x00]).unwrap();
+        let padded = serialization::deserialize_biguint(&[0x00, 0x00, 0x01, 0x00]).unwrap();
+        assert_eq!(zkp.canonical_bytes(&short), zkp.canonical_bytes(&padded));
+
+        // Distinct values stay distinct at the same width
+        let one = zkp.canonical_bytes(&BigUint::from(1u32));
+        let canonical = zkp.canonical_bytes(&short);
+        assert_ne!(one, canonical);
+        assert_eq!(one.len(), width);
+        assert_eq!(canonical.len(), width);
+        assert_eq!(canonical[width - 2..], [0x01, 0x00]);
+        assert!(canonical[..width - 2].iter().all(|&b| b == 0));
+
+        let top = &zkp.p - 1u32;
+        assert_eq!(zkp.canonical_bytes(&top), top.to_bytes_be());
+        assert_eq!(BigUint::from_bytes_be(&zkp.canonical_bytes(&top)), top);
+    }
+
+    #[test]
+    fn test_cached_constants_match_decoded() {
+        assert_eq!(ZKP::get_constants(), ZKP::decode_constants());
+        assert_eq!(ZKP::get_constants(), ZKP::get_constants());
+    }
+
+    #[test]
+    fn test_predefined_instances_share_constants() {
+        let first = ZKP::new(None).unwrap();
+        let second = ZKP::new(None).unwrap();
+        assert!(first.is_compatible_with(&second));
+        assert_eq!(first.group_checksum(), second.group_checksum());
+
+        let (alpha, beta, p, q) = ZKP::decode_constants();
+        assert_eq!(
+            (&first.alpha, &first.beta, &first.p, &first.q),
+            (&alpha, &beta, &p, &q)
+        );
+        first.validate_parameters().unwrap();
+    }
+
+    #[test]
+    fn test_soundness_error() {
+        let toy = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        assert_eq!(toy.soundness_error(), 1.0 / 11.0);
+        assert_eq!(toy.soundness_error_after_rounds(1), toy.soundness_error());
+        assert_eq!(toy.soundness_error_after_rounds(0), 1.0);
+        assert!((toy.soundness_error_after_rounds(2) - 1.0 / 121.0).abs() < 1e-15);
+        assert!(toy.soundness_error_after_rounds(3) < toy.soundness_error_after_rounds(2));
+
+        let zkp = ZKP::new(None).unwrap();
+        let error = zkp.soundness_error();
+        assert!(error > 0.0 && error < toy.soundness_error());
+        assert!((error.log2() + zkp.q.bits() as f64).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_deterministic_nonce() {
+        let zkp = ZKP::new(None).unwrap();
+        let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
+
+        let k = zkp.deterministic_nonce(&x, b"login");
+        assert_eq!(k, zkp.deterministic_nonce(&x, b"login"));
+        assert!(k >= BigUint::from(1u32) && k < zkp.q);
+        assert_ne!(k, zkp.deterministic_nonce(&x, b"login again"));
+        assert_ne!(k, zkp.deterministic_nonce(&(&x + 1u32), b"login"));
+
+        // A proof built on the derived nonce verifies as usual
+        let proof = zkp.prove_noninteractive(&x, &k).unwrap();
+        let (y1, y2) = zkp.public_key(&x).unwrap();
+        assert!(zkp.verify_noninteractive(&y1, &y2, &proof).unwrap());
+
+        // RFC 6979 A.2.1: DSA, 1024 bits, SHA-256, message "sample" (only q is used)
+        let hex = |s: &str| BigUint::parse_bytes(s.as_bytes(), 16).unwrap();
+        let dsa = ZKP {
+            p: zkp.p.clone(),
+            q: hex("996F967F6C8E388D9E28D01E205FBA957A5698B1"),
+            alpha: zkp.alpha.clone(),
+            beta: zkp.beta.clone(),
         };
+        assert_eq!(
+            dsa.deterministic_nonce(&hex("411602CB19A6CCC34494D79D98EF1E7ED5AF25F7"), b"sample"),
+            hex("519BA0546D0C39202A7D34D7DFA5E760B318BCFB")
+        );
+    }
 
-        let x = BigUint::from(6u32);
-        let k = BigUint::from(7u32);
-        let c = BigUint::from(4u32);
+    #[test]
+    fn test_serialization_endianness() {
+        use serialization::Endianness;
 
-        let (y1, y2) = zkp.compute_pair(&x).unwrap();
-        assert_eq!(y1, BigUint::from(2u32));
-        assert_eq!(y2, BigUint::from(3u32));
+        let value = BigUint::from(0x0102_0304u32);
+        let be = serialization::serialize_biguint(&value);
+        let le = serialization::serialize_biguint_le(&value);
+        assert_eq!(be, [1, 2, 3, 4]);
+        assert_eq!(le, [4, 3, 2, 1]);
+        assert_eq!(
+            serialization::serialize_biguint_with(&value, Endianness::default()),
+            be
+        );
+
+        assert_eq!(serialization::deserialize_biguint(&be).unwrap(), value);
+        assert_eq!(serialization::deserialize_biguint_le(&le).unwrap(), value);
+        let x = ZKP::generate_random_number_below(&ZKP::new(None).unwrap().q).unwrap();
+        for endianness in [Endianness::Big, Endianness::Little] {
+            let bytes = serialization::serialize_biguint_with(&x, endianness);
+            assert_eq!(
+                serialization::deserialize_biguint_with(&bytes, endianness).unwrap(),
+                x
+            );
+        }
+
+        // Reading with the wrong byte order silently yields a different value
+        assert_eq!(
+            serialization::deserialize_biguint_le(&be).unwrap(),
+            BigUint::from(0x0403_0201u32)
+        );
+        assert_ne!(serialization::deserialize_biguint(&le).unwrap(), value);
+        assert!(serialization::deserialize_biguint_le(&[]).is_err());
+    }
+
+    #[test]
+    fn test_deterministic_user() {
+        let zkp = ZKP::new(None).unwrap();
+
+        let (name_a, x_a, y1_a, y2_a) = zkp.deterministic_user(7);
+        let (name_b, x_b, y1_b, y2_b) = zkp.deterministic_user(7);
+        assert_eq!(name_a, name_b);
+        assert_eq!(x_a, x_b);
+        assert_eq!(y1_a, y1_b);
+        assert_eq!(y2_a, y2_b);
+        assert_eq!(
+            zkp.compute_pair(&x_a).unwrap(),
+            (y1_a.clone(), y2_a.clone())
+        );
+
+        let (name_c, x_c, y1_c, y2_c) = zkp.deterministic_user(8);
+        assert_ne!(name_a, name_c);
+        assert_ne!(x_a, x_c);
+        assert_ne!(y1_a, y1_c);
+        assert_ne!(y2_a, y2_c);
+    }
+
+    #[test]
+    fn test_compressed_biguint_roundtrip() {
+        let zkp = ZKP::new(None).unwrap();
+        let compressed = serialization::CompressedBigUint::new(&zkp.p);
+        assert_eq!(compressed.len(), 128);
+        assert_eq!(compressed.to_biguint(), zkp.p);
+
+        let zero = serialization::CompressedBigUint::new(&BigUint::from(0u32));
+        assert_eq!(zero.to_biguint(), BigUint::from(0u32));
+    }
+
+    #[test]
+    fn test_binding_challenge() {
+        let zkp = ZKP::new(None).unwrap();
+        let (_, _, r1, r2) = zkp.deterministic_user(1);
+        let (_, _, other_r1, other_r2) = zkp.deterministic_user(2);
+
+        let c = zkp.binding_challenge(&r1, &r2, "alice", b"secret");
+        assert!(c < zkp.q);
+        assert_eq!(c, zkp.binding_challenge(&r1, &r2, "alice", b"secret"));
+        assert_ne!(
+            c,
+            zkp.binding_challenge(&other_r1, &other_r2, "alice", b"secret")
+        );
+        assert_ne!(c, zkp.binding_challenge(&r1, &r2, "bob", b"secret"));
+        assert_ne!(c, zkp.binding_challenge(&r1, &r2, "alice", b"other"));
+    }
 
+    #[test]
+    fn test_verify_detailed() {
+        let zkp = ZKP::new(None).unwrap();
+        let (_, x, y1, y2) = zkp.deterministic_user(0);
+        let k = BigUint::from(12345u32);
+        let c = BigUint::from(678u32);
         let (r1, r2) = zkp.compute_pair(&k).unwrap();
-        assert_eq!(r1, BigUint::from(8u32));
-        assert_eq!(r2, BigUint::from(4u32));
+        let s = zkp.solve(&k, &c, &x).unwrap();
+
+        let details = zkp.verify_detailed(&r1, &r2, &y1, &y2, &c, &s).unwrap();
+        assert!(details.valid);
+        assert_eq!(details.recomputed_r1, r1);
+        assert_eq!(details.recomputed_r2, r2);
+
+        let wrong_s = (&s + 1u32) % &zkp.q;
+        let details = zkp
+            .verify_detailed(&r1, &r2, &y1, &y2, &c, &wrong_s)
+            .unwrap();
+        assert!(!details.valid);
+        assert_ne!(details.recomputed_r1, r1);
+        assert_ne!(details.recomputed_r2, r2);
+    }
 
+    /// Prove knowledge of the deterministic user 2 secret in `zkp`
+    fn transcript_for(zkp: &ZKP) -> ProofTranscript {
+        let (_, x, y1, y2) = zkp.deterministic_user(2);
+        let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let c = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let (r1, r2) = zkp.compute_pair(&k).unwrap();
         let s = zkp.solve(&k, &c, &x).unwrap();
-        assert_eq!(s, BigUint::from(5u32));
+        ProofTranscript {
+            r1,
+            r2,
+            y1,
+            y2,
+            c,
+            s,
+            ..Default::default()
+        }
+    }
 
-        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap();
-        assert!(result);
+    #[test]
+    fn test_verify_with_group() {
+        let default_group = GroupParams::try_from(ZKP::new(None).unwrap()).unwrap();
+        let toy_group = GroupParams::new(
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            BigUint::from(4u32),
+            BigUint::from(9u32),
+        )
+        .unwrap();
 
-        // fake secret
-        let x_fake = BigUint::from(7u32);
-        let s_fake = zkp.solve(&k, &c, &x_fake).unwrap();
+        let default_proof = transcript_for(default_group.group());
+        let toy_proof = transcript_for(toy_group.group());
 
-        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s_fake).unwrap();
-        assert!(!result);
+        assert!(verify_with_group(&default_group, &default_proof).unwrap());
+        assert!(verify_with_group(&toy_group, &toy_proof).unwrap());
+
+        // A proof only holds in the group it was produced in
+        assert!(!verify_with_group(&default_group, &toy_proof).unwrap());
+        assert!(verify_with_group(&toy_group, &default_proof).is_err());
     }
 
     #[test]
-    fn test_toy_example_with_random_numbers() {
-        let alpha = BigUint::from(4u32);
-        let beta = BigUint::from(9u32);
-        let p = BigUint::from(23u32);
-        let q = BigUint::from(11u32);
+    fn test_proof_transcript_json_roundtrip() {
+        let transcript = transcript_for(&ZKP::new(None).unwrap());
+        let json = transcript.to_json().unwrap();
+        assert!(json.contains(&format!("\"c\": \"{}\"", transcript.c.to_str_radix(16))));
+        assert_eq!(ProofTranscript::from_json(&json).unwrap(), transcript);
+
+        let bad = json.replace(&transcript.s.to_str_radix(16), "not hex");
+        assert!(ProofTranscript::from_json(&bad).is_err());
+    }
+
+    #[test]
+    fn test_group_params_rejects_invalid_group() {
+        let result = GroupParams::new(
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            BigUint::from(1u32),
+            BigUint::from(9u32),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_verify_against_commitment() {
+        let zkp = ZKP::new(None).unwrap();
+        let proof = transcript_for(&zkp);
+        let digest = ZKP::commitment_digest(&proof.y1, &proof.y2);
+
+        assert!(zkp
+            .verify_against_commitment(
+                &digest, &proof.r1, &proof.r2, &proof.c, &proof.s, &proof.y1, &proof.y2
+            )
+            .unwrap());
+
+        // y1 = p would fail range validation in verify, so an error about the
+        // digest shows the hash is checked first
+        let err = zkp
+            .verify_against_commitment(
+                &digest, &proof.r1, &proof.r2, &proof.c, &proof.s, &zkp.p, &proof.y2,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("committed digest"));
+    }
+
+    #[test]
+    fn test_security_level_bits() {
+        // 1024-bit p with a 160-bit subgroup: bounded by rho on q
+        let zkp = ZKP::new(None).unwrap();
+        assert_eq!(zkp.security_level_bits(), 80);
+
+        // 2048-bit p with a 256-bit subgroup: bounded by the NFS on p
+        let one = BigUint::from(1u32);
+        let large = ZKP {
+            p: (&one << 2047) + 1u32,
+            q: (&one << 255) + 1u32,
+            alpha: BigUint::from(2u32),
+            beta: BigUint::from(3u32),
+        };
+        let bits = large.security_level_bits();
+        assert!((110..=120).contains(&bits), "got {} bits", bits);
+        assert!(bits > zkp.security_level_bits());
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let zkp = ZKP::new(None).unwrap();
+        let mut proofs: Vec<_> = (0..4)
+            .map(|_| {
+                let t = transcript_for(&zkp);
+                (t.r1, t.r2, t.y1, t.y2, t.c, t.s)
+            })
+            .collect();
+        assert_eq!(zkp.verify_batch(&proofs).unwrap(), vec![true; 4]);
+        assert!(zkp.verify_batch(&[]).unwrap().is_empty());
+
+        proofs[1].5 = (&proofs[1].5 + 1u32) % &zkp.q;
+        proofs[3].0 = zkp.p.clone();
+        assert_eq!(
+            zkp.verify_batch(&proofs).unwrap(),
+            vec![true, false, true, false]
+        );
+
+        // A negated commitment fails `verify`, so it must fail the batch on every run
+        let mut proofs: Vec<_> = (0..4)
+            .map(|_| {
+                let t = transcript_for(&zkp);
+                (t.r1, t.r2, t.y1, t.y2, t.c, t.s)
+            })
+            .collect();
+        proofs[2].0 = &zkp.p - &proofs[2].0;
+        let (r1, r2, y1, y2, c, s) = &proofs[2];
+        assert!(!zkp.verify(r1, r2, y1, y2, c, s).unwrap());
+        for _ in 0..32 {
+            assert_eq!(
+                zkp.verify_batch(&proofs).unwrap(),
+                vec![true, true, false, true]
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_transcripts() {
+        let zkp = ZKP::new(None).unwrap();
+        let mut transcripts: Vec<ProofTranscript> = (0..4).map(|_| transcript_for(&zkp)).collect();
+        assert_eq!(zkp.verify_transcripts(&transcripts), vec![true; 4]);
+        assert!(zkp.verify_transcripts(&[]).is_empty());
+
+        transcripts[2].c = (&transcripts[2].c + 1u32) % &zkp.q;
+        assert_eq!(
+            zkp.verify_transcripts(&transcripts),
+            vec![true, true, false, true]
+        );
+
+        // -r1 is off the subgroup but matches r1 under any even weight
+        transcripts[2] = transcript_for(&zkp);
+        transcripts[0].r1 = &zkp.p - &transcripts[0].r1;
+        assert!(!zkp
+            .verify(
+                &transcripts[0].r1,
+                &transcripts[0].r2,
+                &transcripts[0].y1,
+                &transcripts[0].y2,
+                &transcripts[0].c,
+                &transcripts[0].s
+            )
+            .unwrap());
+        for _ in 0..32 {
+            assert_eq!(
+                zkp.verify_transcripts(&transcripts),
+                vec![false, true, true, true]
+            );
+        }
+    }
+
+    #[test]
+    fn test_negotiate_protocol_version() {
+        assert_eq!(negotiate_protocol_version(0).unwrap(), 1);
+        assert_eq!(negotiate_protocol_version(PROTOCOL_VERSION).unwrap(), 1);
+
+        let err = negotiate_protocol_version(99).unwrap_err();
+        assert!(err.to_string().contains("supported versions: 1"));
+    }
+
+    /// Chi-squared statistic of `samples` against a uniform distribution on `[0, q)`
+    fn chi_squared(samples: &[BigUint], q: u32) -> f64 {
+        let mut counts = vec![0u32; q as usize];
+        for sample in samples {
+            counts[sample.to_u32_digits().first().copied().unwrap_or(0) as usize] += 1;
+        }
+        let expected = samples.len() as f64 / q as f64;
+        counts
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    #[test]
+    fn test_hash_to_scalar_is_uniform() {
+        use sha2::{Digest, Sha256};
+
+        // 256 is not a multiple of 171, so reducing a single byte favours
+        // the low residues; the wide reduction should not
+        let q = 171u32;
         let zkp = ZKP {
-            p: p.clone(),
-            q: q.clone(),
-            alpha: alpha.clone(),
-            beta: beta.clone(),
+            p: BigUint::from(2u32 * q + 1),
+            q: BigUint::from(q),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
         };
+        let inputs: Vec<[u8; 4]> = (0..q * 200).map(|i| i.to_be_bytes()).collect();
 
-        let x = BigUint::from(6u32);
-        let k = ZKP::generate_random_number_below(&q).unwrap();
-        let c = ZKP::generate_random_number_below(&q).unwrap();
+        let wide: Vec<BigUint> = inputs.iter().map(|i| zkp.hash_to_scalar(i)).collect();
+        let naive: Vec<BigUint> = inputs
+            .iter()
+            .map(|i| BigUint::from(Sha256::digest(i)[0]) % q)
+            .collect();
+
+        // 170 degrees of freedom: mean 170, and 250 is far in the tail
+        assert!(wide.iter().all(|x| x < &zkp.q));
+        let wide_chi = chi_squared(&wide, q);
+        let naive_chi = chi_squared(&naive, q);
+        assert!(wide_chi < 250.0, "wide reduction chi^2 = {}", wide_chi);
+        assert!(naive_chi > 1000.0, "naive reduction chi^2 = {}", naive_chi);
+    }
+
+    #[test]
+    fn test_hash_password() {
+        let zkp = ZKP::new(None).unwrap();
+        let toy = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+
+        for group in [&zkp, &toy] {
+            for salt in [None, Some(&b"alice"[..])] {
+                for password in [&b""[..], b"hunter2", &[0xffu8; 64]] {
+                    let secret = group.hash_password(password, salt);
+                    assert!(secret < group.q);
+                    assert_eq!(secret, group.hash_password(password, salt));
+                }
+            }
+        }
+
+        assert_ne!(
+            zkp.hash_password(b"hunter2", Some(b"alice")),
+            zkp.hash_password(b"hunter2", Some(b"bob"))
+        );
+        assert_ne!(
+            zkp.hash_password(b"hunter2", Some(b"")),
+            zkp.hash_password(b"hunter2", None)
+        );
+    }
 
+    #[test]
+    fn test_generate_validated_small_group() {
+        let zkp = ZKP::generate_validated(64, 5).unwrap();
+        assert_eq!(zkp.p.bits(), 64);
+        assert_eq!(zkp.q.bits(), 32);
+        zkp.validate_parameters_strict().unwrap();
+
+        let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let c = ZKP::generate_random_number_below(&zkp.q).unwrap();
         let (y1, y2) = zkp.compute_pair(&x).unwrap();
-        assert_eq!(y1, BigUint::from(2u32));
-        assert_eq!(y2, BigUint::from(3u32));
+        let (r1, r2) = zkp.compute_pair(&k).unwrap();
+        let s = zkp.solve(&k, &c, &x).unwrap();
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap());
+
+        assert!(ZKP::generate_validated(8, 5).is_err());
+    }
 
+    #[test]
+    fn test_validate_parameters_strict() {
+        ZKP::new(None)
+            .unwrap()
+            .validate_parameters_strict()
+            .unwrap();
+
+        // 4 and 9 generate the order-11 subgroup mod 23; 5 is a primitive root
+        let toy = |q: u32, alpha: u32| ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(q),
+            alpha: BigUint::from(alpha),
+            beta: BigUint::from(9u32),
+        };
+        toy(11, 4).validate_parameters_strict().unwrap();
+        assert!(toy(11, 5).validate_parameters_strict().is_err());
+        assert!(toy(9, 4).validate_parameters_strict().is_err());
+        assert!(toy(7, 4).validate_parameters_strict().is_err());
+    }
+
+    #[test]
+    fn test_standard_groups() {
+        let zkp = ZKP::from_standard_group(StandardGroup::Modp2048).unwrap();
+        assert_eq!(zkp.p.bits(), 2048);
+        assert_eq!(zkp.q.bits(), 2047);
+
+        let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let c = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let (y1, y2) = zkp.compute_pair(&x).unwrap();
         let (r1, r2) = zkp.compute_pair(&k).unwrap();
         let s = zkp.solve(&k, &c, &x).unwrap();
-        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap();
-        assert!(result);
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap());
+        let wrong_s = (&s + 1u32) % &zkp.q;
+        assert!(!zkp.verify(&r1, &r2, &y1, &y2, &c, &wrong_s).unwrap());
+
+        let predefined = ZKP::from_standard_group(StandardGroup::Modp1024).unwrap();
+        assert!(predefined.is_compatible_with(&ZKP::new(None).unwrap()));
+        for (group, bits) in [
+            (StandardGroup::Modp3072, 3072),
+            (StandardGroup::Modp4096, 4096),
+        ] {
+            assert_eq!(ZKP::from_standard_group(group).unwrap().p.bits(), bits);
+        }
     }
 
     #[test]
-    fn test_1024_bits_constants() {
+    fn test_structured_errors() {
         let zkp = ZKP::new(None).unwrap();
-        let q = &zkp.q;
+        let one = BigUint::from(1u32);
+        let q = zkp.q.clone();
+        let p = zkp.p.clone();
 
-        let x = ZKP::generate_random_number_below(q).unwrap();
-        let k = ZKP::generate_random_number_below(q).unwrap();
-        let c = ZKP::generate_random_number_below(q).unwrap();
+        assert!(matches!(
+            zkp.compute_pair(&q),
+            Err(ZkpError::ExponentTooLarge)
+        ));
+        assert!(matches!(
+            zkp.solve(&one, &q, &one),
+            Err(ZkpError::ScalarOutOfRange)
+        ));
+        assert!(matches!(
+            zkp.verify(&one, &one, &one, &one, &q, &one),
+            Err(ZkpError::ChallengeOutOfRange)
+        ));
+        assert!(matches!(
+            zkp.verify(&p, &one, &one, &one, &one, &one),
+            Err(ZkpError::CommitmentOutOfRange)
+        ));
+        assert!(matches!(
+            serialization::deserialize_biguint(&[]),
+            Err(ZkpError::EmptyInput)
+        ));
+
+        let group = |p: u32, q: u32, alpha: u32, beta: u32| {
+            ZKP {
+                p: BigUint::from(p),
+                q: BigUint::from(q),
+                alpha: BigUint::from(alpha),
+                beta: BigUint::from(beta),
+            }
+            .validate_parameters()
+        };
+        assert!(matches!(
+            group(23, 1, 4, 9),
+            Err(ZkpError::ModulusOutOfRange { .. })
+        ));
+        assert!(matches!(
+            group(23, 11, 1, 9),
+            Err(ZkpError::GeneratorOutOfRange { .. })
+        ));
+        assert!(matches!(
+            group(23, 11, 4, 23),
+            Err(ZkpError::GeneratorOutOfRange { .. })
+        ));
+        assert!(group(23, 11, 4, 9).is_ok());
+    }
+
+    #[test]
+    fn test_oversized_group_rejected_at_construction() {
+        let huge = (BigUint::from(1u32) << 100_000u32) + 1u32;
+        let err =
+            ZKP::from_parameters(huge.clone(), huge, BigUint::from(2u32), BigUint::from(9u32))
+                .unwrap_err();
+        assert!(matches!(
+            err,
+            ZkpError::GroupTooLarge {
+                max_bits: DEFAULT_MAX_GROUP_BITS,
+                ..
+            }
+        ));
+        assert!(err.to_string().contains("8192-bit limit"));
+
+        let zkp = ZKP::from_standard_group(StandardGroup::Modp2048).unwrap();
+        assert!(zkp.validate_parameters_with_max_bits(1024).is_err());
+        zkp.validate_parameters_with_max_bits(2048).unwrap();
+    }
+
+    #[test]
+    fn test_from_parameters() {
+        let toy = ZKP::from_parameters(
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            BigUint::from(4u32),
+            BigUint::from(9u32),
+        )
+        .unwrap();
+        assert_eq!(toy.alpha, BigUint::from(4u32));
+
+        let (alpha, beta, p, q) = ZKP::get_constants();
+        let zkp = ZKP::from_parameters(p, q, alpha, beta).unwrap();
+        assert!(zkp.is_compatible_with(&ZKP::new(None).unwrap()));
+
+        // 5 is a primitive root mod 23, so its order is 22 rather than 11
+        let err = ZKP::from_parameters(
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            BigUint::from(5u32),
+            BigUint::from(9u32),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ZkpError::GeneratorOrder { .. }));
+        assert!(err.to_string().contains("Generators must have order q"));
+    }
+
+    #[test]
+    fn test_validate_parameters_rejects_equal_generators() {
+        ZKP::new(None).unwrap().validate_parameters().unwrap();
+
+        let mut zkp = ZKP::new(None).unwrap();
+        zkp.beta = zkp.alpha.clone();
+        let err = zkp.validate_parameters().unwrap_err();
+        assert!(matches!(err, ZkpError::GeneratorsNotDistinct { .. }));
+        assert!(err.to_string().contains("Generators must be distinct"));
+
+        // 5 is a primitive root mod 23, outside the order-11 subgroup of 9
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(5u32),
+            beta: BigUint::from(9u32),
+        };
+        assert!(zkp.validate_parameters().is_err());
+    }
+
+    #[test]
+    fn test_commitment_challenge() {
+        let zkp = ZKP::new(None).unwrap();
+        let (_, _, y1, y2) = zkp.deterministic_user(1);
+        let (_, _, other_y1, other_y2) = zkp.deterministic_user(2);
+        let nonce = BigUint::from(42u32);
+
+        let c = zkp.commitment_challenge(&nonce, &y1, &y2);
+        assert!(c < zkp.q);
+        assert_eq!(c, zkp.commitment_challenge(&nonce, &y1, &y2));
+        assert_ne!(c, zkp.commitment_challenge(&nonce, &other_y1, &other_y2));
+        assert_ne!(c, zkp.commitment_challenge(&BigUint::from(43u32), &y1, &y2));
+    }
+
+    #[test]
+    fn test_constants_checksum() {
+        assert_eq!(
+            hex::encode(ZKP::constants_checksum()),
+            "81deb649c5725f58b3e03c241cfa29ee235f6b3c8e8c6fd1105f516b607c7037"
+        );
+        assert_eq!(
+            hex::encode(ZKP::new(None).unwrap().legacy_group_checksum()),
+            "a50de10868006c88dd6027e4cf3be214e8b31d85f703ec24a4a96ddbefba3258"
+        );
+        assert_eq!(ZKP::constants_checksum(), PREDEFINED_CONSTANTS_CHECKSUM);
+        assert!(ZKP::verify_constants_integrity().is_ok());
+
+        let mut zkp = ZKP::new(None).unwrap();
+        zkp.beta += 1u32;
+        assert_ne!(zkp.group_checksum(), PREDEFINED_CONSTANTS_CHECKSUM);
+    }
+
+    #[test]
+    fn test_check_pinned_checksum() {
+        let zkp = ZKP::new(None).unwrap();
+        let checksum = hex::encode(PREDEFINED_CONSTANTS_CHECKSUM);
+
+        assert!(zkp.check_pinned_checksum(None).is_ok());
+        assert!(zkp.check_pinned_checksum(Some("")).is_ok());
+        assert!(zkp.check_pinned_checksum(Some(&checksum)).is_ok());
+        assert!(zkp
+            .check_pinned_checksum(Some(&checksum.to_uppercase()))
+            .is_ok());
+
+        let mismatched = "00".repeat(32);
+        let err = zkp.check_pinned_checksum(Some(&mismatched)).unwrap_err();
+        assert!(err.to_string().contains("Group checksum mismatch"));
+    }
+
+    #[test]
+    fn test_verify_rejects_identity_public_values() {
+        let zkp = ZKP::new(None).unwrap();
+        let one = BigUint::from(1u32);
 
+        // With y1 = y2 = 1 the proof for k = s would hold for any challenge
+        let s = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let c = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let (r1, r2) = zkp.compute_pair(&s).unwrap();
+        assert!(matches!(
+            zkp.verify(&r1, &r2, &one, &one, &c, &s),
+            Err(ZkpError::IdentityPublicValue)
+        ));
+        assert!(matches!(
+            zkp.verify_ct(&r1, &r2, &one, &one, &c, &s),
+            Err(ZkpError::IdentityPublicValue)
+        ));
+
+        let t = transcript_for(&zkp);
+        assert!(zkp.verify(&t.r1, &t.r2, &one, &t.y2, &t.c, &t.s).is_err());
+        assert!(zkp.verify(&t.r1, &t.r2, &t.y1, &one, &t.c, &t.s).is_err());
+        assert!(zkp.verify(&t.r1, &t.r2, &t.y1, &t.y2, &t.c, &t.s).unwrap());
+        assert_eq!(
+            zkp.verify_batch(&[(r1, r2, one.clone(), one, c, s)])
+                .unwrap(),
+            vec![false]
+        );
+    }
+
+    #[test]
+    fn test_argon2_proof_of_work_solves_and_verifies() {
+        let algorithm: pow::Algorithm = "argon2:16".parse().unwrap();
+        assert_eq!(algorithm, pow::Algorithm::Argon2 { memory_kib: 16 });
+        assert_eq!(algorithm.to_string(), "argon2:16");
+        assert!("argon2:4".parse::<pow::Algorithm>().is_err());
+        assert!("balloon:16".parse::<pow::Algorithm>().is_err());
+
+        let context = pow::context("alice", &BigUint::from(2u32), &BigUint::from(3u32), b"seed");
+        let nonce = algorithm.solve(&context, 4).unwrap();
+        assert!(algorithm.is_valid(&context, nonce, 4));
+        assert!((0..nonce).all(|earlier| !algorithm.is_valid(&context, earlier, 4)));
+
+        // Shorter than Argon2's minimum salt
+        assert!(algorithm.solve(b"short", 4).is_err());
+        assert!(!algorithm.is_valid(b"short", nonce, 0));
+    }
+
+    #[test]
+    fn test_generated_safe_prime_parameters_round_trip() {
+        let zkp = ZKP::generate_parameters(256).unwrap();
+        assert_eq!(zkp.p.bits(), 256);
+        assert_eq!(zkp.p, &zkp.q * 2u32 + 1u32);
+        zkp.validate_parameters_strict().unwrap();
+
+        let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let c = ZKP::generate_random_number_below(&zkp.q).unwrap();
         let (y1, y2) = zkp.compute_pair(&x).unwrap();
         let (r1, r2) = zkp.compute_pair(&k).unwrap();
         let s = zkp.solve(&k, &c, &x).unwrap();
-        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap();
-        assert!(result);
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s).unwrap());
+
+        // ZkpConfig::key_size_bits is honored once predefined constants are off
+        let config = ZkpConfig {
+            key_size_bits: 64,
+            use_predefined_constants: false,
+        };
+        assert_eq!(ZKP::new(Some(config)).unwrap().p.bits(), 64);
+        assert!(ZKP::generate_parameters(8).is_err());
     }
 
     #[test]
-    fn test_serialization() {
-        let value = BigUint::from(12345u32);
-        let serialized = serialization::serialize_biguint(&value);
-        let deserialized = serialization::deserialize_biguint(&serialized).unwrap();
-        assert_eq!(value, deserialized);
+    fn test_verify_ct_matches_verify() {
+        let zkp = ZKP::new(None).unwrap();
+        let x = BigUint::from(300u32);
+        let k = BigUint::from(10u32);
+        let c = BigUint::from(4u32);
+        let (y1, y2) = zkp.compute_pair(&x).unwrap();
+        let (r1, r2) = zkp.compute_pair(&k).unwrap();
+        let s = zkp.solve(&k, &c, &x).unwrap();
+        let bad = &r1 + 1u32;
+
+        for (r1, r2, expected) in [
+            (&r1, &r2, true),
+            (&bad, &r2, false),
+            (&r1, &bad, false),
+            (&bad, &bad, false),
+        ] {
+            let valid = zkp.verify_ct(r1, r2, &y1, &y2, &c, &s).unwrap();
+            assert_eq!(valid, expected);
+            assert_eq!(valid, zkp.verify(r1, r2, &y1, &y2, &c, &s).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_weak_challenges() {
+        let zkp = ZKP::new(None).unwrap();
+        assert!(zkp.is_weak_challenge(&BigUint::from(0u32)));
+        assert!(zkp.is_weak_challenge(&BigUint::from(1u32)));
+        assert!(!zkp.is_weak_challenge(&BigUint::from(2u32)));
+        assert!(!zkp.is_weak_challenge(&(&zkp.q - 1u32)));
+
+        // A composite q makes its factors weak
+        let composite = ZKP {
+            q: BigUint::from(15u32),
+            ..zkp
+        };
+        assert!(composite.is_weak_challenge(&BigUint::from(3u32)));
+        assert!(!composite.is_weak_challenge(&BigUint::from(4u32)));
+    }
+
+    #[test]
+    fn test_solve_stays_below_q() {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+
+        // c * x - k is a multiple of q, which used to yield s = q
+        let s = zkp
+            .solve(
+                &BigUint::from(1u32),
+                &BigUint::from(4u32),
+                &BigUint::from(3u32),
+            )
+            .unwrap();
+        assert_eq!(s, BigUint::from(0u32));
+
+        for k in 0..11u32 {
+            for c in 0..11u32 {
+                for x in 0..11u32 {
+                    let (k, c, x) = (BigUint::from(k), BigUint::from(c), BigUint::from(x));
+                    let s = zkp.solve(&k, &c, &x).unwrap();
+                    assert!(s < zkp.q);
+                    assert_eq!((&s + &c * &x) % &zkp.q, k);
+                }
+            }
+        }
     }
 
     #[test]
@@ -347,4 +4665,33 @@ mod test {
         // Test zero bound for random generation
         assert!(ZKP::generate_random_number_below(&BigUint::from(0u32)).is_err());
     }
+
+    /// Tests of the gRPC client helpers, which need the `grpc` feature
+    #[cfg(feature = "grpc")]
+    mod client_helpers {
+        use super::*;
+
+        #[test]
+        fn test_password_to_biguint_matches_unsalted_hash() {
+            let zkp = ZKP::new(None).unwrap();
+            let secret = client::password_to_biguint("hunter2", &zkp);
+
+            // SHA-512 is the mapping secrets were registered with before the
+            // choice, and unsalted stays compatible with those registrations
+            assert_eq!(
+                secret,
+                zkp.hash_password_with(PasswordHash::Sha512, b"hunter2", None)
+            );
+            assert_eq!(secret, zkp.hash_password(b"hunter2", None));
+        }
+
+        #[test]
+        fn test_commitment_debug_redacts_nonce() {
+            let zkp = ZKP::new(None).unwrap();
+            let commitment = client::Commitment::generate(&zkp).unwrap();
+            let debug = format!("{:?}", commitment);
+            assert!(debug.contains("k: ***"));
+            assert!(!debug.contains(&commitment.k.to_string()));
+        }
+    }
 }
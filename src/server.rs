@@ -1,38 +1,202 @@
 use std::net::SocketAddr;
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::path::{Path, PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock as StdRwLock,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
+use clap::{Parser, Subcommand};
 use config::{Config, ConfigError, Environment, File};
+use futures::future::BoxFuture;
+use indexmap::IndexMap;
 use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
-use tonic::{transport::Server, Request, Response, Status};
-use tower::ServiceBuilder;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::{oneshot, RwLock},
+};
+#[cfg(unix)]
+use tokio_stream::wrappers::UnixListenerStream;
+use tokio_stream::{Stream, StreamExt};
+#[cfg(unix)]
+use tonic::transport::server::UdsConnectInfo;
+use tonic::{
+    codegen::http,
+    metadata::{MetadataMap, MetadataValue},
+    transport::{
+        server::{Connected, TcpConnectInfo, TcpIncoming},
+        Identity, Server, ServerTlsConfig,
+    },
+    Code, Request, Response, Status,
+};
+use tower::{Layer, Service, ServiceBuilder};
 use tower_http::{cors::CorsLayer, timeout::TimeoutLayer, trace::TraceLayer};
 use tracing::{error, info, instrument, warn};
 use uuid::Uuid;
 
-use zkp::{serialization, ZkpResult, ZKP};
+#[cfg(feature = "test-determinism")]
+use num_bigint::RandBigInt;
+#[cfg(feature = "test-determinism")]
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-pub mod zkp_auth {
-    include!("./zkp_auth.rs");
-}
+use zkp::{
+    negotiate_protocol_version, pow, prevalidate_value, serialization,
+    serialization::CompressedBigUint, PasswordHash, ProofTranscript, Redacted, ZkpError, ZkpResult,
+    DEFAULT_MAX_GROUP_BITS, PINNED_GROUP_CHECKSUM, SUPPORTED_PROTOCOL_VERSIONS, ZKP,
+};
+
+pub use zkp::zkp_auth;
 
 use zkp_auth::{
     auth_server::{Auth, AuthServer},
     AuthenticationAnswerRequest, AuthenticationAnswerResponse, AuthenticationChallengeRequest,
-    AuthenticationChallengeResponse, RegisterRequest, RegisterResponse,
+    AuthenticationChallengeResponse, EchoRequest, EchoResponse, PublicCommitmentRequest,
+    PublicCommitmentResponse, RegisterRequest, RegisterResponse, ServerInfoRequest,
+    ServerInfoResponse, UpdateCredentialsRequest, UpdateCredentialsResponse,
+    ValidateSessionRequest, ValidateSessionResponse,
 };
 
 /// Server configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub request_timeout_secs: u64,
     pub max_concurrent_streams: u32,
+    /// Ping HTTP/2 connections that have been quiet this long (disabled if unset)
+    pub http2_keepalive_interval_secs: Option<u64>,
+    /// Drop a connection whose keepalive ping goes unanswered this long
+    pub http2_keepalive_timeout_secs: Option<u64>,
+    /// Close connections that have carried no RPC for this long (disabled if unset)
+    pub max_connection_idle_secs: Option<u64>,
     pub enable_reflection: bool,
     pub log_level: String,
+    /// Shed verification requests when the recent p99 latency exceeds this budget
+    pub verify_p99_budget_ms: Option<u64>,
+    /// Length of the moving window used to measure verification latency
+    pub verify_latency_window_secs: u64,
+    /// Group to fall back to if the predefined constants fail validation
+    pub fallback_group: Option<GroupConfig>,
+    /// Reject any group whose p or q is wider than this many bits
+    pub max_group_bits: u64,
+    /// Derive challenges from the commitments and a server secret instead of at random
+    pub deterministic_challenge_binding: bool,
+    /// Issue challenges in `[1, 2^challenge_bits)` instead of `[0, q)` (full size if unset)
+    ///
+    /// A prover who does not know the secret can still answer a challenge
+    /// it guessed in advance, which succeeds with probability about
+    /// `2^-challenge_bits` per attempt; 128 bits keeps that negligible while
+    /// shrinking `c` and `s` on the wire.
+    pub challenge_bits: Option<u32>,
+    /// Redraw challenges `ZKP::is_weak_challenge` flags instead of only
+    /// logging them (not possible with `deterministic_challenge_binding`)
+    pub regenerate_weak_challenges: bool,
+    /// Scopes granted to newly registered users
+    pub default_scopes: Vec<String>,
+    /// Maximum number of registered users held in memory (unbounded if unset)
+    pub max_users: Option<usize>,
+    /// Serve `GetPublicCommitment`; off by default since it reveals which users exist
+    pub enable_public_commitment_export: bool,
+    /// Rotate to the next group in `rotation_groups` every this many days
+    pub group_rotation_days: Option<u64>,
+    /// Groups to rotate through, in order, once `group_rotation_days` is set
+    pub rotation_groups: Vec<GroupConfig>,
+    /// Log and keep a redacted report of each failed verification for support
+    pub enable_failure_reports: bool,
+    /// Persist registered users, including failed-attempt counters, to this JSON file
+    pub user_store_path: Option<String>,
+    /// Persist issued sessions to this JSON file and restore the live ones on startup
+    pub session_store_path: Option<String>,
+    /// How long a session stays valid after a successful authentication
+    pub session_ttl_secs: u64,
+    /// Remove accounts that never authenticated this long after registering (0 disables)
+    pub unverified_account_ttl_secs: u64,
+    /// Ask clients to rotate a salt this old on their next login (0 disables)
+    pub salt_rotation_age_secs: u64,
+    /// Lock an account after this many consecutive failed verifications
+    pub max_failed_attempts: Option<u32>,
+    /// How long a locked account rejects challenge requests
+    pub lockout_secs: u64,
+    /// Per-user token bucket size shared by challenge and verify requests;
+    /// replaces the one-challenge-per-second check when set
+    pub rate_limit_capacity: Option<u32>,
+    /// Tokens added back to each user's bucket per second
+    pub rate_limit_refill_per_sec: f64,
+    /// Require a proof of work of this many bits before the next challenge
+    /// after a failed verification (disabled if unset)
+    pub pow_base_difficulty: Option<u32>,
+    /// Extra proof-of-work bits required per further consecutive failure
+    pub pow_difficulty_growth: u32,
+    /// Cap on the proof-of-work difficulty
+    pub pow_max_difficulty: u32,
+    /// Puzzle hash: `sha256`, or memory-hard `argon2:<memory_kib>` to blunt
+    /// dedicated hardware (use a difficulty of a few bits with argon2)
+    pub pow_algorithm: pow::Algorithm,
+    /// Serve on this Unix domain socket instead of `host:port`; Unix only
+    pub uds_path: Option<String>,
+    /// Hold every `verify_authentication` response until this long after it
+    /// arrived, so success and failure take the same time (disabled if unset)
+    pub min_response_ms: Option<u64>,
+    /// Serve Prometheus metrics over HTTP on this port of `host` (disabled if unset)
+    pub metrics_port: Option<u16>,
+    /// PEM certificate chain to serve TLS with; requires `tls_key_path`
+    pub tls_cert_path: Option<String>,
+    /// PEM private key for `tls_cert_path`
+    pub tls_key_path: Option<String>,
+    /// Verify answers arriving within this window as one batch (disabled if unset)
+    pub verify_batch_window_ms: Option<u64>,
+    /// Verify a batch as soon as it holds this many answers
+    pub verify_batch_max: usize,
+    /// Allow administrative operations such as exporting the user directory
+    pub enable_admin: bool,
+    /// Keep users in registration order so listings are stable (mainly for tests)
+    pub preserve_registration_order: bool,
+    /// Reject registrations whose `(y1, y2)` already belongs to another username
+    pub reject_duplicate_public_keys: bool,
+    /// Relying party clients must bind their secrets to; registration and
+    /// login requests naming another one are refused (unbound if unset)
+    pub rp_id: Option<String>,
+    /// Serve read-only endpoints from a copy of the user map rebuilt this
+    /// often, so they never wait on the lock (disabled if unset)
+    pub read_snapshot_interval_ms: Option<u64>,
+}
+
+/// Hex-encoded group parameters supplied through configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupConfig {
+    pub p: String,
+    pub q: String,
+    pub alpha: String,
+    pub beta: String,
+}
+
+impl GroupConfig {
+    /// Decode the configured parameters into a ZKP instance
+    pub fn to_zkp(&self) -> ZkpResult<ZKP> {
+        let decode = |name: &str, value: &str| {
+            hex::decode(value)
+                .map(|bytes| BigUint::from_bytes_be(&bytes))
+                .map_err(|e| ZkpError::InvalidInput(format!("Invalid hex for {}: {}", name, e)))
+        };
+
+        Ok(ZKP {
+            p: decode("p", &self.p)?,
+            q: decode("q", &self.q)?,
+            alpha: decode("alpha", &self.alpha)?,
+            beta: decode("beta", &self.beta)?,
+        })
+    }
 }
 
 impl Default for ServerConfig {
@@ -42,8 +206,49 @@ impl Default for ServerConfig {
             port: 50051,
             request_timeout_secs: 30,
             max_concurrent_streams: 100,
+            http2_keepalive_interval_secs: None,
+            http2_keepalive_timeout_secs: None,
+            max_connection_idle_secs: None,
             enable_reflection: false,
             log_level: "info".to_string(),
+            verify_p99_budget_ms: None,
+            verify_latency_window_secs: 10,
+            fallback_group: None,
+            max_group_bits: DEFAULT_MAX_GROUP_BITS,
+            deterministic_challenge_binding: false,
+            challenge_bits: None,
+            regenerate_weak_challenges: true,
+            default_scopes: Vec::new(),
+            max_users: None,
+            enable_public_commitment_export: false,
+            group_rotation_days: None,
+            rotation_groups: Vec::new(),
+            enable_failure_reports: false,
+            user_store_path: None,
+            session_store_path: None,
+            session_ttl_secs: 24 * 60 * 60,
+            unverified_account_ttl_secs: 0,
+            salt_rotation_age_secs: 0,
+            max_failed_attempts: None,
+            lockout_secs: 300,
+            rate_limit_capacity: None,
+            rate_limit_refill_per_sec: 1.0,
+            pow_base_difficulty: None,
+            pow_difficulty_growth: 2,
+            pow_max_difficulty: 24,
+            pow_algorithm: pow::Algorithm::Sha256,
+            uds_path: None,
+            metrics_port: None,
+            min_response_ms: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            verify_batch_window_ms: None,
+            verify_batch_max: 64,
+            enable_admin: false,
+            preserve_registration_order: false,
+            reject_duplicate_public_keys: false,
+            rp_id: None,
+            read_snapshot_interval_ms: None,
         }
     }
 }
@@ -64,68 +269,2090 @@ impl ServerConfig {
         let addr = format!("{}:{}", self.host, self.port);
         Ok(addr.parse()?)
     }
+
+    /// Interval between HTTP/2 keepalive pings
+    pub fn http2_keepalive_interval(&self) -> Option<Duration> {
+        self.http2_keepalive_interval_secs.map(Duration::from_secs)
+    }
+
+    /// How long to wait for a keepalive ping to be acknowledged
+    pub fn http2_keepalive_timeout(&self) -> Option<Duration> {
+        self.http2_keepalive_timeout_secs.map(Duration::from_secs)
+    }
+
+    /// How long a connection may sit without an RPC before it is closed
+    pub fn max_connection_idle(&self) -> Option<Duration> {
+        self.max_connection_idle_secs.map(Duration::from_secs)
+    }
+
+    /// TLS settings for the listener, or `None` to serve plaintext
+    pub fn tls_config(&self) -> Result<Option<ServerTlsConfig>> {
+        let (cert_path, key_path) = match (&self.tls_cert_path, &self.tls_key_path) {
+            (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+            (None, None) => return Ok(None),
+            _ => anyhow::bail!("tls_cert_path and tls_key_path must be set together"),
+        };
+
+        let read = |path: &str| {
+            std::fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))
+        };
+        let identity = Identity::from_pem(read(cert_path)?, read(key_path)?);
+        Ok(Some(ServerTlsConfig::new().identity(identity)))
+    }
+
+    /// Check settings that would only fail, or silently misbehave, at runtime
+    ///
+    /// Every problem found is reported, not just the first.
+    pub fn validate(&self) -> ZkpResult<()> {
+        let mut problems = Vec::new();
+
+        if cfg!(not(unix)) && self.uds_path.is_some() {
+            problems.push("uds_path is only supported on Unix".to_string());
+        }
+        if self.uds_path.is_none() {
+            if let Err(e) = self.socket_addr() {
+                problems.push(format!(
+                    "invalid listen address {}:{}: {}",
+                    self.host, self.port, e
+                ));
+            }
+        }
+        if self.tls_cert_path.is_some() != self.tls_key_path.is_some() {
+            problems.push("tls_cert_path and tls_key_path must be set together".to_string());
+        }
+        if self.request_timeout_secs == 0 {
+            problems.push("request_timeout_secs must be positive".to_string());
+        }
+        if self.max_concurrent_streams == 0 {
+            problems.push("max_concurrent_streams must be positive".to_string());
+        }
+        match (
+            self.http2_keepalive_interval_secs,
+            self.http2_keepalive_timeout_secs,
+        ) {
+            (Some(0), _) => {
+                problems.push("http2_keepalive_interval_secs must be positive".to_string())
+            }
+            (_, Some(0)) => {
+                problems.push("http2_keepalive_timeout_secs must be positive".to_string())
+            }
+            // Timeouts only apply to pings, which are off without an interval
+            (None, Some(_)) => problems.push(
+                "http2_keepalive_timeout_secs requires http2_keepalive_interval_secs".to_string(),
+            ),
+            _ => {}
+        }
+        if self.max_connection_idle_secs == Some(0) {
+            problems.push("max_connection_idle_secs must be positive".to_string());
+        }
+        if self.verify_p99_budget_ms.is_some() && self.verify_latency_window_secs == 0 {
+            problems.push("verify_latency_window_secs must be positive".to_string());
+        }
+        match self.challenge_bits {
+            Some(0) => problems.push("challenge_bits must be positive".to_string()),
+            // Every challenge in [1, 2) is weak, so redrawing never ends
+            Some(1) if self.regenerate_weak_challenges => problems.push(
+                "challenge_bits must be at least 2 with regenerate_weak_challenges".to_string(),
+            ),
+            _ => {}
+        }
+        if self.session_ttl_secs == 0 {
+            problems.push("session_ttl_secs must be positive".to_string());
+        }
+        if self.max_failed_attempts == Some(0) {
+            problems.push("max_failed_attempts must be positive".to_string());
+        }
+        if self
+            .pow_base_difficulty
+            .is_some_and(|base| base > self.pow_max_difficulty)
+        {
+            problems.push("pow_base_difficulty exceeds pow_max_difficulty".to_string());
+        }
+        if self.read_snapshot_interval_ms == Some(0) {
+            problems.push("read_snapshot_interval_ms must be positive".to_string());
+        }
+        if self.rate_limit_capacity == Some(0) {
+            problems.push("rate_limit_capacity must be positive".to_string());
+        }
+        if self.rate_limit_capacity.is_some() && self.rate_limit_refill_per_sec <= 0.0 {
+            problems.push("rate_limit_refill_per_sec must be positive".to_string());
+        }
+        match self.group_rotation_days {
+            Some(0) => problems.push("group_rotation_days must be positive".to_string()),
+            Some(_) if self.rotation_groups.is_empty() => problems.push(
+                "group_rotation_days is set but no rotation_groups are configured".to_string(),
+            ),
+            _ => {}
+        }
+
+        let groups = self
+            .fallback_group
+            .iter()
+            .map(|group| ("fallback_group".to_string(), group))
+            .chain(
+                self.rotation_groups
+                    .iter()
+                    .enumerate()
+                    .map(|(i, group)| (format!("rotation_groups[{}]", i), group)),
+            );
+        for (name, group) in groups {
+            if let Err(e) = group
+                .to_zkp()
+                .and_then(|zkp| zkp.validate_parameters_with_max_bits(self.max_group_bits))
+            {
+                problems.push(format!("{}: {}", name, e));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ZkpError::InvalidInput(problems.join("; ")))
+        }
+    }
 }
 
 /// Enhanced user information with additional metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserInfo {
     // registration
+    pub user_id: String,
     pub user_name: String,
-    pub y1: BigUint,
-    pub y2: BigUint,
+    pub y1: CompressedBigUint,
+    pub y2: CompressedBigUint,
     pub registration_timestamp: chrono::DateTime<chrono::Utc>,
+    pub scopes: Vec<String>,
+    pub group_id: String,
+    pub password_hash: PasswordHash,
+    pub salt: Vec<u8>,
+    pub salt_updated_at: chrono::DateTime<chrono::Utc>,
 
     // authorization
     pub r1: Option<BigUint>,
     pub r2: Option<BigUint>,
+    pub challenge_nonce: Option<Redacted<BigUint>>,
     pub last_challenge_timestamp: Option<chrono::DateTime<chrono::Utc>>,
 
     // verification
-    pub c: Option<BigUint>,
-    pub s: Option<BigUint>,
-    pub session_id: Option<String>,
+    pub c: Option<Redacted<BigUint>>,
+    pub s: Option<Redacted<BigUint>>,
+    /// `session_hash` of the current session id; the id itself is not kept
+    pub session_hash: Option<Redacted<String>>,
+    /// `session_hash` of the one-shot token a login set `rotate_salt` with
+    pub rotation_token_hash: Option<Redacted<String>>,
     pub last_successful_auth: Option<chrono::DateTime<chrono::Utc>>,
     pub failed_attempts: u32,
+    pub locked_until: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl Default for UserInfo {
     fn default() -> Self {
         Self {
+            user_id: String::new(),
             user_name: String::new(),
-            y1: BigUint::from(0u32),
-            y2: BigUint::from(0u32),
+            y1: CompressedBigUint::default(),
+            y2: CompressedBigUint::default(),
             registration_timestamp: chrono::Utc::now(),
+            scopes: Vec::new(),
+            group_id: String::new(),
+            password_hash: PasswordHash::default(),
+            salt: Vec::new(),
+            salt_updated_at: chrono::Utc::now(),
             r1: None,
             r2: None,
+            challenge_nonce: None,
             last_challenge_timestamp: None,
             c: None,
             s: None,
-            session_id: None,
+            session_hash: None,
+            rotation_token_hash: None,
             last_successful_auth: None,
             failed_attempts: 0,
+            locked_until: None,
+        }
+    }
+}
+
+impl UserInfo {
+    /// Whether the account is locked out at `now`
+    pub fn is_locked(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.locked_until.is_some_and(|until| until > now)
+    }
+
+    /// Lift a lock whose cooldown has passed, starting the failure count over
+    ///
+    /// Returns whether a lock was lifted.
+    pub fn clear_expired_lock(&mut self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        match self.locked_until {
+            Some(until) if until <= now => {}
+            _ => return false,
+        }
+        self.locked_until = None;
+        self.failed_attempts = 0;
+        true
+    }
+
+    /// Transcript of the user's last accepted authentication, for audit
+    ///
+    /// `None` until a round has completed. Every value in it is public, so
+    /// it can be stored or shipped off and re-verified later.
+    pub fn proof_transcript(&self) -> Option<ProofTranscript> {
+        Some(ProofTranscript {
+            r1: self.r1.clone()?,
+            r2: self.r2.clone()?,
+            y1: self.y1.to_biguint(),
+            y2: self.y2.to_biguint(),
+            c: self.c.as_ref()?.0.clone(),
+            s: self.s.as_ref()?.0.clone(),
+            challenged_at: self.last_challenge_timestamp,
+            verified_at: self.last_successful_auth,
+        })
+    }
+
+    /// Redacted view of this user's state, safe to attach to a bug report
+    pub fn debug_snapshot(&self) -> UserDebugSnapshot {
+        let now = chrono::Utc::now();
+        UserDebugSnapshot {
+            user_id: self.user_id.clone(),
+            user_name: self.user_name.clone(),
+            group_id: self.group_id.clone(),
+            registration_timestamp: self.registration_timestamp,
+            scopes: self.scopes.clone(),
+            commitment: RedactedValue::of(self.r1.as_ref()),
+            challenge_nonce: RedactedValue::of(self.challenge_nonce.as_ref().map(|n| &n.0)),
+            challenge: RedactedValue::of(self.c.as_ref().map(|c| &c.0)),
+            answer: RedactedValue::of(self.s.as_ref().map(|s| &s.0)),
+            session_count: usize::from(self.session_hash.is_some()),
+            last_challenge_timestamp: self.last_challenge_timestamp,
+            last_successful_auth: self.last_successful_auth,
+            failed_attempts: self.failed_attempts,
+            locked: self.is_locked(now),
+            locked_until: self.locked_until,
+        }
+    }
+}
+
+/// A value reported only by whether it is set and how many bits it has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RedactedValue {
+    pub present: bool,
+    pub bits: u64,
+}
+
+impl RedactedValue {
+    fn of(value: Option<&BigUint>) -> Self {
+        Self {
+            present: value.is_some(),
+            bits: value.map_or(0, BigUint::bits),
+        }
+    }
+}
+
+/// Server-side state of one user with every secret redacted
+///
+/// `commitment` is the pending `r1`, `challenge` the issued `c`, and
+/// `answer` the last accepted `s`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserDebugSnapshot {
+    pub user_id: String,
+    pub user_name: String,
+    pub group_id: String,
+    pub registration_timestamp: chrono::DateTime<chrono::Utc>,
+    pub scopes: Vec<String>,
+    pub commitment: RedactedValue,
+    pub challenge_nonce: RedactedValue,
+    pub challenge: RedactedValue,
+    pub answer: RedactedValue,
+    pub session_count: usize,
+    pub last_challenge_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_successful_auth: Option<chrono::DateTime<chrono::Utc>>,
+    pub failed_attempts: u32,
+    pub locked: bool,
+    pub locked_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Durable part of a user record; in-flight challenge state is not persisted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredUser {
+    pub user_id: String,
+    pub user_name: String,
+    pub y1: CompressedBigUint,
+    pub y2: CompressedBigUint,
+    pub registration_timestamp: chrono::DateTime<chrono::Utc>,
+    pub scopes: Vec<String>,
+    pub group_id: String,
+    #[serde(default)]
+    pub password_hash: PasswordHash,
+    #[serde(default)]
+    pub salt: Vec<u8>,
+    /// Missing for users stored before salts, whose salt dates from registration
+    #[serde(default)]
+    pub salt_updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub failed_attempts: u32,
+    pub locked_until: Option<chrono::DateTime<chrono::Utc>>,
+    /// `None` for users stored before logins were tracked and `Some(None)`
+    /// for users who never authenticated
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub last_successful_auth: Option<Option<chrono::DateTime<chrono::Utc>>>,
+}
+
+/// Wrap a field that is present, even as `null`, in `Some`
+///
+/// Combined with `#[serde(default)]` this tells a missing field apart from
+/// an explicit `null`.
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+impl From<&UserInfo> for StoredUser {
+    fn from(user: &UserInfo) -> Self {
+        Self {
+            user_id: user.user_id.clone(),
+            user_name: user.user_name.clone(),
+            y1: user.y1.clone(),
+            y2: user.y2.clone(),
+            registration_timestamp: user.registration_timestamp,
+            scopes: user.scopes.clone(),
+            group_id: user.group_id.clone(),
+            password_hash: user.password_hash,
+            salt: user.salt.clone(),
+            salt_updated_at: Some(user.salt_updated_at),
+            failed_attempts: user.failed_attempts,
+            locked_until: user.locked_until,
+            last_successful_auth: Some(user.last_successful_auth),
+        }
+    }
+}
+
+impl From<StoredUser> for UserInfo {
+    fn from(user: StoredUser) -> Self {
+        Self {
+            user_id: user.user_id,
+            user_name: user.user_name,
+            y1: user.y1,
+            y2: user.y2,
+            registration_timestamp: user.registration_timestamp,
+            scopes: user.scopes,
+            group_id: user.group_id,
+            password_hash: user.password_hash,
+            salt: user.salt,
+            salt_updated_at: user.salt_updated_at.unwrap_or(user.registration_timestamp),
+            failed_attempts: user.failed_attempts,
+            locked_until: user.locked_until,
+            // Older stores cannot tell a user who never logged in from one who
+            // has, so count registration as a login rather than sweep them
+            last_successful_auth: user
+                .last_successful_auth
+                .unwrap_or(Some(user.registration_timestamp)),
+            ..Default::default()
+        }
+    }
+}
+
+/// Registered users keyed by username
+///
+/// `Hashed` is the default. `Ordered` keeps registration order so listings
+/// such as `export_directory` come out the same way every run, which tests
+/// that register users concurrently depend on.
+#[derive(Debug, Clone)]
+pub enum UserMap {
+    Hashed(HashMap<String, UserInfo>),
+    Ordered(IndexMap<String, UserInfo>),
+}
+
+impl UserMap {
+    pub fn new(preserve_order: bool) -> Self {
+        if preserve_order {
+            Self::Ordered(IndexMap::new())
+        } else {
+            Self::Hashed(HashMap::new())
+        }
+    }
+
+    pub fn get(&self, user_name: &str) -> Option<&UserInfo> {
+        match self {
+            Self::Hashed(users) => users.get(user_name),
+            Self::Ordered(users) => users.get(user_name),
+        }
+    }
+
+    pub fn get_mut(&mut self, user_name: &str) -> Option<&mut UserInfo> {
+        match self {
+            Self::Hashed(users) => users.get_mut(user_name),
+            Self::Ordered(users) => users.get_mut(user_name),
+        }
+    }
+
+    pub fn contains_key(&self, user_name: &str) -> bool {
+        self.get(user_name).is_some()
+    }
+
+    pub fn insert(&mut self, user_name: String, user: UserInfo) -> Option<UserInfo> {
+        match self {
+            Self::Hashed(users) => users.insert(user_name, user),
+            Self::Ordered(users) => users.insert(user_name, user),
+        }
+    }
+
+    /// Remove a user, keeping the remaining order intact
+    pub fn remove(&mut self, user_name: &str) -> Option<UserInfo> {
+        match self {
+            Self::Hashed(users) => users.remove(user_name),
+            Self::Ordered(users) => users.shift_remove(user_name),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Hashed(users) => users.len(),
+            Self::Ordered(users) => users.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Users in map order: registration order for `Ordered`, arbitrary otherwise
+    pub fn values(&self) -> Box<dyn Iterator<Item = &UserInfo> + '_> {
+        match self {
+            Self::Hashed(users) => Box::new(users.values()),
+            Self::Ordered(users) => Box::new(users.values()),
+        }
+    }
+}
+
+impl<Q: AsRef<str> + ?Sized> std::ops::Index<&Q> for UserMap {
+    type Output = UserInfo;
+
+    fn index(&self, user_name: &Q) -> &UserInfo {
+        self.get(user_name.as_ref()).expect("no such user")
+    }
+}
+
+/// JSON file holding every registered user, rewritten on each change
+#[derive(Debug)]
+pub struct UserStore {
+    path: PathBuf,
+}
+
+impl UserStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Load all stored users in saved order; a missing file is an empty store
+    pub fn load(&self) -> ZkpResult<Vec<UserInfo>> {
+        let json = match std::fs::read_to_string(&self.path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(ZkpError::SerializationError(e.to_string())),
+        };
+
+        let users: Vec<StoredUser> =
+            serde_json::from_str(&json).map_err(|e| ZkpError::SerializationError(e.to_string()))?;
+        Ok(users.into_iter().map(UserInfo::from).collect())
+    }
+
+    /// Replace the stored users with `users`
+    pub fn save(&self, users: &UserMap) -> ZkpResult<()> {
+        self.write(&Self::snapshot(users))
+    }
+}
+
+impl SnapshotStore for UserStore {
+    type Entry = StoredUser;
+
+    fn snapshot(users: &UserMap) -> Vec<StoredUser> {
+        users.values().map(StoredUser::from).collect()
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// A session as written to the session store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredSession {
+    pub user_name: String,
+    pub session_hash: String,
+    pub issued_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// JSON file holding the sessions issued to users, rewritten on each login
+#[derive(Debug)]
+pub struct SessionStore {
+    path: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Load all stored sessions; a missing file is an empty store
+    ///
+    /// Entries that fail to parse are logged and skipped, so one bad record
+    /// cannot keep the server from starting.
+    pub fn load(&self) -> ZkpResult<Vec<StoredSession>> {
+        let json = match std::fs::read_to_string(&self.path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(ZkpError::SerializationError(e.to_string())),
+        };
+
+        let entries: Vec<serde_json::Value> =
+            serde_json::from_str(&json).map_err(|e| ZkpError::SerializationError(e.to_string()))?;
+        Ok(entries
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                serde_json::from_value(entry)
+                    .inspect_err(|e| warn!("Skipping corrupt session store entry {}: {}", i, e))
+                    .ok()
+            })
+            .collect())
+    }
+
+    /// Replace the stored sessions with those currently held by `users`
+    pub fn save(&self, users: &UserMap) -> ZkpResult<()> {
+        self.write(&Self::snapshot(users))
+    }
+}
+
+impl SnapshotStore for SessionStore {
+    type Entry = StoredSession;
+
+    fn snapshot(users: &UserMap) -> Vec<StoredSession> {
+        users
+            .values()
+            .filter_map(|user| {
+                Some(StoredSession {
+                    user_name: user.user_name.clone(),
+                    session_hash: user.session_hash.as_ref()?.0.clone(),
+                    issued_at: user.last_successful_auth?,
+                })
+            })
+            .collect()
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// JSON file rewritten from a snapshot of the user map
+pub trait SnapshotStore: Send + Sync + 'static {
+    type Entry: Serialize + Send + Sync + 'static;
+
+    /// The entries to store for `users`
+    fn snapshot(users: &UserMap) -> Vec<Self::Entry>;
+
+    fn path(&self) -> &Path;
+
+    /// Replace the stored entries with `entries`
+    ///
+    /// Writes to a temporary file first so a crash never leaves a torn store.
+    fn write(&self, entries: &[Self::Entry]) -> ZkpResult<()> {
+        let json = serde_json::to_string(entries)
+            .map_err(|e| ZkpError::SerializationError(e.to_string()))?;
+
+        let tmp_path = self.path().with_extension("tmp");
+        std::fs::write(&tmp_path, json)
+            .and_then(|()| std::fs::rename(&tmp_path, self.path()))
+            .map_err(|e| ZkpError::SerializationError(e.to_string()))
+    }
+}
+
+/// Writes store snapshots on the blocking pool
+///
+/// Callers take the snapshot while holding the user lock, which is cheap,
+/// and the file write happens after the lock is released. Writes run one
+/// at a time and each snapshot is numbered, so a write that was overtaken
+/// by a newer snapshot is skipped rather than clobbering it.
+#[derive(Debug)]
+pub struct StoreWriter<S> {
+    store: Arc<S>,
+    queued: AtomicU64,
+    /// Number of the last snapshot written; held for the whole write
+    written: Arc<Mutex<u64>>,
+}
+
+impl<S: SnapshotStore> StoreWriter<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store: Arc::new(store),
+            queued: AtomicU64::new(0),
+            written: Arc::default(),
+        }
+    }
+
+    /// Write a snapshot of `users` in the background
+    ///
+    /// Failures are logged rather than surfaced so a full disk cannot take
+    /// authentication down with it. Await the handle to wait for the write.
+    pub fn persist(&self, users: &UserMap) -> tokio::task::JoinHandle<()> {
+        let entries = S::snapshot(users);
+        let number = self.queued.fetch_add(1, Ordering::SeqCst) + 1;
+        let store = self.store.clone();
+        let written = self.written.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut written = written.lock().unwrap();
+            if *written > number {
+                return;
+            }
+            if let Err(e) = store.write(&entries) {
+                error!("Failed to persist {}: {}", store.path().display(), e);
+            }
+            *written = number;
+        })
+    }
+}
+
+/// Groups known to the server, keyed by group id (hex group checksum)
+#[derive(Debug)]
+pub struct GroupRegistry {
+    active: String,
+    groups: HashMap<String, Arc<ZKP>>,
+}
+
+impl GroupRegistry {
+    fn new(zkp: ZKP) -> Self {
+        let id = group_id(&zkp);
+        Self {
+            active: id.clone(),
+            groups: HashMap::from([(id, Arc::new(zkp))]),
+        }
+    }
+}
+
+/// Identifier under which a group is registered
+pub fn group_id(zkp: &ZKP) -> String {
+    hex::encode(zkp.group_checksum())
+}
+
+/// Map the hash of each user's current session id to the username
+fn session_index(users: &UserMap) -> HashMap<String, String> {
+    users
+        .values()
+        .filter_map(|user| {
+            Some((
+                user.session_hash.as_ref()?.0.clone(),
+                user.user_name.clone(),
+            ))
+        })
+        .collect()
+}
+
+/// Hex SHA-256 of a session id, the form in which sessions are held and stored
+///
+/// A leaked user map or session store then cannot be replayed as sessions.
+pub fn session_hash(session_id: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    hex::encode(Sha256::digest(session_id.as_bytes()))
+}
+
+/// Record of the active group being rotated
+#[derive(Debug, Clone, Serialize)]
+pub struct RotationEvent {
+    pub previous_group_id: String,
+    pub new_group_id: String,
+    pub rotated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Sanitized bundle describing a failed verification, safe to share with support
+///
+/// Numbers are hex-encoded. The user's registered commitment is derived from
+/// their secret, so it only appears as a SHA-256 fingerprint.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureReport {
+    pub auth_id: String,
+    pub user_id: String,
+    pub group_id: String,
+    pub c: String,
+    pub s: String,
+    pub expected_r1: String,
+    pub expected_r2: String,
+    pub recomputed_r1: String,
+    pub recomputed_r2: String,
+    pub commitment_fingerprint: String,
+    pub failed_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Point-in-time view of server counters
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSnapshot {
+    pub registered_users: usize,
+    pub max_users: Option<usize>,
+    /// Time from a challenge being issued to its answer arriving
+    pub challenge_answer_delay: Vec<HistogramBucket>,
+    /// Handling time per gRPC method, recorded by `RpcLatencyLayer`
+    pub rpc_latency: Vec<RpcLatencySummary>,
+}
+
+/// One histogram bucket: observations no larger than `le_ms` (unbounded if `None`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramBucket {
+    pub le_ms: Option<u64>,
+    pub count: u64,
+}
+
+/// Histogram of durations over fixed millisecond buckets
+#[derive(Debug, Default)]
+pub struct DurationHistogram {
+    counts: Mutex<[u64; Self::BUCKET_BOUNDS_MS.len() + 1]>,
+}
+
+impl DurationHistogram {
+    /// Upper bounds of the finite buckets; larger observations go in a final overflow bucket
+    pub const BUCKET_BOUNDS_MS: [u64; 8] = [100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000];
+
+    pub fn record(&self, duration: Duration) {
+        let ms = duration.as_millis();
+        let bucket = Self::BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| ms <= bound as u128)
+            .unwrap_or(Self::BUCKET_BOUNDS_MS.len());
+        self.counts.lock().unwrap()[bucket] += 1;
+    }
+
+    /// Per-bucket (not cumulative) counts
+    pub fn buckets(&self) -> Vec<HistogramBucket> {
+        let counts = *self.counts.lock().unwrap();
+        Self::BUCKET_BOUNDS_MS
+            .iter()
+            .map(|&bound| Some(bound))
+            .chain([None])
+            .zip(counts)
+            .map(|(le_ms, count)| HistogramBucket { le_ms, count })
+            .collect()
+    }
+}
+
+/// Prometheus counters and histograms, scraped through `serve_metrics`
+///
+/// Each instance has its own registry, so several servers in one process
+/// (as in the tests) do not share counts.
+#[derive(Clone)]
+pub struct PrometheusMetrics {
+    registry: prometheus::Registry,
+    pub registrations: prometheus::IntCounter,
+    pub challenges: prometheus::IntCounter,
+    pub auth_success: prometheus::IntCounter,
+    pub auth_failure: prometheus::IntCounter,
+    pub verify_latency: prometheus::Histogram,
+}
+
+impl PrometheusMetrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = prometheus::Registry::new();
+        let counter = |name: &str, help: &str| -> prometheus::Result<prometheus::IntCounter> {
+            let counter = prometheus::IntCounter::new(name, help)?;
+            registry.register(Box::new(counter.clone()))?;
+            Ok(counter)
+        };
+        let registrations = counter("zkp_registrations_total", "Successful registrations")?;
+        let challenges = counter("zkp_challenges_total", "Authentication challenges issued")?;
+        let auth_success = counter("zkp_auth_success_total", "Accepted authentication answers")?;
+        let auth_failure = counter("zkp_auth_failure_total", "Rejected authentication answers")?;
+
+        let verify_latency = prometheus::Histogram::with_opts(prometheus::HistogramOpts::new(
+            "zkp_verify_duration_seconds",
+            "Time spent checking an authentication answer",
+        ))?;
+        registry.register(Box::new(verify_latency.clone()))?;
+
+        Ok(Self {
+            registry,
+            registrations,
+            challenges,
+            auth_success,
+            auth_failure,
+            verify_latency,
+        })
+    }
+
+    /// Every metric in the Prometheus text exposition format
+    pub fn encode(&self) -> String {
+        use prometheus::Encoder;
+
+        let mut buffer = Vec::new();
+        if let Err(e) = prometheus::TextEncoder::new().encode(&self.registry.gather(), &mut buffer)
+        {
+            error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+impl std::fmt::Debug for PrometheusMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrometheusMetrics").finish_non_exhaustive()
+    }
+}
+
+/// Per-key token buckets: each request takes a token, refilled at a steady rate
+#[derive(Debug)]
+pub struct TokenBucketLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Mutex<HashMap<String, (f64, Instant)>>,
+}
+
+impl TokenBucketLimiter {
+    /// Buckets kept before full ones, which carry no state, are dropped
+    const MAX_BUCKETS: usize = 10_000;
+
+    pub fn new(capacity: u32, refill_per_sec: f64) -> Self {
+        Self {
+            capacity: capacity as f64,
+            refill_per_sec,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token from `key`'s bucket, or return `false` if it is empty
+    pub fn try_acquire(&self, key: &str) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= Self::MAX_BUCKETS {
+            buckets.retain(|_, bucket| self.refilled(*bucket, now) < self.capacity);
+        }
+
+        let bucket = buckets
+            .entry(key.to_string())
+            .or_insert((self.capacity, now));
+        let tokens = self.refilled(*bucket, now);
+        if tokens < 1.0 {
+            *bucket = (tokens, now);
+            return false;
+        }
+        *bucket = (tokens - 1.0, now);
+        true
+    }
+
+    fn refilled(&self, (tokens, updated): (f64, Instant), now: Instant) -> f64 {
+        let elapsed = now.duration_since(updated).as_secs_f64();
+        (tokens + elapsed * self.refill_per_sec).min(self.capacity)
+    }
+}
+
+/// Moving-window tracker of recent verification latencies used for load shedding
+#[derive(Debug)]
+pub struct LatencyTracker {
+    samples: Mutex<VecDeque<(Instant, Duration)>>,
+    window: Duration,
+    budget: Option<Duration>,
+}
+
+impl LatencyTracker {
+    /// Minimum number of samples in the window before shedding kicks in
+    pub const MIN_SAMPLES: usize = 20;
+
+    /// Upper bound on retained samples, regardless of the window length
+    const MAX_SAMPLES: usize = 10_000;
+
+    pub fn new(window: Duration, budget: Option<Duration>) -> Self {
+        Self {
+            samples: Mutex::new(VecDeque::new()),
+            window,
+            budget,
+        }
+    }
+
+    /// Record the latency of a completed verification
+    pub fn record(&self, latency: Duration) {
+        let now = Instant::now();
+        let mut samples = self.samples.lock().unwrap();
+        Self::evict(&mut samples, now, self.window);
+        if samples.len() == Self::MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back((now, latency));
+    }
+
+    /// 99th percentile latency over the current window
+    pub fn p99(&self) -> Option<Duration> {
+        let mut samples = self.samples.lock().unwrap();
+        Self::evict(&mut samples, Instant::now(), self.window);
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut latencies: Vec<Duration> = samples.iter().map(|(_, latency)| *latency).collect();
+        latencies.sort_unstable();
+        let rank = (latencies.len() * 99).div_ceil(100);
+        Some(latencies[rank - 1])
+    }
+
+    /// Whether new verifications should be rejected to protect the server
+    pub fn should_shed(&self) -> bool {
+        let Some(budget) = self.budget else {
+            return false;
+        };
+
+        if self.samples.lock().unwrap().len() < Self::MIN_SAMPLES {
+            return false;
+        }
+
+        self.p99().is_some_and(|p99| p99 > budget)
+    }
+
+    fn evict(samples: &mut VecDeque<(Instant, Duration)>, now: Instant, window: Duration) {
+        while let Some((recorded_at, _)) = samples.front() {
+            if now.duration_since(*recorded_at) <= window {
+                break;
+            }
+            samples.pop_front();
+        }
+    }
+}
+
+/// Latency percentiles of one gRPC method
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RpcLatencySummary {
+    pub method: String,
+    /// Calls recorded since startup
+    pub count: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+}
+
+/// Recent handling times per gRPC method
+///
+/// Keeps the latest `MAX_SAMPLES` calls of each method, so percentiles
+/// follow current behaviour rather than the whole uptime.
+#[derive(Debug, Default)]
+pub struct RpcLatency {
+    methods: Mutex<BTreeMap<String, (u64, VecDeque<Duration>)>>,
+}
+
+impl RpcLatency {
+    /// Upper bound on retained samples per method
+    const MAX_SAMPLES: usize = 1_024;
+
+    pub fn record(&self, method: &str, latency: Duration) {
+        let mut methods = self.methods.lock().unwrap();
+        let (count, samples) = methods.entry(method.to_string()).or_default();
+        *count += 1;
+        if samples.len() == Self::MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+
+    /// Percentiles of every method seen so far, ordered by method name
+    pub fn summaries(&self) -> Vec<RpcLatencySummary> {
+        let methods = self.methods.lock().unwrap();
+        methods
+            .iter()
+            .map(|(method, (count, samples))| {
+                let mut latencies: Vec<Duration> = samples.iter().copied().collect();
+                latencies.sort_unstable();
+                let percentile = |pct: usize| {
+                    let rank = (latencies.len() * pct).div_ceil(100).max(1);
+                    latencies[rank - 1].as_micros() as u64
+                };
+                RpcLatencySummary {
+                    method: method.clone(),
+                    count: *count,
+                    p50_us: percentile(50),
+                    p95_us: percentile(95),
+                    p99_us: percentile(99),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Tower layer timing every gRPC call by method name into an `RpcLatency`
+#[derive(Debug, Clone)]
+pub struct RpcLatencyLayer {
+    latency: Arc<RpcLatency>,
+}
+
+impl RpcLatencyLayer {
+    pub fn new(latency: Arc<RpcLatency>) -> Self {
+        Self { latency }
+    }
+}
+
+impl<S> Layer<S> for RpcLatencyLayer {
+    type Service = RpcLatencyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcLatencyService {
+            inner,
+            latency: self.latency.clone(),
+        }
+    }
+}
+
+/// Service produced by `RpcLatencyLayer`
+#[derive(Debug, Clone)]
+pub struct RpcLatencyService<S> {
+    inner: S,
+    latency: Arc<RpcLatency>,
+}
+
+impl<S, B> Service<http::Request<B>> for RpcLatencyService<S>
+where
+    S: Service<http::Request<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        // gRPC paths look like `/zkp_auth.Auth/Register`
+        let method = request
+            .uri()
+            .path()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let latency = self.latency.clone();
+        let start = Instant::now();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            latency.record(&method, start.elapsed());
+            response
+        })
+    }
+}
+
+/// RPC activity on one connection, shared with the requests it carries
+#[derive(Debug, Clone)]
+pub struct ConnectionActivity {
+    in_flight: Arc<AtomicUsize>,
+    last_active: Arc<Mutex<Instant>>,
+}
+
+impl ConnectionActivity {
+    fn new() -> Self {
+        Self {
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            last_active: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    fn begin(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn end(&self) {
+        *self.last_active.lock().unwrap() = Instant::now();
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Time since the last RPC finished, or `None` while one is in flight
+    fn idle_for(&self) -> Option<Duration> {
+        if self.in_flight.load(Ordering::SeqCst) > 0 {
+            return None;
+        }
+        Some(self.last_active.lock().unwrap().elapsed())
+    }
+}
+
+/// Connection that reports end-of-stream once it has carried no RPC for `max_idle`
+///
+/// Keepalive pings do not count as activity, so an idle client that keeps
+/// answering them is still closed.
+pub struct IdleConnection<T> {
+    io: T,
+    activity: ConnectionActivity,
+    max_idle: Option<Duration>,
+    timer: Pin<Box<tokio::time::Sleep>>,
+}
+
+impl<T> IdleConnection<T> {
+    pub fn new(io: T, max_idle: Option<Duration>) -> Self {
+        let deadline = tokio::time::Instant::now() + max_idle.unwrap_or_default();
+        Self {
+            io,
+            activity: ConnectionActivity::new(),
+            max_idle,
+            timer: Box::pin(tokio::time::sleep_until(deadline)),
+        }
+    }
+
+    /// Ready once the connection has been idle for `max_idle`
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let Some(max_idle) = self.max_idle else {
+            return Poll::Pending;
+        };
+        while self.timer.as_mut().poll(cx).is_ready() {
+            let wait = match self.activity.idle_for() {
+                Some(idle) if idle >= max_idle => return Poll::Ready(()),
+                Some(idle) => max_idle - idle,
+                None => max_idle,
+            };
+            self.timer
+                .as_mut()
+                .reset(tokio::time::Instant::now() + wait);
+        }
+        Poll::Pending
+    }
+}
+
+/// Connect info of an `IdleConnection`: the wrapped stream's, plus its activity
+///
+/// `ConnectionActivityLayer` unwraps it so `Request::remote_addr` and friends
+/// still find the inner info.
+#[derive(Debug, Clone)]
+pub struct IdleConnectInfo<I> {
+    pub inner: I,
+    pub activity: ConnectionActivity,
+}
+
+impl<T: Connected> Connected for IdleConnection<T> {
+    type ConnectInfo = IdleConnectInfo<T::ConnectInfo>;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        IdleConnectInfo {
+            inner: self.io.connect_info(),
+            activity: self.activity.clone(),
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for IdleConnection<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        // An empty read is end-of-stream, which makes hyper close the connection
+        if self.poll_idle(cx).is_ready() {
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for IdleConnection<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+/// Wrap every accepted connection in an `IdleConnection`
+pub fn reap_idle<S, T, E>(
+    incoming: S,
+    max_idle: Option<Duration>,
+) -> impl Stream<Item = Result<IdleConnection<T>, E>>
+where
+    S: Stream<Item = Result<T, E>>,
+{
+    incoming.map(move |io| io.map(|io| IdleConnection::new(io, max_idle)))
+}
+
+/// Tower layer marking RPCs in flight on their `IdleConnection`
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionActivityLayer;
+
+impl<S> Layer<S> for ConnectionActivityLayer {
+    type Service = ConnectionActivityService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectionActivityService { inner }
+    }
+}
+
+/// Service produced by `ConnectionActivityLayer`
+#[derive(Debug, Clone)]
+pub struct ConnectionActivityService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<http::Request<B>> for ConnectionActivityService<S>
+where
+    S: Service<http::Request<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        /// Ends the RPC even if its future is dropped unfinished
+        struct InFlight(Option<ConnectionActivity>);
+
+        impl Drop for InFlight {
+            fn drop(&mut self) {
+                if let Some(activity) = &self.0 {
+                    activity.end();
+                }
+            }
+        }
+
+        let extensions = request.extensions_mut();
+        let activity = unwrap_connect_info::<TcpConnectInfo>(extensions);
+        #[cfg(unix)]
+        let activity = activity.or_else(|| unwrap_connect_info::<UdsConnectInfo>(extensions));
+        if let Some(activity) = &activity {
+            activity.begin();
+        }
+        let in_flight = InFlight(activity);
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            drop(in_flight);
+            response
+        })
+    }
+}
+
+/// Replace an `IdleConnectInfo<I>` extension with its inner `I`, returning the activity
+fn unwrap_connect_info<I>(extensions: &mut http::Extensions) -> Option<ConnectionActivity>
+where
+    I: Clone + Send + Sync + 'static,
+{
+    let info = extensions.remove::<IdleConnectInfo<I>>()?;
+    extensions.insert(info.inner);
+    Some(info.activity)
+}
+
+/// Micro-batcher that verifies answers arriving close together in one batch
+///
+/// Each caller queues its proof and waits up to `window` for someone else to
+/// flush the queue; the first caller to time out flushes it instead, so a
+/// cancelled request can never strand the rest of its batch.
+#[derive(Debug)]
+pub struct VerifyBatcher {
+    window: Duration,
+    max_batch: usize,
+    pending: Mutex<Vec<PendingVerify>>,
+}
+
+#[derive(Debug)]
+struct PendingVerify {
+    group_id: String,
+    zkp: Arc<ZKP>,
+    transcript: ProofTranscript,
+    reply: oneshot::Sender<bool>,
+}
+
+impl VerifyBatcher {
+    pub fn new(window: Duration, max_batch: usize) -> Self {
+        Self {
+            window,
+            max_batch: max_batch.max(1),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Queue `transcript` for verification under `zkp` and wait for its result
+    pub async fn verify(
+        &self,
+        group_id: String,
+        zkp: Arc<ZKP>,
+        transcript: ProofTranscript,
+    ) -> ZkpResult<bool> {
+        let (reply, mut result) = oneshot::channel();
+        let full = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.push(PendingVerify {
+                group_id,
+                zkp,
+                transcript,
+                reply,
+            });
+            pending.len() >= self.max_batch
+        };
+
+        if full {
+            self.flush();
+        }
+
+        let outcome = match tokio::time::timeout(self.window, &mut result).await {
+            Ok(outcome) => outcome,
+            Err(_) => {
+                self.flush();
+                result.await
+            }
+        };
+        outcome.map_err(|_| ZkpError::ComputationError("Batch verifier dropped".to_string()))
+    }
+
+    /// Verify everything queued so far, one batch per group
+    fn flush(&self) {
+        let queued = std::mem::take(&mut *self.pending.lock().unwrap());
+        if queued.is_empty() {
+            return;
+        }
+
+        let mut by_group: HashMap<String, Vec<PendingVerify>> = HashMap::new();
+        for job in queued {
+            by_group.entry(job.group_id.clone()).or_default().push(job);
+        }
+
+        // Verify on the blocking pool; callers are woken through their replies
+        for jobs in by_group.into_values() {
+            tokio::task::spawn_blocking(move || {
+                let zkp = jobs[0].zkp.clone();
+                let transcripts: Vec<ProofTranscript> =
+                    jobs.iter().map(|job| job.transcript.clone()).collect();
+                let results = zkp.verify_transcripts(&transcripts);
+
+                for (job, valid) in jobs.into_iter().zip(results) {
+                    let _ = job.reply.send(valid);
+                }
+            });
+        }
+    }
+}
+
+/// Immutable copy of the user map for read-only endpoints
+///
+/// Readers clone the current `Arc` under a lock held only for that clone,
+/// so they never wait on writers of the authoritative map. The copy is up
+/// to one refresh interval stale.
+#[derive(Debug)]
+pub struct ReadSnapshot {
+    current: StdRwLock<Arc<UserMap>>,
+}
+
+impl ReadSnapshot {
+    pub fn new(users: UserMap) -> Self {
+        Self {
+            current: StdRwLock::new(Arc::new(users)),
+        }
+    }
+
+    pub fn load(&self) -> Arc<UserMap> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn store(&self, users: UserMap) {
+        *self.current.write().unwrap() = Arc::new(users);
+    }
+}
+
+/// Users as seen by a read-only endpoint: the locked map or the snapshot
+enum UsersView<'a> {
+    Locked(tokio::sync::RwLockReadGuard<'a, UserMap>),
+    Snapshot(Arc<UserMap>),
+}
+
+impl std::ops::Deref for UsersView<'_> {
+    type Target = UserMap;
+
+    fn deref(&self) -> &UserMap {
+        match self {
+            UsersView::Locked(users) => users,
+            UsersView::Snapshot(users) => users,
+        }
+    }
+}
+
+/// Enhanced authentication service with better concurrency and error handling
+#[derive(Debug)]
+pub struct AuthImpl {
+    pub user_info: Arc<RwLock<UserMap>>,
+    /// Set with `read_snapshot_interval_ms`
+    pub read_snapshot: Option<ReadSnapshot>,
+    pub auth_id_to_user: Arc<RwLock<HashMap<String, String>>>,
+    /// `session_hash` of each user's current session, mapped to the username
+    pub session_to_user: Arc<RwLock<HashMap<String, String>>>,
+    pub groups: StdRwLock<GroupRegistry>,
+    pub rotation_events: Mutex<Vec<RotationEvent>>,
+    pub verify_latency: LatencyTracker,
+    pub challenge_answer_delay: DurationHistogram,
+    /// Shared with the `RpcLatencyLayer` wrapping this service
+    pub rpc_latency: Arc<RpcLatency>,
+    pub prometheus: PrometheusMetrics,
+    /// Minimum time before `verify_authentication` responds
+    pub min_response: Option<Duration>,
+    pub verify_batcher: Option<VerifyBatcher>,
+    pub deterministic_challenge_binding: bool,
+    pub challenge_bits: Option<u32>,
+    pub regenerate_weak_challenges: bool,
+    /// Configured relying party, empty when unbound
+    pub rp_id: String,
+    pub default_scopes: Vec<String>,
+    pub max_users: Option<usize>,
+    pub enable_public_commitment_export: bool,
+    pub enable_failure_reports: bool,
+    pub enable_admin: bool,
+    pub max_failed_attempts: Option<u32>,
+    pub lockout: chrono::Duration,
+    rate_limiter: Option<TokenBucketLimiter>,
+    pub pow_base_difficulty: Option<u32>,
+    pub pow_difficulty_growth: u32,
+    pub pow_max_difficulty: u32,
+    pub pow_algorithm: pow::Algorithm,
+    user_store: Option<StoreWriter<UserStore>>,
+    session_store: Option<StoreWriter<SessionStore>>,
+    pub session_ttl: chrono::Duration,
+    /// Cap on the size of groups adopted by `rotate_group`
+    pub max_group_bits: u64,
+    /// Age at which a never-authenticated account is swept, if enabled
+    pub unverified_account_ttl: Option<chrono::Duration>,
+    /// Salt age past which a login sets `rotate_salt`, if enabled
+    pub salt_rotation_age: Option<chrono::Duration>,
+    /// Owner of each registered `commitment_digest(y1, y2)`, kept only when
+    /// `reject_duplicate_public_keys` is set
+    public_key_index: Option<Mutex<HashMap<[u8; 32], String>>>,
+    failure_reports: Mutex<VecDeque<FailureReport>>,
+    challenge_secret: [u8; 32],
+    /// Source of challenges and ids once seeded for a replay test
+    #[cfg(feature = "test-determinism")]
+    replay_rng: Option<Mutex<StdRng>>,
+}
+
+impl AuthImpl {
+    /// Failure reports kept before the oldest are dropped
+    pub const MAX_FAILURE_REPORTS: usize = 1024;
+
+    /// Challenges drawn before giving up on finding one that is not weak
+    pub const MAX_CHALLENGE_DRAWS: u32 = 64;
+
+    /// Longest salt accepted at registration or credential update
+    pub const MAX_SALT_LEN: usize = 64;
+
+    /// Create a new authentication service instance
+    pub fn new() -> ZkpResult<Self> {
+        Self::with_config(&ServerConfig::default())
+    }
+
+    /// Create a new authentication service instance using the given server configuration
+    pub fn with_config(config: &ServerConfig) -> ZkpResult<Self> {
+        Self::with_group(ZKP::new(None)?, config)
+    }
+
+    /// Create a new authentication service instance for the given group
+    ///
+    /// If the group fails validation and `config.fallback_group` is set, the
+    /// fallback is validated and used instead.
+    pub fn with_group(zkp: ZKP, config: &ServerConfig) -> ZkpResult<Self> {
+        if config.challenge_bits == Some(0) {
+            return Err(ZkpError::InvalidInput(
+                "challenge_bits must be at least 1".to_string(),
+            ));
+        }
+
+        let zkp = match zkp.validate_parameters_with_max_bits(config.max_group_bits) {
+            Ok(()) => zkp,
+            Err(e) => {
+                error!("ZKP group failed validation: {}", e);
+
+                let Some(fallback) = &config.fallback_group else {
+                    return Err(e);
+                };
+
+                let fallback_zkp = fallback.to_zkp()?;
+                fallback_zkp
+                    .validate_parameters_with_max_bits(config.max_group_bits)
+                    .map_err(|fallback_err| {
+                        error!("Fallback group failed validation: {}", fallback_err);
+                        ZkpError::InvalidInput(format!(
+                            "{}; fallback group also invalid: {}",
+                            e, fallback_err
+                        ))
+                    })?;
+
+                warn!(
+                    "⚠️ FALLING BACK to configured group ({})",
+                    fallback_zkp.describe_parameters()
+                );
+                fallback_zkp
+            }
+        };
+
+        zkp.check_pinned_checksum(PINNED_GROUP_CHECKSUM)
+            .inspect_err(|e| error!("Refusing to start with unpinned group: {}", e))?;
+
+        let user_store = config.user_store_path.as_ref().map(UserStore::new);
+        let mut users = UserMap::new(config.preserve_registration_order);
+        if let Some(store) = &user_store {
+            // Stores written before the canonical group checksum hold the old id
+            let legacy_group_id = hex::encode(zkp.legacy_group_checksum());
+            for mut user in store.load()? {
+                if user.group_id == legacy_group_id {
+                    user.group_id = group_id(&zkp);
+                }
+                users.insert(user.user_name.clone(), user);
+            }
+            info!("Loaded {} users from the user store", users.len());
+        }
+        let user_store = user_store.map(StoreWriter::new);
+
+        let session_ttl = chrono::Duration::seconds(config.session_ttl_secs as i64);
+        let session_store = config.session_store_path.as_ref().map(SessionStore::new);
+        if let Some(store) = &session_store {
+            let restored = Self::apply_sessions(&mut users, store.load()?, session_ttl);
+            info!("Restored {} live sessions from the session store", restored);
+        }
+        let session_store = session_store.map(StoreWriter::new);
+
+        let public_key_index = config.reject_duplicate_public_keys.then(|| {
+            let index = users
+                .values()
+                .map(|user| {
+                    let digest =
+                        ZKP::commitment_digest(&user.y1.to_biguint(), &user.y2.to_biguint());
+                    (digest, user.user_name.clone())
+                })
+                .collect();
+            Mutex::new(index)
+        });
+
+        let session_to_user = session_index(&users);
+        let prometheus = PrometheusMetrics::new().map_err(|e| {
+            ZkpError::ComputationError(format!("Failed to register metrics: {}", e))
+        })?;
+
+        Ok(Self {
+            read_snapshot: config
+                .read_snapshot_interval_ms
+                .map(|_| ReadSnapshot::new(users.clone())),
+            user_info: Arc::new(RwLock::new(users)),
+            auth_id_to_user: Arc::new(RwLock::new(HashMap::new())),
+            session_to_user: Arc::new(RwLock::new(session_to_user)),
+            groups: StdRwLock::new(GroupRegistry::new(zkp)),
+            rotation_events: Mutex::new(Vec::new()),
+            verify_latency: LatencyTracker::new(
+                Duration::from_secs(config.verify_latency_window_secs),
+                config.verify_p99_budget_ms.map(Duration::from_millis),
+            ),
+            challenge_answer_delay: DurationHistogram::default(),
+            rpc_latency: Arc::default(),
+            prometheus,
+            min_response: config.min_response_ms.map(Duration::from_millis),
+            verify_batcher: config.verify_batch_window_ms.map(|window_ms| {
+                VerifyBatcher::new(Duration::from_millis(window_ms), config.verify_batch_max)
+            }),
+            deterministic_challenge_binding: config.deterministic_challenge_binding,
+            challenge_bits: config.challenge_bits,
+            regenerate_weak_challenges: config.regenerate_weak_challenges,
+            rp_id: config.rp_id.clone().unwrap_or_default(),
+            default_scopes: config.default_scopes.clone(),
+            max_users: config.max_users,
+            enable_public_commitment_export: config.enable_public_commitment_export,
+            enable_failure_reports: config.enable_failure_reports,
+            enable_admin: config.enable_admin,
+            max_failed_attempts: config.max_failed_attempts,
+            lockout: chrono::Duration::seconds(config.lockout_secs as i64),
+            rate_limiter: config.rate_limit_capacity.map(|capacity| {
+                TokenBucketLimiter::new(capacity, config.rate_limit_refill_per_sec)
+            }),
+            pow_base_difficulty: config.pow_base_difficulty,
+            pow_difficulty_growth: config.pow_difficulty_growth,
+            pow_max_difficulty: config.pow_max_difficulty,
+            pow_algorithm: config.pow_algorithm,
+            user_store,
+            session_store,
+            session_ttl,
+            max_group_bits: config.max_group_bits,
+            unverified_account_ttl: (config.unverified_account_ttl_secs > 0)
+                .then(|| chrono::Duration::seconds(config.unverified_account_ttl_secs as i64)),
+            salt_rotation_age: (config.salt_rotation_age_secs > 0)
+                .then(|| chrono::Duration::seconds(config.salt_rotation_age_secs as i64)),
+            public_key_index,
+            failure_reports: Mutex::new(VecDeque::new()),
+            challenge_secret: rand::random(),
+            #[cfg(feature = "test-determinism")]
+            replay_rng: None,
+        })
+    }
+
+    /// Derive every challenge, id and server secret from `seed` from now on
+    ///
+    /// Two instances seeded alike and fed the same requests hand out the same
+    /// `auth_id`s and challenges, for golden-transcript tests.
+    #[cfg(feature = "test-determinism")]
+    pub fn seed_for_replay(&mut self, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.challenge_secret = rng.gen();
+        self.replay_rng = Some(Mutex::new(rng));
+    }
+
+    fn random_below(&self, bound: &BigUint) -> ZkpResult<BigUint> {
+        #[cfg(feature = "test-determinism")]
+        if let Some(rng) = &self.replay_rng {
+            return Ok(rng.lock().unwrap().gen_biguint_below(bound));
+        }
+        ZKP::generate_random_number_below(bound)
+    }
+
+    fn new_id(&self) -> String {
+        #[cfg(feature = "test-determinism")]
+        if let Some(rng) = &self.replay_rng {
+            let bytes = rng.lock().unwrap().gen();
+            return uuid::Builder::from_random_bytes(bytes)
+                .into_uuid()
+                .to_string();
+        }
+        Uuid::new_v4().to_string()
+    }
+
+    /// Group new registrations and challenges are issued under
+    pub fn active_group(&self) -> Arc<ZKP> {
+        self.active_group_with_id().1
+    }
+
+    /// Id of the active group alongside the group itself
+    pub fn active_group_with_id(&self) -> (String, Arc<ZKP>) {
+        let groups = self.groups.read().unwrap();
+        (groups.active.clone(), groups.groups[&groups.active].clone())
+    }
+
+    /// Look up a known group by id
+    pub fn group(&self, group_id: &str) -> Option<Arc<ZKP>> {
+        self.groups.read().unwrap().groups.get(group_id).cloned()
+    }
+
+    /// Byte length of the widest `q` among known groups
+    fn max_scalar_len(&self) -> usize {
+        self.groups
+            .read()
+            .unwrap()
+            .groups
+            .values()
+            .map(|zkp| zkp.q.to_bytes_be().len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Make `next` the active group, keeping earlier groups for existing users
+    pub fn rotate_group(&self, next: ZKP) -> ZkpResult<RotationEvent> {
+        next.validate_parameters_with_max_bits(self.max_group_bits)?;
+        let new_group_id = group_id(&next);
+
+        let previous_group_id = {
+            let mut groups = self.groups.write().unwrap();
+            groups
+                .groups
+                .entry(new_group_id.clone())
+                .or_insert_with(|| Arc::new(next));
+            std::mem::replace(&mut groups.active, new_group_id.clone())
+        };
+
+        let event = RotationEvent {
+            previous_group_id,
+            new_group_id,
+            rotated_at: chrono::Utc::now(),
+        };
+        info!(
+            "🔄 Rotated active group {} -> {}",
+            event.previous_group_id, event.new_group_id
+        );
+        self.rotation_events.lock().unwrap().push(event.clone());
+
+        Ok(event)
+    }
+
+    /// Rebuild the read snapshot from the authoritative map, if enabled
+    pub async fn refresh_read_snapshot(&self) {
+        if let Some(snapshot) = &self.read_snapshot {
+            let users = self.user_info.read().await.clone();
+            snapshot.store(users);
+        }
+    }
+
+    /// Users for a read-only endpoint, from the snapshot when enabled
+    async fn read_users(&self) -> UsersView<'_> {
+        match &self.read_snapshot {
+            Some(snapshot) => UsersView::Snapshot(snapshot.load()),
+            None => UsersView::Locked(self.user_info.read().await),
+        }
+    }
+
+    /// Snapshot of the server's counters
+    pub async fn metrics_snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            registered_users: self.read_users().await.len(),
+            max_users: self.max_users,
+            challenge_answer_delay: self.challenge_answer_delay.buckets(),
+            rpc_latency: self.rpc_latency.summaries(),
+        }
+    }
+
+    /// `UserInfo::debug_snapshot` of `user_name`, for support (admin only)
+    pub async fn user_debug_snapshot(&self, user_name: &str) -> ZkpResult<UserDebugSnapshot> {
+        if !self.enable_admin {
+            return Err(ZkpError::InvalidInput(
+                "User debug snapshots require enable_admin".to_string(),
+            ));
+        }
+
+        self.user_info
+            .read()
+            .await
+            .get(user_name)
+            .map(UserInfo::debug_snapshot)
+            .ok_or_else(|| ZkpError::InvalidInput(format!("User {} not found", user_name)))
+    }
+
+    /// Username and public `(y1, y2)` of every registered user
+    ///
+    /// Admin only, since it enumerates all accounts. No challenge or session
+    /// state is included. Users come out in registration order only with
+    /// `preserve_registration_order`.
+    pub async fn export_directory(&self) -> ZkpResult<Vec<(String, BigUint, BigUint)>> {
+        if !self.enable_admin {
+            return Err(ZkpError::InvalidInput(
+                "Directory export requires enable_admin".to_string(),
+            ));
+        }
+
+        let user_info_map = self.read_users().await;
+        info!("Exporting directory of {} users", user_info_map.len());
+        Ok(user_info_map
+            .values()
+            .map(|user| {
+                (
+                    user.user_name.clone(),
+                    user.y1.to_biguint(),
+                    user.y2.to_biguint(),
+                )
+            })
+            .collect())
+    }
+
+    /// Reinstate the live sessions in `sessions`, dropping expired ones
+    ///
+    /// Returns how many sessions were restored. Runs on startup when
+    /// `session_store_path` is set, so a restart does not log users out.
+    fn apply_sessions(
+        users: &mut UserMap,
+        sessions: Vec<StoredSession>,
+        ttl: chrono::Duration,
+    ) -> usize {
+        let now = chrono::Utc::now();
+        let mut restored = 0;
+        for session in sessions {
+            if session.issued_at + ttl <= now {
+                continue;
+            }
+            let Some(user) = users.get_mut(&session.user_name) else {
+                warn!("Skipping session for unknown user {}", session.user_name);
+                continue;
+            };
+            user.session_hash = Some(Redacted(session.session_hash));
+            user.last_successful_auth = Some(session.issued_at);
+            restored += 1;
+        }
+        restored
+    }
+
+    /// Write the sessions held by `users` to the session store, like `persist_users`
+    fn persist_sessions(&self, users: &UserMap) -> Option<tokio::task::JoinHandle<()>> {
+        Some(self.session_store.as_ref()?.persist(users))
+    }
+
+    /// Remove accounts older than `unverified_account_ttl` that never authenticated
+    ///
+    /// Returns the removed usernames; nothing is removed when the TTL is disabled.
+    pub async fn sweep_unverified_accounts(&self) -> Vec<String> {
+        let Some(ttl) = self.unverified_account_ttl else {
+            return Vec::new();
+        };
+        let cutoff = chrono::Utc::now() - ttl;
+
+        let removed: HashSet<String> = {
+            let mut users = self.user_info.write().await;
+            let stale: HashSet<String> = users
+                .values()
+                .filter(|user| {
+                    user.last_successful_auth.is_none() && user.registration_timestamp <= cutoff
+                })
+                .map(|user| user.user_name.clone())
+                .collect();
+            if stale.is_empty() {
+                return Vec::new();
+            }
+            for user_name in &stale {
+                users.remove(user_name);
+            }
+            self.persist_users(&users);
+            stale
+        };
+
+        if let Some(index) = &self.public_key_index {
+            index
+                .lock()
+                .unwrap()
+                .retain(|_, owner| !removed.contains(owner));
+        }
+        self.auth_id_to_user
+            .write()
+            .await
+            .retain(|_, owner| !removed.contains(owner));
+        self.refresh_read_snapshot().await;
+
+        info!("Swept {} never-authenticated accounts", removed.len());
+        let mut removed: Vec<String> = removed.into_iter().collect();
+        removed.sort();
+        removed
+    }
+
+    /// Write the user and session stores, e.g. before shutting down
+    ///
+    /// Returns once both stores hold the current state.
+    pub async fn flush(&self) {
+        let pending = {
+            let users = self.user_info.read().await;
+            [self.persist_users(&users), self.persist_sessions(&users)]
+        };
+        for pending in pending.into_iter().flatten() {
+            let _ = pending.await;
+        }
+    }
+
+    /// Write `users` to the user store in the background, if one is configured
+    ///
+    /// Only the snapshot is taken here, so callers may hold the user lock.
+    fn persist_users(&self, users: &UserMap) -> Option<tokio::task::JoinHandle<()>> {
+        Some(self.user_store.as_ref()?.persist(users))
+    }
+
+    /// Report recorded for a failed verification of `auth_id`, if any
+    pub fn failure_report(&self, auth_id: &str) -> Option<FailureReport> {
+        self.failure_reports
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|report| report.auth_id == auth_id)
+            .cloned()
+    }
+
+    /// Build, log and keep a failure report for a rejected answer
+    ///
+    /// The log line redacts `c` and `s`; only the kept report carries them.
+    fn record_failure_report(
+        &self,
+        auth_id: &str,
+        user_info: &UserInfo,
+        zkp: &ZKP,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> ZkpResult<()> {
+        let (Some(r1), Some(r2)) = (&user_info.r1, &user_info.r2) else {
+            return Err(ZkpError::InvalidInput(
+                "No challenge commitments to report".to_string(),
+            ));
+        };
+        let y1 = user_info.y1.to_biguint();
+        let y2 = user_info.y2.to_biguint();
+        let details = zkp.verify_detailed(r1, r2, &y1, &y2, c, s)?;
+
+        let hex = |value: &BigUint| hex::encode(value.to_bytes_be());
+        let report = FailureReport {
+            auth_id: auth_id.to_string(),
+            user_id: user_info.user_id.clone(),
+            group_id: user_info.group_id.clone(),
+            c: hex(c),
+            s: hex(s),
+            expected_r1: hex(r1),
+            expected_r2: hex(r2),
+            recomputed_r1: hex(&details.recomputed_r1),
+            recomputed_r2: hex(&details.recomputed_r2),
+            commitment_fingerprint: hex::encode(ZKP::commitment_digest(&y1, &y2)),
+            failed_at: chrono::Utc::now(),
+        };
+        warn!(
+            auth_id = %report.auth_id,
+            user_id = %report.user_id,
+            group_id = %report.group_id,
+            c = %Redacted(&report.c),
+            s = %Redacted(&report.s),
+            expected_r1 = %report.expected_r1,
+            expected_r2 = %report.expected_r2,
+            recomputed_r1 = %report.recomputed_r1,
+            recomputed_r2 = %report.recomputed_r2,
+            commitment_fingerprint = %report.commitment_fingerprint,
+            "Failure report"
+        );
+
+        let mut reports = self.failure_reports.lock().unwrap();
+        if reports.len() >= Self::MAX_FAILURE_REPORTS {
+            reports.pop_front();
+        }
+        reports.push_back(report);
+        Ok(())
+    }
+
+    /// Proof-of-work difficulty required before a challenge after `failed_attempts` failures
+    ///
+    /// Starts at `pow_base_difficulty` after the first failure and grows by
+    /// `pow_difficulty_growth` bits per further failure, up to the cap. A
+    /// successful login resets `failed_attempts` and with it the difficulty.
+    pub fn pow_difficulty(&self, failed_attempts: u32) -> u32 {
+        match self.pow_base_difficulty {
+            Some(base) if failed_attempts > 0 => base
+                .saturating_add(
+                    self.pow_difficulty_growth
+                        .saturating_mul(failed_attempts - 1),
+                )
+                .min(self.pow_max_difficulty),
+            _ => 0,
+        }
+    }
+
+    /// Error asking for the proof of work due after `failed_attempts` failures
+    fn pow_required(&self, user_name: &str, failed_attempts: u32) -> Status {
+        let difficulty = self.pow_difficulty(failed_attempts);
+        info!(
+            "Requiring a {}-bit {} proof of work from user: {}",
+            difficulty, self.pow_algorithm, user_name
+        );
+        let mut metadata = MetadataMap::new();
+        metadata.insert(pow::DIFFICULTY_METADATA_KEY, difficulty.into());
+        let seed = hex::encode(self.pow_seed(user_name, failed_attempts));
+        for (key, value) in [
+            (pow::ALGORITHM_METADATA_KEY, self.pow_algorithm.to_string()),
+            (pow::SEED_METADATA_KEY, seed),
+        ] {
+            match MetadataValue::try_from(value) {
+                Ok(value) => metadata.insert(key, value),
+                Err(e) => return Status::internal(e.to_string()),
+            };
+        }
+        Status::with_metadata(Code::FailedPrecondition, "proof of work required", metadata)
+    }
+
+    /// Seed a proof of work must be bound to, fresh after every failure
+    pub fn pow_seed(&self, user_name: &str, failed_attempts: u32) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        Sha256::new()
+            .chain_update(self.challenge_secret)
+            .chain_update((user_name.len() as u64).to_be_bytes())
+            .chain_update(user_name)
+            .chain_update(failed_attempts.to_be_bytes())
+            .finalize()
+            .into()
+    }
+
+    /// Produce the challenge nonce for a user's commitments
+    ///
+    /// In binding mode the nonce is a hash of the commitments, the user and
+    /// a per-instance secret, so a challenge replayed against different
+    /// commitments no longer matches. Otherwise it is uniformly random.
+    /// The challenge sent to the client is this nonce bound to the user's
+    /// public commitment (see `ZKP::commitment_challenge`).
+    pub fn generate_challenge(
+        &self,
+        zkp: &ZKP,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> ZkpResult<BigUint> {
+        if self.deterministic_challenge_binding {
+            Ok(zkp.binding_challenge(r1, r2, user_name, &self.challenge_secret))
+        } else {
+            self.random_below(&zkp.q)
         }
     }
-}
 
-/// Enhanced authentication service with better concurrency and error handling
-#[derive(Debug)]
-pub struct AuthImpl {
-    pub user_info: Arc<RwLock<HashMap<String, UserInfo>>>,
-    pub auth_id_to_user: Arc<RwLock<HashMap<String, String>>>,
-    pub zkp: ZKP,
-}
+    /// Refuse requests whose secret is bound to another relying party
+    fn check_rp_id(&self, rp_id: &str) -> ZkpResult<()> {
+        if rp_id == self.rp_id {
+            return Ok(());
+        }
+        warn!(
+            "Request for relying party {:?}, expected {:?}",
+            rp_id, self.rp_id
+        );
+        Err(ZkpError::InvalidInput(format!(
+            "Relying party mismatch: this server is {:?}",
+            self.rp_id
+        )))
+    }
 
-impl AuthImpl {
-    /// Create a new authentication service instance
-    pub fn new() -> ZkpResult<Self> {
-        let zkp = ZKP::new(None)?;
-        zkp.validate_parameters()?;
+    /// Draw a challenge nonce and the challenge it yields for this user
+    ///
+    /// Weak challenges are logged and, with `regenerate_weak_challenges`,
+    /// redrawn. A binding-mode nonce is fixed by its inputs, so there a weak
+    /// challenge can only be logged.
+    pub fn draw_challenge(
+        &self,
+        zkp: &ZKP,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+    ) -> ZkpResult<(BigUint, BigUint)> {
+        for _ in 0..Self::MAX_CHALLENGE_DRAWS {
+            let nonce = self.generate_challenge(zkp, user_name, r1, r2)?;
+            let c = self.challenge_for(zkp, &nonce, y1, y2);
+            if !zkp.is_weak_challenge(&c) {
+                return Ok((nonce, c));
+            }
 
-        Ok(Self {
-            user_info: Arc::new(RwLock::new(HashMap::new())),
-            auth_id_to_user: Arc::new(RwLock::new(HashMap::new())),
-            zkp,
-        })
+            warn!("Drew a weak challenge for user: {}", user_name);
+            if !self.regenerate_weak_challenges || self.deterministic_challenge_binding {
+                return Ok((nonce, c));
+            }
+        }
+
+        Err(ZkpError::ComputationError(format!(
+            "No strong challenge in {} draws",
+            Self::MAX_CHALLENGE_DRAWS
+        )))
+    }
+
+    /// Chance that a login without the secret passes against `zkp`
+    ///
+    /// `2^-challenge_bits` when challenges are shortened, else `1/q`.
+    pub fn soundness_error(&self, zkp: &ZKP) -> f64 {
+        match self.challenge_bits {
+            Some(bits) if u64::from(bits) < zkp.q.bits() => {
+                0.5f64.powi(i32::try_from(bits).unwrap_or(i32::MAX))
+            }
+            _ => zkp.soundness_error(),
+        }
+    }
+
+    /// Challenge sent for `nonce`, bound to the user's public commitment
+    ///
+    /// Shortened to `challenge_bits` when configured, never to zero.
+    pub fn challenge_for(&self, zkp: &ZKP, nonce: &BigUint, y1: &BigUint, y2: &BigUint) -> BigUint {
+        let c = zkp.commitment_challenge(nonce, y1, y2);
+        match self.challenge_bits {
+            Some(bits) if u64::from(bits) < zkp.q.bits() => {
+                let c = c % (BigUint::from(1u32) << bits);
+                c.max(BigUint::from(1u32))
+            }
+            _ => c,
+        }
     }
 }
 
@@ -136,61 +2363,141 @@ impl Auth for AuthImpl {
         &self,
         request: Request<RegisterRequest>,
     ) -> Result<Response<RegisterResponse>, Status> {
+        let peer = request
+            .remote_addr()
+            .map_or_else(|| "unknown".to_string(), |addr| addr.to_string());
         let request = request.into_inner();
         let user_name = request.user;
+        let bits = |bytes: &[u8]| BigUint::from_bytes_be(bytes).bits();
+        let (y1_bits, y2_bits) = (bits(&request.y1), bits(&request.y2));
+
+        let result: Result<Response<RegisterResponse>, Status> = async {
+            negotiate_protocol_version(request.protocol_version)
+                .map_err(|e| Status::failed_precondition(e.to_string()))?;
+            self.check_rp_id(&request.rp_id)
+                .map_err(|e| Status::failed_precondition(e.to_string()))?;
+
+            // Input validation
+            if user_name.is_empty() {
+                return Err(Status::invalid_argument("Username cannot be empty"));
+            }
 
-        // Input validation
-        if user_name.is_empty() {
-            return Err(Status::invalid_argument("Username cannot be empty"));
-        }
+            if user_name.len() > 100 {
+                return Err(Status::invalid_argument("Username too long"));
+            }
 
-        if user_name.len() > 100 {
-            return Err(Status::invalid_argument("Username too long"));
-        }
+            // Deserialize and validate y1, y2
+            let y1 = serialization::deserialize_biguint(&request.y1)
+                .map_err(|e| Status::invalid_argument(format!("Invalid y1: {}", e)))?;
 
-        info!("Processing registration for user: {}", user_name);
+            let y2 = serialization::deserialize_biguint(&request.y2)
+                .map_err(|e| Status::invalid_argument(format!("Invalid y2: {}", e)))?;
 
-        // Deserialize and validate y1, y2
-        let y1 = serialization::deserialize_biguint(&request.y1)
-            .map_err(|e| Status::invalid_argument(format!("Invalid y1: {}", e)))?;
+            let password_hash = PasswordHash::from_wire(&request.password_hash)
+                .map_err(|e| Status::invalid_argument(e.to_string()))?;
 
-        let y2 = serialization::deserialize_biguint(&request.y2)
-            .map_err(|e| Status::invalid_argument(format!("Invalid y2: {}", e)))?;
+            if request.salt.len() > Self::MAX_SALT_LEN {
+                return Err(Status::invalid_argument("Salt too long"));
+            }
 
-        // Validate that y1 and y2 are within valid range
-        if y1 >= self.zkp.p || y2 >= self.zkp.p {
-            return Err(Status::invalid_argument("y1 and y2 must be less than p"));
-        }
+            let (group_id, zkp) = self.active_group_with_id();
 
-        if y1 <= BigUint::from(1u32) || y2 <= BigUint::from(1u32) {
-            return Err(Status::invalid_argument("y1 and y2 must be greater than 1"));
-        }
+            // Validate that y1 and y2 are within valid range
+            if y1 >= zkp.p || y2 >= zkp.p {
+                return Err(Status::invalid_argument("y1 and y2 must be less than p"));
+            }
 
-        let user_info = UserInfo {
-            user_name: user_name.clone(),
-            y1,
-            y2,
-            registration_timestamp: chrono::Utc::now(),
-            ..Default::default()
-        };
+            if y1 <= BigUint::from(1u32) || y2 <= BigUint::from(1u32) {
+                return Err(Status::invalid_argument("y1 and y2 must be greater than 1"));
+            }
 
-        // Check if user already exists
-        {
-            let user_info_map = self.user_info.read().await;
-            if user_info_map.contains_key(&user_name) {
-                warn!("Registration attempt for existing user: {}", user_name);
-                return Err(Status::already_exists("User already registered"));
+            if !zkp.is_subgroup_member(&y1) || !zkp.is_subgroup_member(&y2) {
+                return Err(Status::invalid_argument(
+                    "y1 and y2 must lie in the order-q subgroup",
+                ));
             }
-        }
 
-        // Register the user
-        {
-            let mut user_info_map = self.user_info.write().await;
-            user_info_map.insert(user_name.clone(), user_info);
-        }
+            let user_id = self.new_id();
+            let registration_timestamp = chrono::Utc::now();
+
+            let user_info = UserInfo {
+                user_id: user_id.clone(),
+                user_name: user_name.clone(),
+                y1: CompressedBigUint::new(&y1),
+                y2: CompressedBigUint::new(&y2),
+                registration_timestamp,
+                scopes: self.default_scopes.clone(),
+                group_id,
+                password_hash,
+                salt: request.salt,
+                salt_updated_at: registration_timestamp,
+                ..Default::default()
+            };
+
+            // Check if user already exists
+            {
+                let user_info_map = self.user_info.read().await;
+                if user_info_map.contains_key(&user_name) {
+                    return Err(Status::already_exists("User already registered"));
+                }
+            }
+
+            // Register the user
+            {
+                let mut user_info_map = self.user_info.write().await;
+                if let Some(max_users) = self.max_users {
+                    if user_info_map.len() >= max_users {
+                        return Err(Status::resource_exhausted("user capacity reached"));
+                    }
+                }
+                if let Some(index) = &self.public_key_index {
+                    let mut index = index.lock().unwrap();
+                    let digest = ZKP::commitment_digest(&y1, &y2);
+                    if index.contains_key(&digest) {
+                        return Err(Status::already_exists(
+                            "Public values already registered to another user",
+                        ));
+                    }
+                    index.insert(digest, user_name.clone());
+                }
+                user_info_map.insert(user_name.clone(), user_info);
+                self.persist_users(&user_info_map);
+            }
 
-        info!("✅ Successful registration for user: {}", user_name);
-        Ok(Response::new(RegisterResponse {}))
+            self.prometheus.registrations.inc();
+            Ok(Response::new(RegisterResponse {
+                user_id,
+                registration_timestamp: registration_timestamp.timestamp(),
+            }))
+        }
+        .await;
+
+        // One record per registration, whatever its outcome
+        let user: String = user_name.chars().take(100).collect();
+        match &result {
+            Ok(response) => info!(
+                event = "registration",
+                user = %user,
+                user_id = %response.get_ref().user_id,
+                y1_bits,
+                y2_bits,
+                peer = %peer,
+                outcome = "registered",
+                "✅ Registration"
+            ),
+            Err(status) => warn!(
+                event = "registration",
+                user = %user,
+                y1_bits,
+                y2_bits,
+                peer = %peer,
+                outcome = "rejected",
+                code = ?status.code(),
+                reason = %status.message(),
+                "Registration rejected"
+            ),
+        }
+        result
     }
 
     #[instrument(skip(self, request))]
@@ -201,6 +2508,11 @@ impl Auth for AuthImpl {
         let request = request.into_inner();
         let user_name = request.user;
 
+        negotiate_protocol_version(request.protocol_version)
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+        self.check_rp_id(&request.rp_id)
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+
         if user_name.is_empty() {
             return Err(Status::invalid_argument("Username cannot be empty"));
         }
@@ -214,32 +2526,117 @@ impl Auth for AuthImpl {
         let r2 = serialization::deserialize_biguint(&request.r2)
             .map_err(|e| Status::invalid_argument(format!("Invalid r2: {}", e)))?;
 
-        // Validate r1 and r2
-        if r1 >= self.zkp.p || r2 >= self.zkp.p {
-            return Err(Status::invalid_argument("r1 and r2 must be less than p"));
-        }
-
         if r1 <= BigUint::from(1u32) || r2 <= BigUint::from(1u32) {
             return Err(Status::invalid_argument("r1 and r2 must be greater than 1"));
         }
 
+        let known = self
+            .user_info
+            .read()
+            .await
+            .get(&user_name)
+            .map(|user_info| (user_info.failed_attempts, user_info.group_id.clone()));
+        let failed_attempts = known.as_ref().map(|(failed, _)| *failed);
+
+        // Membership takes a full exponentiation per value, so like the proof
+        // of work below it is checked before the write lock
+        if let Some(zkp) = known
+            .as_ref()
+            .and_then(|(_, group_id)| self.group(group_id))
+        {
+            if r1 >= zkp.p || r2 >= zkp.p {
+                return Err(Status::invalid_argument("r1 and r2 must be less than p"));
+            }
+            if !zkp.is_subgroup_member(&r1) || !zkp.is_subgroup_member(&r2) {
+                return Err(Status::invalid_argument(
+                    "r1 and r2 must lie in the order-q subgroup",
+                ));
+            }
+        }
+
+        // Argon2 attempts are slow by design, so check the proof of work on
+        // the blocking pool before taking the write lock
+        let difficulty = failed_attempts.map_or(0, |failed| self.pow_difficulty(failed));
+        if let Some(failed_attempts) = failed_attempts.filter(|_| difficulty > 0) {
+            let seed = self.pow_seed(&user_name, failed_attempts);
+            let context = pow::context(&user_name, &r1, &r2, &seed);
+            let (algorithm, nonce) = (self.pow_algorithm, request.pow_nonce);
+            let valid = tokio::task::spawn_blocking(move || {
+                algorithm.is_valid(&context, nonce, difficulty)
+            })
+            .await
+            .map_err(|e| Status::internal(format!("Proof-of-work task failed: {}", e)))?;
+            if !valid {
+                return Err(self.pow_required(&user_name, failed_attempts));
+            }
+        }
+
         let mut user_info_map = self.user_info.write().await;
 
+        let now = chrono::Utc::now();
+        if user_info_map
+            .get_mut(&user_name)
+            .is_some_and(|user_info| user_info.clear_expired_lock(now))
+        {
+            info!("🔓 Lockout expired for user: {}", user_name);
+            self.persist_users(&user_info_map);
+        }
+
         if let Some(user_info) = user_info_map.get_mut(&user_name) {
-            // Check rate limiting (simple implementation){}
-            if let Some(last_challenge) = user_info.last_challenge_timestamp {
-                let time_since_last = chrono::Utc::now() - last_challenge;
-                if time_since_last < chrono::Duration::seconds(1) {
-                    return Err(Status::resource_exhausted("Too many challenge requests"));
+            // r1 and r2 were checked against the group the user had then
+            if known.as_ref().map(|(_, group_id)| group_id) != Some(&user_info.group_id) {
+                return Err(Status::aborted("Credentials changed, retry the challenge"));
+            }
+            let zkp = self
+                .group(&user_info.group_id)
+                .ok_or_else(|| Status::internal("Unknown group for user"))?;
+
+            if user_info.is_locked(now) {
+                warn!("Challenge request for locked user: {}", user_name);
+                return Err(Status::permission_denied("account locked"));
+            }
+
+            // A failure since the check moved the seed the proof must be bound to
+            if user_info.failed_attempts != failed_attempts.unwrap_or_default()
+                && self.pow_difficulty(user_info.failed_attempts) > 0
+            {
+                return Err(self.pow_required(&user_name, user_info.failed_attempts));
+            }
+
+            match &self.rate_limiter {
+                Some(limiter) => {
+                    if !limiter.try_acquire(&user_name) {
+                        return Err(Status::resource_exhausted(
+                            "Too many requests for this user",
+                        ));
+                    }
+                }
+                // Without a limiter, allow one challenge per second
+                None => {
+                    if let Some(last_challenge) = user_info.last_challenge_timestamp {
+                        let time_since_last = chrono::Utc::now() - last_challenge;
+                        if time_since_last < chrono::Duration::seconds(1) {
+                            return Err(Status::resource_exhausted("Too many challenge requests"));
+                        }
+                    }
                 }
             }
 
-            let c = ZKP::generate_random_number_below(&self.zkp.q)
+            let (nonce, c) = self
+                .draw_challenge(
+                    &zkp,
+                    &user_name,
+                    &r1,
+                    &r2,
+                    &user_info.y1.to_biguint(),
+                    &user_info.y2.to_biguint(),
+                )
                 .map_err(|e| Status::internal(format!("Failed to generate challenge: {}", e)))?;
 
-            let auth_id = Uuid::new_v4().to_string();
+            let auth_id = self.new_id();
 
-            user_info.c = Some(c.clone());
+            user_info.c = Some(Redacted(c.clone()));
+            user_info.challenge_nonce = Some(Redacted(nonce));
             user_info.r1 = Some(r1);
             user_info.r2 = Some(r2);
             user_info.last_challenge_timestamp = Some(chrono::Utc::now());
@@ -251,10 +2648,13 @@ impl Auth for AuthImpl {
             }
 
             info!("✅ Challenge created for user: {}", user_name);
+            self.prometheus.challenges.inc();
 
             Ok(Response::new(AuthenticationChallengeResponse {
                 auth_id,
                 c: serialization::serialize_biguint(&c),
+                password_hash: user_info.password_hash.to_string(),
+                salt: user_info.salt.clone(),
             }))
         } else {
             warn!("Challenge request for non-existent user: {}", user_name);
@@ -267,104 +2667,646 @@ impl Auth for AuthImpl {
         &self,
         request: Request<AuthenticationAnswerRequest>,
     ) -> Result<Response<AuthenticationAnswerResponse>, Status> {
-        let request = request.into_inner();
-        let auth_id = request.auth_id;
+        if self.verify_latency.should_shed() {
+            warn!("Shedding verification request: p99 latency over budget");
+            return Err(Status::resource_exhausted(
+                "Server is overloaded, please retry later",
+            ));
+        }
+
+        // Pad every outcome past the shed check to the same minimum latency
+        let started = tokio::time::Instant::now();
+        let result = async move {
+            let request = request.into_inner();
+            let auth_id = request.auth_id;
+
+            if auth_id.is_empty() {
+                return Err(Status::invalid_argument("Auth ID cannot be empty"));
+            }
+
+            // The answer is the only proof value on the wire here; reject a
+            // malformed one before any lookup or arithmetic
+            prevalidate_value("s", &request.s, self.max_scalar_len())
+                .map_err(|e| Status::invalid_argument(format!("Invalid solution: {}", e)))?;
+
+            info!(
+                "Processing authentication verification for auth_id: {}",
+                auth_id
+            );
+
+            // Find user by auth_id
+            let user_name = {
+                let auth_id_map = self.auth_id_to_user.read().await;
+                auth_id_map.get(&auth_id).cloned()
+            };
+
+            let user_name = match user_name {
+                Some(name) => name,
+                None => {
+                    warn!("Verification attempt with invalid auth_id: {}", auth_id);
+                    return Err(Status::not_found("Invalid auth ID"));
+                }
+            };
+
+            if let Some(limiter) = &self.rate_limiter {
+                if !limiter.try_acquire(&user_name) {
+                    return Err(Status::resource_exhausted(
+                        "Too many requests for this user",
+                    ));
+                }
+            }
+
+            // Deserialize solution
+            let s = serialization::deserialize_biguint(&request.s)
+                .map_err(|e| Status::invalid_argument(format!("Invalid solution: {}", e)))?;
+
+            // Read the challenge under a shared lock so concurrent answers don't
+            // queue behind each other; the stored values are borrowed, not cloned
+            let user_info_map = self.user_info.read().await;
+            let user_info = user_info_map
+                .get(&user_name)
+                .ok_or_else(|| Status::internal("User info not found"))?;
+            let zkp = self
+                .group(&user_info.group_id)
+                .ok_or_else(|| Status::internal("Unknown group for user"))?;
+
+            if s >= zkp.q {
+                return Err(Status::invalid_argument("Solution must be less than q"));
+            }
+
+            // Check if we have the required challenge data
+            let (Some(r1), Some(r2), Some(nonce)) =
+                (&user_info.r1, &user_info.r2, &user_info.challenge_nonce)
+            else {
+                error!("Incomplete challenge data for user: {}", user_name);
+                return Err(Status::failed_precondition(
+                    "No active challenge for this user",
+                ));
+            };
+
+            // Consume the auth_id before verifying, so concurrent answers to
+            // the same challenge cannot both be checked. A malformed or
+            // rate-limited answer above leaves it for a retry.
+            if self
+                .auth_id_to_user
+                .write()
+                .await
+                .remove(&auth_id)
+                .is_none()
+            {
+                return Err(Status::not_found("Invalid auth ID"));
+            }
+
+            if let Some(issued_at) = user_info.last_challenge_timestamp {
+                let delay = (chrono::Utc::now() - issued_at)
+                    .to_std()
+                    .unwrap_or_default();
+                self.challenge_answer_delay.record(delay);
+                info!(
+                    user = %user_name,
+                    challenge_answer_delay_ms = delay.as_millis() as u64,
+                    "Answer received"
+                );
+            }
+
+            let y1 = user_info.y1.to_biguint();
+            let y2 = user_info.y2.to_biguint();
+
+            // Recompute the challenge from this user's commitment rather than
+            // trusting the stored one, so an answer for another user's challenge
+            // fails even if the auth_id maps to the wrong user
+            let c = self.challenge_for(&zkp, &nonce.0, &y1, &y2);
+
+            let verify_start = Instant::now();
+            let verification_result = match &self.verify_batcher {
+                // The batcher outlives the lock, so it gets its own copy
+                Some(batcher) => {
+                    let group_id = user_info.group_id.clone();
+                    let transcript = ProofTranscript {
+                        r1: r1.clone(),
+                        r2: r2.clone(),
+                        y1,
+                        y2,
+                        c: c.clone(),
+                        s: s.clone(),
+                        ..Default::default()
+                    };
+                    drop(user_info_map);
+                    batcher.verify(group_id, zkp.clone(), transcript).await
+                }
+                // The modpows would stall this worker thread, so they run on
+                // the blocking pool against copies taken before the lock drops
+                None => {
+                    let (r1, r2) = (r1.clone(), r2.clone());
+                    let (c, s) = (c.clone(), s.clone());
+                    let zkp = zkp.clone();
+                    drop(user_info_map);
+                    tokio::task::spawn_blocking(move || zkp.verify(&r1, &r2, &y1, &y2, &c, &s))
+                        .await
+                        .map_err(|e| Status::internal(format!("Verification task failed: {}", e)))?
+                }
+            }
+            .map_err(|e| Status::internal(format!("Verification error: {}", e)))?;
+            let verify_elapsed = verify_start.elapsed();
+            self.verify_latency.record(verify_elapsed);
+            self.prometheus
+                .verify_latency
+                .observe(verify_elapsed.as_secs_f64());
+
+            let mut user_info_map = self.user_info.write().await;
+            let user_info = user_info_map
+                .get_mut(&user_name)
+                .ok_or_else(|| Status::internal("User info not found"))?;
+
+            if verification_result {
+                user_info.s = Some(Redacted(s));
+                let session_id = self.new_id();
+                let previous_session = user_info
+                    .session_hash
+                    .replace(Redacted(session_hash(&session_id)));
+                user_info.last_successful_auth = Some(chrono::Utc::now());
+                user_info.failed_attempts = 0;
+                user_info.locked_until = None;
+                let scopes = user_info.scopes.clone();
+                let rotate_salt = self
+                    .salt_rotation_age
+                    .is_some_and(|age| chrono::Utc::now() - user_info.salt_updated_at >= age);
+                // Only this fresh proof, not the long-lived session, may rotate the salt
+                let rotation_token = rotate_salt.then(|| self.new_id());
+                user_info.rotation_token_hash = rotation_token
+                    .as_deref()
+                    .map(|token| Redacted(session_hash(token)));
+                self.persist_users(&user_info_map);
+                self.persist_sessions(&user_info_map);
+
+                {
+                    let mut session_map = self.session_to_user.write().await;
+                    if let Some(previous) = previous_session {
+                        session_map.remove(&previous.0);
+                    }
+                    session_map.insert(session_hash(&session_id), user_name.clone());
+                }
+
+                info!("✅ Successful authentication for user: {}", user_name);
+                self.prometheus.auth_success.inc();
+                Ok(Response::new(AuthenticationAnswerResponse {
+                    session_id,
+                    scopes,
+                    rotate_salt,
+                    rotation_token: rotation_token.unwrap_or_default(),
+                }))
+            } else {
+                user_info.failed_attempts += 1;
+                self.prometheus.auth_failure.inc();
+                warn!(
+                    "❌ Failed authentication for user: {} (attempt {})",
+                    user_name, user_info.failed_attempts
+                );
+
+                if self.enable_failure_reports {
+                    if let Err(e) = self.record_failure_report(&auth_id, user_info, &zkp, &c, &s) {
+                        error!("Failed to build failure report: {}", e);
+                    }
+                }
+                user_info.s = Some(Redacted(s));
+
+                if self
+                    .max_failed_attempts
+                    .is_some_and(|max| user_info.failed_attempts >= max)
+                {
+                    user_info.locked_until = Some(chrono::Utc::now() + self.lockout);
+                    warn!("🔒 Locked account for user: {}", user_name);
+                }
+                self.persist_users(&user_info_map);
 
-        if auth_id.is_empty() {
-            return Err(Status::invalid_argument("Auth ID cannot be empty"));
+                Err(Status::permission_denied("Authentication failed"))
+            }
         }
+        .await;
 
-        info!(
-            "Processing authentication verification for auth_id: {}",
-            auth_id
-        );
+        if let Some(min_response) = self.min_response {
+            tokio::time::sleep_until(started + min_response).await;
+        }
+        result
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_public_commitment(
+        &self,
+        request: Request<PublicCommitmentRequest>,
+    ) -> Result<Response<PublicCommitmentResponse>, Status> {
+        if !self.enable_public_commitment_export {
+            return Err(Status::unimplemented(
+                "Public commitment export is disabled",
+            ));
+        }
+
+        let user_name = request.into_inner().user;
+
+        if user_name.is_empty() {
+            return Err(Status::invalid_argument("Username cannot be empty"));
+        }
+
+        let user_info_map = self.read_users().await;
+        let user_info = user_info_map.get(&user_name).ok_or_else(|| {
+            warn!(
+                "Public commitment request for non-existent user: {}",
+                user_name
+            );
+            Status::not_found(format!("User {} not found", user_name))
+        })?;
+
+        info!("Exported public commitment for user: {}", user_name);
+        Ok(Response::new(PublicCommitmentResponse {
+            y1: serialization::serialize_biguint(&user_info.y1.to_biguint()),
+            y2: serialization::serialize_biguint(&user_info.y2.to_biguint()),
+        }))
+    }
+
+    #[instrument(skip(self, _request))]
+    async fn get_server_info(
+        &self,
+        _request: Request<ServerInfoRequest>,
+    ) -> Result<Response<ServerInfoResponse>, Status> {
+        let (group_id, zkp) = self.active_group_with_id();
+
+        Ok(Response::new(ServerInfoResponse {
+            p: serialization::serialize_biguint(&zkp.p),
+            q: serialization::serialize_biguint(&zkp.q),
+            alpha: serialization::serialize_biguint(&zkp.alpha),
+            beta: serialization::serialize_biguint(&zkp.beta),
+            group_id,
+            security_level_bits: zkp.security_level_bits() as u32,
+            min_protocol_version: *SUPPORTED_PROTOCOL_VERSIONS.start(),
+            max_protocol_version: *SUPPORTED_PROTOCOL_VERSIONS.end(),
+        }))
+    }
 
-        // Find user by auth_id
-        let user_name = {
-            let auth_id_map = self.auth_id_to_user.read().await;
-            auth_id_map.get(&auth_id).cloned()
+    async fn validate_session(
+        &self,
+        request: Request<ValidateSessionRequest>,
+    ) -> Result<Response<ValidateSessionResponse>, Status> {
+        let session_hash = session_hash(&request.into_inner().session_id);
+        let invalid = || Response::new(ValidateSessionResponse::default());
+
+        let Some(user_name) = self
+            .session_to_user
+            .read()
+            .await
+            .get(&session_hash)
+            .cloned()
+        else {
+            return Ok(invalid());
         };
 
-        let user_name = match user_name {
-            Some(name) => name,
-            None => {
-                warn!("Verification attempt with invalid auth_id: {}", auth_id);
-                return Err(Status::not_found("Invalid auth ID"));
+        let expires_at = {
+            let user_info_map = self.user_info.read().await;
+            user_info_map
+                .get(&user_name)
+                .filter(|user| user.session_hash.as_ref().map(|s| &s.0) == Some(&session_hash))
+                .and_then(|user| user.last_successful_auth)
+                .map(|issued_at| issued_at + self.session_ttl)
+        };
+
+        match expires_at {
+            Some(expires_at) if expires_at > chrono::Utc::now() => {
+                Ok(Response::new(ValidateSessionResponse {
+                    valid: true,
+                    user: user_name,
+                    expires_at: expires_at.timestamp(),
+                }))
+            }
+            _ => {
+                info!("Rejected expired session for user: {}", user_name);
+                self.session_to_user.write().await.remove(&session_hash);
+                Ok(invalid())
             }
+        }
+    }
+
+    #[instrument(skip(self, request))]
+    async fn update_credentials(
+        &self,
+        request: Request<UpdateCredentialsRequest>,
+    ) -> Result<Response<UpdateCredentialsResponse>, Status> {
+        let request = request.into_inner();
+        let unauthenticated = || Status::unauthenticated("Invalid or expired session");
+
+        let rotation_token_hash = session_hash(&request.rotation_token);
+        let session_hash = session_hash(&request.session_id);
+        let Some(user_name) = self
+            .session_to_user
+            .read()
+            .await
+            .get(&session_hash)
+            .cloned()
+        else {
+            return Err(unauthenticated());
         };
 
-        // Deserialize solution
-        let s = serialization::deserialize_biguint(&request.s)
-            .map_err(|e| Status::invalid_argument(format!("Invalid solution: {}", e)))?;
+        let y1 = serialization::deserialize_biguint(&request.y1)
+            .map_err(|e| Status::invalid_argument(format!("Invalid y1: {}", e)))?;
+        let y2 = serialization::deserialize_biguint(&request.y2)
+            .map_err(|e| Status::invalid_argument(format!("Invalid y2: {}", e)))?;
+        let password_hash = PasswordHash::from_wire(&request.password_hash)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        if request.salt.len() > Self::MAX_SALT_LEN {
+            return Err(Status::invalid_argument("Salt too long"));
+        }
 
-        if s >= self.zkp.q {
-            return Err(Status::invalid_argument("Solution must be less than q"));
+        // New credentials always live in the active group
+        let (group_id, zkp) = self.active_group_with_id();
+        if y1 >= zkp.p || y2 >= zkp.p {
+            return Err(Status::invalid_argument("y1 and y2 must be less than p"));
+        }
+        if y1 <= BigUint::from(1u32) || y2 <= BigUint::from(1u32) {
+            return Err(Status::invalid_argument("y1 and y2 must be greater than 1"));
+        }
+        if !zkp.is_subgroup_member(&y1) || !zkp.is_subgroup_member(&y2) {
+            return Err(Status::invalid_argument(
+                "y1 and y2 must lie in the order-q subgroup",
+            ));
         }
 
         let mut user_info_map = self.user_info.write().await;
+        let now = chrono::Utc::now();
         let user_info = user_info_map
             .get_mut(&user_name)
-            .ok_or_else(|| Status::internal("User info not found"))?;
+            .filter(|user| {
+                user.session_hash.as_ref().map(|s| &s.0) == Some(&session_hash)
+                    && user
+                        .last_successful_auth
+                        .is_some_and(|issued_at| issued_at + self.session_ttl > now)
+            })
+            .ok_or_else(unauthenticated)?;
+
+        // The token is spent by this attempt whether or not it matches
+        let token = user_info.rotation_token_hash.take();
+        if token.map(|token| token.0) != Some(rotation_token_hash) {
+            return Err(Status::permission_denied(
+                "Updating credentials needs the rotation token of a fresh login",
+            ));
+        }
 
-        // Check if we have the required challenge data
-        let (r1, r2, c) = match (&user_info.r1, &user_info.r2, &user_info.c) {
-            (Some(r1), Some(r2), Some(c)) => (r1.clone(), r2.clone(), c.clone()),
-            _ => {
-                error!("Incomplete challenge data for user: {}", user_name);
-                return Err(Status::failed_precondition(
-                    "No active challenge for this user",
+        if let Some(index) = &self.public_key_index {
+            let mut index = index.lock().unwrap();
+            let digest = ZKP::commitment_digest(&y1, &y2);
+            if index.get(&digest).is_some_and(|owner| *owner != user_name) {
+                return Err(Status::already_exists(
+                    "Public values already registered to another user",
                 ));
             }
-        };
+            index.remove(&ZKP::commitment_digest(
+                &user_info.y1.to_biguint(),
+                &user_info.y2.to_biguint(),
+            ));
+            index.insert(digest, user_name.clone());
+        }
 
-        user_info.s = Some(s.clone());
+        user_info.y1 = CompressedBigUint::new(&y1);
+        user_info.y2 = CompressedBigUint::new(&y2);
+        user_info.group_id = group_id;
+        user_info.password_hash = password_hash;
+        user_info.salt = request.salt;
+        user_info.salt_updated_at = now;
+        self.persist_users(&user_info_map);
+
+        info!("🔑 Updated credentials for user: {}", user_name);
+        Ok(Response::new(UpdateCredentialsResponse {
+            updated_at: now.timestamp(),
+        }))
+    }
 
-        // Verify the proof
-        let verification_result = self
-            .zkp
-            .verify(&r1, &r2, &user_info.y1, &user_info.y2, &c, &s)
-            .map_err(|e| Status::internal(format!("Verification error: {}", e)))?;
+    async fn echo_values(
+        &self,
+        request: Request<EchoRequest>,
+    ) -> Result<Response<EchoResponse>, Status> {
+        if !self.enable_admin {
+            return Err(Status::unimplemented("Value echo requires enable_admin"));
+        }
 
-        if verification_result {
-            let session_id = Uuid::new_v4().to_string();
-            user_info.session_id = Some(session_id.clone());
-            user_info.last_successful_auth = Some(chrono::Utc::now());
-            user_info.failed_attempts = 0;
+        let value = serialization::deserialize_biguint(&request.into_inner().value)
+            .map_err(|e| Status::invalid_argument(format!("Invalid value: {}", e)))?;
 
-            // Clean up auth_id
-            {
-                let mut auth_id_map = self.auth_id_to_user.write().await;
-                auth_id_map.remove(&auth_id);
-            }
+        Ok(Response::new(EchoResponse {
+            bit_length: value.bits(),
+            byte_length: value.to_bytes_be().len() as u64,
+            hex: value.to_str_radix(16),
+        }))
+    }
+}
 
-            info!("✅ Successful authentication for user: {}", user_name);
-            Ok(Response::new(AuthenticationAnswerResponse { session_id }))
-        } else {
-            user_info.failed_attempts += 1;
-            warn!(
-                "❌ Failed authentication for user: {} (attempt {})",
-                user_name, user_info.failed_attempts
-            );
+/// Rotate through `groups`, one every `period`, until the list is exhausted
+pub async fn run_group_rotation(
+    auth_impl: Arc<AuthImpl>,
+    period: Duration,
+    groups: Vec<GroupConfig>,
+) {
+    let mut interval = tokio::time::interval(period);
+    // The first tick completes immediately
+    interval.tick().await;
+
+    for group in groups {
+        interval.tick().await;
+
+        match group.to_zkp().and_then(|zkp| auth_impl.rotate_group(zkp)) {
+            Ok(_) => {}
+            Err(e) => error!("Scheduled group rotation failed: {}", e),
+        }
+    }
 
-            // Clean up auth_id
-            {
-                let mut auth_id_map = self.auth_id_to_user.write().await;
-                auth_id_map.remove(&auth_id);
+    warn!("No configured groups left to rotate to; keeping the active group");
+}
+
+/// Rebuild the read snapshot every `period`
+pub async fn run_read_snapshot_refresh(auth_impl: Arc<AuthImpl>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        auth_impl.refresh_read_snapshot().await;
+    }
+}
+
+/// Sweep never-authenticated accounts every `period`
+pub async fn run_unverified_account_sweep(auth_impl: Arc<AuthImpl>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        auth_impl.sweep_unverified_accounts().await;
+    }
+}
+
+/// Serve `metrics` in the Prometheus text format on `listener` until the task is dropped
+///
+/// Answers `GET /metrics`; every other path is a 404.
+pub async fn serve_metrics(
+    metrics: PrometheusMetrics,
+    listener: std::net::TcpListener,
+) -> hyper::Result<()> {
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, StatusCode,
+    };
+
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+        async move {
+            Ok::<_, std::convert::Infallible>(service_fn(move |request: http::Request<Body>| {
+                let response = match (request.method(), request.uri().path()) {
+                    (&http::Method::GET, "/metrics") => http::Response::builder()
+                        .header(http::header::CONTENT_TYPE, prometheus::TEXT_FORMAT)
+                        .body(Body::from(metrics.encode())),
+                    _ => http::Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::empty()),
+                };
+                async move { response }
+            }))
+        }
+    });
+
+    hyper::Server::from_tcp(listener)?.serve(make_service).await
+}
+
+/// Future resolving on the first SIGINT or SIGTERM
+///
+/// The handlers are installed when this is called rather than when the
+/// future is first polled, so a signal arriving while the server is still
+/// binding is not lost.
+#[cfg(unix)]
+pub fn shutdown_signal() -> std::io::Result<impl std::future::Future<Output = ()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    Ok(async move {
+        tokio::select! {
+            _ = interrupt.recv() => info!("Received SIGINT, shutting down"),
+            _ = terminate.recv() => info!("Received SIGTERM, shutting down"),
+        }
+    })
+}
+
+/// Future resolving on the first Ctrl-C, where there are no Unix signals
+#[cfg(not(unix))]
+pub fn shutdown_signal() -> std::io::Result<impl std::future::Future<Output = ()>> {
+    Ok(async {
+        match tokio::signal::ctrl_c().await {
+            Ok(()) => info!("Received Ctrl-C, shutting down"),
+            Err(e) => {
+                // Keep serving rather than shutting down straight away
+                error!("Failed to listen for Ctrl-C: {}", e);
+                std::future::pending::<()>().await
             }
+        }
+    })
+}
 
-            Err(Status::permission_denied("Authentication failed"))
+/// Bind a Unix domain socket at `path` for `serve_with_incoming`
+///
+/// A socket file left behind by a previous run is replaced; any other kind
+/// of file at `path` is an error.
+#[cfg(unix)]
+pub fn bind_uds(path: impl AsRef<Path>) -> std::io::Result<UnixListenerStream> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let path = path.as_ref();
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
         }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    Ok(UnixListenerStream::new(UnixListener::bind(path)?))
+}
+
+/// Validate `config` and build the service from it without binding a socket
+///
+/// Returns a one-line summary of what would be served.
+pub fn check_config(config: &ServerConfig) -> ZkpResult<String> {
+    config.validate()?;
+    let tls = config
+        .tls_config()
+        .map_err(|e| ZkpError::InvalidInput(e.to_string()))?;
+    let auth_impl = AuthImpl::with_config(config)?;
+
+    let mut listen = match &config.uds_path {
+        Some(path) => format!("unix socket {}", path),
+        None => format!("{}:{}", config.host, config.port),
+    };
+    if tls.is_some() {
+        listen.push_str(" with TLS");
     }
+    Ok(format!(
+        "Configuration OK: group {} on {}",
+        group_id(&auth_impl.active_group()),
+        listen
+    ))
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "zkp-server")]
+#[command(about = "A Zero Knowledge Proof authentication server")]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Load and validate the configuration, then exit 0 if it is usable
+    CheckConfig,
+    /// Write known-answer test vectors as JSON for other implementations
+    GenVectors {
+        /// File to write the vectors to
+        #[arg(long)]
+        out: PathBuf,
+
+        /// Vectors per group
+        #[arg(long, default_value_t = 8)]
+        count: usize,
+    },
 }
 
 /// Initialize and run the ZKP authentication server
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Args::parse();
+
     // Initialize tracing
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
+    if let Some(Command::GenVectors { out, count }) = &args.command {
+        let vectors = zkp::generate_test_vectors(*count)?;
+        std::fs::write(out, serde_json::to_string_pretty(&vectors)?)?;
+        println!("Wrote {} test vectors to {}", vectors.len(), out.display());
+        return Ok(());
+    }
+
+    if let Some(Command::CheckConfig) = args.command {
+        let report = ServerConfig::from_env()
+            .map_err(|e| format!("Failed to load config: {}", e))
+            .and_then(|config| check_config(&config).map_err(|e| e.to_string()));
+        match report {
+            Ok(summary) => {
+                println!("{}", summary);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("Invalid configuration: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     // Load configuration
     let config = ServerConfig::from_env().unwrap_or_else(|e| {
         warn!("Failed to load config: {}. Using defaults.", e);
@@ -377,29 +3319,112 @@ async fn main() -> Result<()> {
     );
 
     // Create authentication service
-    let auth_impl =
-        AuthImpl::new().map_err(|e| anyhow::anyhow!("Failed to create auth service: {}", e))?;
+    let auth_impl = Arc::new(
+        AuthImpl::with_config(&config)
+            .map_err(|e| anyhow::anyhow!("Failed to create auth service: {}", e))?,
+    );
+
+    info!(
+        "Soundness error per login attempt: {:e}",
+        auth_impl.soundness_error(&auth_impl.active_group())
+    );
 
-    let addr = config.socket_addr()?;
-    info!("🚀 Starting server on {}", addr);
+    if let Some(interval_ms) = config.read_snapshot_interval_ms.filter(|ms| *ms > 0) {
+        info!(
+            "Serving reads from a snapshot refreshed every {}ms",
+            interval_ms
+        );
+        tokio::spawn(run_read_snapshot_refresh(
+            auth_impl.clone(),
+            Duration::from_millis(interval_ms),
+        ));
+    }
+
+    if config.unverified_account_ttl_secs > 0 {
+        info!(
+            "Removing accounts not authenticated within {}s of registering",
+            config.unverified_account_ttl_secs
+        );
+        // Sweep often enough that no account outlives its TTL by more than an hour
+        let period = Duration::from_secs(config.unverified_account_ttl_secs.min(60 * 60));
+        tokio::spawn(run_unverified_account_sweep(auth_impl.clone(), period));
+    }
+
+    if let Some(port) = config.metrics_port {
+        let listener = std::net::TcpListener::bind((config.host.as_str(), port))?;
+        info!("Serving Prometheus metrics on {}", listener.local_addr()?);
+        let metrics = auth_impl.prometheus.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_metrics(metrics, listener).await {
+                error!("Metrics endpoint failed: {}", e);
+            }
+        });
+    }
+
+    match config.group_rotation_days {
+        Some(0) => warn!("group_rotation_days must be positive; rotation disabled"),
+        Some(days) if !config.rotation_groups.is_empty() => {
+            info!("Scheduling group rotation every {} days", days);
+            tokio::spawn(run_group_rotation(
+                auth_impl.clone(),
+                Duration::from_secs(days * 24 * 60 * 60),
+                config.rotation_groups.clone(),
+            ));
+        }
+        Some(_) => warn!("group_rotation_days is set but no rotation_groups are configured"),
+        None => {}
+    }
 
     // Build server with middleware
-    let server = Server::builder()
+    let rpc_latency = RpcLatencyLayer::new(auth_impl.rpc_latency.clone());
+    let mut builder = Server::builder();
+    if let Some(tls) = config.tls_config()? {
+        info!("Serving TLS with certificate {:?}", config.tls_cert_path);
+        builder = builder.tls_config(tls)?;
+    }
+    let server = builder
         .timeout(Duration::from_secs(config.request_timeout_secs))
+        .http2_keepalive_interval(config.http2_keepalive_interval())
+        .http2_keepalive_timeout(config.http2_keepalive_timeout())
         .layer(
             ServiceBuilder::new()
+                .layer(ConnectionActivityLayer)
                 .layer(TraceLayer::new_for_grpc())
+                .layer(rpc_latency)
                 .layer(TimeoutLayer::new(Duration::from_secs(
                     config.request_timeout_secs,
                 )))
                 .layer(CorsLayer::permissive()),
         )
         .max_concurrent_streams(Some(config.max_concurrent_streams))
-        .add_service(AuthServer::new(auth_impl));
+        .add_service(AuthServer::from_arc(auth_impl.clone()));
+
+    // Start the server, draining in-flight requests on SIGINT or SIGTERM
+    let shutdown = shutdown_signal()?;
+    let max_idle = config.max_connection_idle();
+    let result = match &config.uds_path {
+        #[cfg(not(unix))]
+        Some(_) => anyhow::bail!("uds_path is only supported on Unix"),
+        #[cfg(unix)]
+        Some(path) => {
+            info!("🚀 Starting server on unix socket {}", path);
+            server
+                .serve_with_incoming_shutdown(reap_idle(bind_uds(path)?, max_idle), shutdown)
+                .await
+        }
+        None => {
+            let addr = config.socket_addr()?;
+            info!("🚀 Starting server on {}", addr);
+            let incoming = TcpIncoming::new(addr, false, None).map_err(|e| anyhow::anyhow!(e))?;
+            server
+                .serve_with_incoming_shutdown(reap_idle(incoming, max_idle), shutdown)
+                .await
+        }
+    };
 
-    // Start the server
-    match server.serve(addr).await {
+    match result {
         Ok(_) => {
+            auth_impl.flush().await;
             info!("Server shutdown gracefully");
             Ok(())
         }
@@ -0,0 +1,289 @@
+// Not every test binary uses every helper.
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::oneshot,
+};
+use tonic::transport::{server::TcpIncoming, Channel, Endpoint, Server, ServerTlsConfig, Uri};
+use tower::service_fn;
+
+// Compile the server binary's source into the test crate so the fixture can
+// host the real `AuthImpl` in-process.
+#[path = "../../src/server.rs"]
+pub mod server;
+
+use server::{
+    reap_idle,
+    zkp_auth::auth_server::{Auth, AuthServer},
+    AuthImpl, ConnectionActivityLayer, RpcLatencyLayer, ServerConfig,
+};
+
+/// In-process ZKP authentication server bound to an ephemeral port.
+///
+/// The server runs in a background task and is shut down when the fixture
+/// is dropped.
+pub struct TestServer {
+    addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl TestServer {
+    /// Start a server with a fresh `AuthImpl`
+    pub async fn start() -> Self {
+        let auth_impl = AuthImpl::new().expect("Failed to create auth service");
+        Self::start_with(auth_impl).await
+    }
+
+    /// Start a server hosting the given service, usually an `AuthImpl`
+    pub async fn start_with(auth_impl: impl Auth) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind ephemeral port");
+        let addr = listener.local_addr().expect("Failed to read local address");
+        let incoming =
+            TcpIncoming::from_listener(listener, true, None).expect("Failed to accept connections");
+
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            Server::builder()
+                .add_service(AuthServer::new(auth_impl))
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+                .expect("Test server failed");
+        });
+
+        Self {
+            addr,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Start a server behind `RpcLatencyLayer`, recording into `auth_impl.rpc_latency`
+    pub async fn start_with_rpc_latency(auth_impl: Arc<AuthImpl>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind ephemeral port");
+        let addr = listener.local_addr().expect("Failed to read local address");
+        let incoming =
+            TcpIncoming::from_listener(listener, true, None).expect("Failed to accept connections");
+
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            Server::builder()
+                .layer(RpcLatencyLayer::new(auth_impl.rpc_latency.clone()))
+                .add_service(AuthServer::from_arc(auth_impl))
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+                .expect("Test server failed");
+        });
+
+        Self {
+            addr,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Start a server applying `config`'s keepalive and idle-connection settings
+    pub async fn start_with_connection_config(auth_impl: AuthImpl, config: &ServerConfig) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind ephemeral port");
+        let addr = listener.local_addr().expect("Failed to read local address");
+        let incoming =
+            TcpIncoming::from_listener(listener, true, None).expect("Failed to accept connections");
+        let incoming = reap_idle(incoming, config.max_connection_idle());
+
+        let server = Server::builder()
+            .http2_keepalive_interval(config.http2_keepalive_interval())
+            .http2_keepalive_timeout(config.http2_keepalive_timeout())
+            .layer(ConnectionActivityLayer)
+            .add_service(AuthServer::new(auth_impl));
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            server
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+                .expect("Test server failed");
+        });
+
+        Self {
+            addr,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Start a server that only accepts TLS connections
+    pub async fn start_tls(auth_impl: AuthImpl, tls: ServerTlsConfig) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind ephemeral port");
+        let addr = listener.local_addr().expect("Failed to read local address");
+        let incoming =
+            TcpIncoming::from_listener(listener, true, None).expect("Failed to accept connections");
+
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            Server::builder()
+                .tls_config(tls)
+                .expect("Invalid TLS config")
+                .add_service(AuthServer::new(auth_impl))
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+                .expect("Test server failed");
+        });
+
+        Self {
+            addr,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Address the server is bound to
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// URL suitable for `AuthClient::connect`
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Channel to the server, with the local address it connects from
+    pub async fn connect_from(&self) -> (Channel, SocketAddr) {
+        let addr = self.addr;
+        let local_addr = Arc::new(Mutex::new(None));
+        let recorded = local_addr.clone();
+        let channel = Endpoint::from_shared(self.endpoint())
+            .unwrap()
+            .connect_with_connector(service_fn(move |_: Uri| {
+                let recorded = recorded.clone();
+                async move {
+                    let stream = TcpStream::connect(addr).await?;
+                    *recorded.lock().unwrap() = Some(stream.local_addr()?);
+                    Ok::<_, std::io::Error>(stream)
+                }
+            }))
+            .await
+            .expect("Failed to connect");
+        let local_addr = local_addr.lock().unwrap().expect("Connector never ran");
+        (channel, local_addr)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// Fixtures that need Unix domain sockets
+#[cfg(unix)]
+pub mod uds {
+    use std::path::{Path, PathBuf};
+
+    use tokio::{net::UnixStream, sync::oneshot};
+    use tonic::transport::{Channel, Endpoint, Server, Uri};
+    use tower::service_fn;
+
+    use super::server::{bind_uds, zkp_auth::auth_server::AuthServer, AuthImpl};
+
+    /// In-process server listening on a Unix domain socket in the temp dir
+    pub struct UdsTestServer {
+        path: PathBuf,
+        shutdown: Option<oneshot::Sender<()>>,
+    }
+
+    impl UdsTestServer {
+        /// Start a server hosting the given `AuthImpl`
+        pub async fn start_with(auth_impl: AuthImpl) -> Self {
+            let path = std::env::temp_dir().join(format!("zkp-{}.sock", uuid::Uuid::new_v4()));
+            let incoming = bind_uds(&path).expect("Failed to bind unix socket");
+
+            let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+            tokio::spawn(async move {
+                Server::builder()
+                    .add_service(AuthServer::new(auth_impl))
+                    .serve_with_incoming_shutdown(incoming, async {
+                        let _ = shutdown_rx.await;
+                    })
+                    .await
+                    .expect("Test server failed");
+            });
+
+            Self {
+                path,
+                shutdown: Some(shutdown),
+            }
+        }
+
+        /// Path of the socket the server listens on
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Channel connected to the server over its socket
+        pub async fn channel(&self) -> Channel {
+            let path = self.path.clone();
+            // The URI is required by the builder but ignored by the connector
+            Endpoint::from_static("http://[::]:50051")
+                .connect_with_connector(service_fn(move |_: Uri| UnixStream::connect(path.clone())))
+                .await
+                .expect("Failed to connect over unix socket")
+        }
+    }
+
+    impl Drop for UdsTestServer {
+        fn drop(&mut self) {
+            if let Some(shutdown) = self.shutdown.take() {
+                let _ = shutdown.send(());
+            }
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Tracing output captured in memory as JSON lines
+#[derive(Clone, Default)]
+pub struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl CapturedLogs {
+    /// Capture events passing `filter` on this thread until the guard is dropped
+    pub fn set_default(&self, filter: &str) -> tracing::subscriber::DefaultGuard {
+        let writer = self.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_env_filter(filter)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::set_default(subscriber)
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
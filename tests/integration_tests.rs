@@ -1,43 +1,38 @@
-use zkp::{serialization, ZKP};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-// Import the generated proto code
-mod zkp_auth {
-    include!("../src/zkp_auth.rs");
-}
-
-use zkp_auth::{
-    auth_client::AuthClient, AuthenticationAnswerRequest, AuthenticationChallengeRequest,
-    RegisterRequest,
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint};
+use tonic::Code;
+use tonic::{Request, Response, Status};
+use zkp::client::{
+    authenticate_user, authenticate_with_commitment, authenticate_with_commitment_for_rp,
+    derive_secret, derive_secret_with_hash, password_to_biguint, register_user,
+    register_user_for_rp, register_user_with_hash, register_users_bulk, ClientError, Commitment,
+    RetryPolicy,
 };
+use zkp::zkp_auth::{
+    auth_client::AuthClient, auth_server::Auth, AuthenticationAnswerRequest,
+    AuthenticationAnswerResponse, AuthenticationChallengeRequest, AuthenticationChallengeResponse,
+    EchoRequest, EchoResponse, PublicCommitmentRequest, PublicCommitmentResponse, RegisterRequest,
+    RegisterResponse, ServerInfoRequest, ServerInfoResponse, UpdateCredentialsRequest,
+    UpdateCredentialsResponse, ValidateSessionRequest, ValidateSessionResponse,
+};
+use zkp::{serialization, PasswordHash, ZkpError, PROTOCOL_VERSION, ZKP};
 
-/// Convert password string to BigUint deterministically
-fn password_to_biguint(password: &str, zkp: &ZKP) -> num_bigint::BigUint {
-    use sha2::{Digest, Sha256};
-
-    let mut hasher = Sha256::new();
-    hasher.update(password.as_bytes());
-    let hash = hasher.finalize();
-
-    let password_biguint = num_bigint::BigUint::from_bytes_be(&hash);
+mod common;
 
-    // Reduce modulo q to ensure it's in valid range
-    password_biguint % &zkp.q
-}
+#[cfg(unix)]
+use common::uds::UdsTestServer;
+use common::{
+    server::{serve_metrics, AuthImpl, ServerConfig},
+    CapturedLogs, TestServer,
+};
 
 /// Integration tests for the ZKP authentication system
 #[tokio::test]
 async fn test_full_authentication_flow() {
-    // Note: This test requires the server to be running
-    // Start the server in a separate terminal: cargo run --bin server
-
-    // Skip this test if server is not running
-    let client_result = AuthClient::connect("http://127.0.0.1:50051").await;
-    if client_result.is_err() {
-        println!("Skipping integration test - server not running");
-        return;
-    }
-
-    let mut client = client_result.unwrap();
+    let server = TestServer::start().await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
     let zkp = ZKP::new(None).unwrap();
 
     // Test data
@@ -52,6 +47,10 @@ async fn test_full_authentication_flow() {
         user: username.clone(),
         y1: serialization::serialize_biguint(&y1),
         y2: serialization::serialize_biguint(&y2),
+        protocol_version: PROTOCOL_VERSION,
+        rp_id: String::new(),
+        password_hash: String::new(),
+        salt: Vec::new(),
     };
 
     let register_response = client.register(register_request).await;
@@ -65,6 +64,9 @@ async fn test_full_authentication_flow() {
         user: username.clone(),
         r1: serialization::serialize_biguint(&r1),
         r2: serialization::serialize_biguint(&r2),
+        protocol_version: PROTOCOL_VERSION,
+        pow_nonce: 0,
+        rp_id: String::new(),
     };
 
     let challenge_response = client
@@ -97,19 +99,18 @@ async fn test_full_authentication_flow() {
 
 #[tokio::test]
 async fn test_invalid_registration() {
-    let client_result = AuthClient::connect("http://127.0.0.1:50051").await;
-    if client_result.is_err() {
-        println!("Skipping integration test - server not running");
-        return;
-    }
-
-    let mut client = client_result.unwrap();
+    let server = TestServer::start().await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
 
     // Test empty username
     let register_request = RegisterRequest {
         user: "".to_string(),
         y1: vec![1, 2, 3],
         y2: vec![4, 5, 6],
+        protocol_version: PROTOCOL_VERSION,
+        rp_id: String::new(),
+        password_hash: String::new(),
+        salt: Vec::new(),
     };
 
     let register_response = client.register(register_request).await;
@@ -118,13 +119,8 @@ async fn test_invalid_registration() {
 
 #[tokio::test]
 async fn test_authentication_without_registration() {
-    let client_result = AuthClient::connect("http://127.0.0.1:50051").await;
-    if client_result.is_err() {
-        println!("Skipping integration test - server not running");
-        return;
-    }
-
-    let mut client = client_result.unwrap();
+    let server = TestServer::start().await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
     let zkp = ZKP::new(None).unwrap();
 
     let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
@@ -134,6 +130,9 @@ async fn test_authentication_without_registration() {
         user: "non_existent_user".to_string(),
         r1: serialization::serialize_biguint(&r1),
         r2: serialization::serialize_biguint(&r2),
+        protocol_version: PROTOCOL_VERSION,
+        pow_nonce: 0,
+        rp_id: String::new(),
     };
 
     let challenge_response = client
@@ -145,13 +144,8 @@ async fn test_authentication_without_registration() {
 
 #[tokio::test]
 async fn test_wrong_password_authentication() {
-    let client_result = AuthClient::connect("http://127.0.0.1:50051").await;
-    if client_result.is_err() {
-        println!("Skipping integration test - server not running");
-        return;
-    }
-
-    let mut client = client_result.unwrap();
+    let server = TestServer::start().await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
     let zkp = ZKP::new(None).unwrap();
 
     // Test data
@@ -169,6 +163,10 @@ async fn test_wrong_password_authentication() {
         user: username.clone(),
         y1: serialization::serialize_biguint(&y1),
         y2: serialization::serialize_biguint(&y2),
+        protocol_version: PROTOCOL_VERSION,
+        rp_id: String::new(),
+        password_hash: String::new(),
+        salt: Vec::new(),
     };
 
     client.register(register_request).await.unwrap();
@@ -181,6 +179,9 @@ async fn test_wrong_password_authentication() {
         user: username.clone(),
         r1: serialization::serialize_biguint(&r1),
         r2: serialization::serialize_biguint(&r2),
+        protocol_version: PROTOCOL_VERSION,
+        pow_nonce: 0,
+        rp_id: String::new(),
     };
 
     let challenge_response = client
@@ -204,3 +205,859 @@ async fn test_wrong_password_authentication() {
         "Wrong password should fail authentication"
     );
 }
+
+#[tokio::test]
+async fn test_register_response_carries_user_id() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let user_info = auth_impl.user_info.clone();
+    let server = TestServer::start_with(auth_impl).await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+
+    let (username, _, y1, y2) = zkp.deterministic_user(1);
+
+    let register_request = RegisterRequest {
+        user: username.clone(),
+        y1: serialization::serialize_biguint(&y1),
+        y2: serialization::serialize_biguint(&y2),
+        protocol_version: PROTOCOL_VERSION,
+        rp_id: String::new(),
+        password_hash: String::new(),
+        salt: Vec::new(),
+    };
+
+    let register_response = client
+        .register(register_request)
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert!(!register_response.user_id.is_empty());
+
+    let user_info_map = user_info.read().await;
+    let stored = user_info_map.get(&username).unwrap();
+    assert_eq!(register_response.user_id, stored.user_id);
+    assert_eq!(
+        register_response.registration_timestamp,
+        stored.registration_timestamp.timestamp()
+    );
+}
+
+#[tokio::test]
+async fn test_library_client_against_in_process_server() {
+    let server = TestServer::start().await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+
+    let user_id = register_user(&mut client, &zkp, "library_user", "hunter2")
+        .await
+        .unwrap();
+    assert!(!user_id.is_empty());
+
+    let session_id = authenticate_user(
+        &mut client,
+        &zkp,
+        "library_user",
+        "hunter2",
+        &RetryPolicy::default(),
+    )
+    .await
+    .unwrap();
+    assert!(!session_id.is_empty());
+
+    // Stay clear of the per-user challenge rate limit
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    let result = authenticate_user(
+        &mut client,
+        &zkp,
+        "library_user",
+        "wrong",
+        &RetryPolicy::default(),
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_library_client_solves_pow_up_to_its_cap() {
+    let auth_impl = AuthImpl::with_config(&ServerConfig {
+        pow_base_difficulty: Some(4),
+        pow_difficulty_growth: 60,
+        pow_max_difficulty: 64,
+        ..Default::default()
+    })
+    .unwrap();
+    let server = TestServer::start_with(auth_impl).await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+    register_user(&mut client, &zkp, "pow_user", "hunter2")
+        .await
+        .unwrap();
+
+    // Each wrong password raises the difficulty of the next challenge
+    let mut outcomes = Vec::new();
+    for _ in 0..3 {
+        outcomes.push(
+            authenticate_user(
+                &mut client,
+                &zkp,
+                "pow_user",
+                "wrong",
+                &RetryPolicy::default(),
+            )
+            .await
+            .unwrap_err(),
+        );
+        // Stay clear of the per-user challenge rate limit
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+    }
+
+    // No proof of work, then a 4-bit one solved, then 64 bits refused
+    assert!(matches!(outcomes[0], ClientError::AuthRejected(_)));
+    assert!(matches!(outcomes[1], ClientError::AuthRejected(_)));
+    assert!(matches!(
+        outcomes[2],
+        ClientError::Zkp(ZkpError::InvalidInput(_))
+    ));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_authentication_over_unix_socket() {
+    let server = UdsTestServer::start_with(AuthImpl::new().unwrap()).await;
+    assert!(server.path().exists());
+
+    let mut client = AuthClient::new(server.channel().await);
+    let zkp = ZKP::new(None).unwrap();
+
+    let user_id = register_user(&mut client, &zkp, "uds_user", "uds_password")
+        .await
+        .unwrap();
+    assert!(!user_id.is_empty());
+
+    let session_id = authenticate_user(
+        &mut client,
+        &zkp,
+        "uds_user",
+        "uds_password",
+        &RetryPolicy::default(),
+    )
+    .await
+    .unwrap();
+    assert!(!session_id.is_empty());
+}
+
+#[tokio::test]
+async fn test_client_never_logs_secrets_in_the_clear() {
+    let logs = CapturedLogs::default();
+    let _guard = logs.set_default("zkp=trace,integration_tests=trace");
+
+    let server = TestServer::start().await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+    register_user(&mut client, &zkp, "quiet_user", "hunter2")
+        .await
+        .unwrap();
+
+    let commitment = Commitment::generate(&zkp).unwrap();
+    let k = commitment.k.clone();
+    authenticate_with_commitment(
+        &mut client,
+        &zkp,
+        "quiet_user",
+        "hunter2",
+        commitment,
+        &RetryPolicy::default(),
+    )
+    .await
+    .unwrap();
+
+    let logs = logs.contents();
+    assert!(logs.contains("quiet_user"));
+    for secret in [password_to_biguint("hunter2", &zkp), k] {
+        assert!(!logs.contains(&secret.to_string()));
+        assert!(!logs.contains(&secret.to_str_radix(16)));
+    }
+    assert!(!logs.contains("hunter2"));
+}
+
+/// Service that loses the first answer it receives, recording every call
+struct DropFirstAnswer {
+    inner: AuthImpl,
+    challenges: Arc<Mutex<u32>>,
+    answers: Arc<Mutex<Vec<AuthenticationAnswerRequest>>>,
+}
+
+#[tonic::async_trait]
+impl Auth for DropFirstAnswer {
+    async fn register(
+        &self,
+        request: Request<RegisterRequest>,
+    ) -> Result<Response<RegisterResponse>, Status> {
+        self.inner.register(request).await
+    }
+
+    async fn create_authentication_challenge(
+        &self,
+        request: Request<AuthenticationChallengeRequest>,
+    ) -> Result<Response<AuthenticationChallengeResponse>, Status> {
+        *self.challenges.lock().unwrap() += 1;
+        self.inner.create_authentication_challenge(request).await
+    }
+
+    async fn verify_authentication(
+        &self,
+        request: Request<AuthenticationAnswerRequest>,
+    ) -> Result<Response<AuthenticationAnswerResponse>, Status> {
+        let first = {
+            let mut answers = self.answers.lock().unwrap();
+            answers.push(request.get_ref().clone());
+            answers.len() == 1
+        };
+        if first {
+            return Err(Status::unavailable("connection reset"));
+        }
+        self.inner.verify_authentication(request).await
+    }
+
+    async fn get_public_commitment(
+        &self,
+        request: Request<PublicCommitmentRequest>,
+    ) -> Result<Response<PublicCommitmentResponse>, Status> {
+        self.inner.get_public_commitment(request).await
+    }
+
+    async fn get_server_info(
+        &self,
+        request: Request<ServerInfoRequest>,
+    ) -> Result<Response<ServerInfoResponse>, Status> {
+        self.inner.get_server_info(request).await
+    }
+
+    async fn echo_values(
+        &self,
+        request: Request<EchoRequest>,
+    ) -> Result<Response<EchoResponse>, Status> {
+        self.inner.echo_values(request).await
+    }
+
+    async fn validate_session(
+        &self,
+        request: Request<ValidateSessionRequest>,
+    ) -> Result<Response<ValidateSessionResponse>, Status> {
+        self.inner.validate_session(request).await
+    }
+
+    async fn update_credentials(
+        &self,
+        request: Request<UpdateCredentialsRequest>,
+    ) -> Result<Response<UpdateCredentialsResponse>, Status> {
+        self.inner.update_credentials(request).await
+    }
+}
+
+#[tokio::test]
+async fn test_retried_answer_reuses_cached_solution() {
+    let service = DropFirstAnswer {
+        inner: AuthImpl::new().unwrap(),
+        challenges: Arc::default(),
+        answers: Arc::default(),
+    };
+    let challenges = service.challenges.clone();
+    let answers = service.answers.clone();
+    let server = TestServer::start_with(service).await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+    register_user(&mut client, &zkp, "flaky_user", "password")
+        .await
+        .unwrap();
+
+    let retry = RetryPolicy {
+        max_retries: 2,
+        initial_backoff: Duration::from_millis(1),
+    };
+    let session_id = authenticate_user(&mut client, &zkp, "flaky_user", "password", &retry)
+        .await
+        .unwrap();
+    assert!(!session_id.is_empty());
+
+    // One challenge, answered twice with the identical cached solution
+    assert_eq!(*challenges.lock().unwrap(), 1);
+    let answers = answers.lock().unwrap();
+    assert_eq!(answers.len(), 2);
+    assert_eq!(answers[0], answers[1]);
+}
+
+#[tokio::test]
+async fn test_proof_for_one_relying_party_fails_at_another() {
+    let config = ServerConfig {
+        rp_id: Some("B".to_string()),
+        ..Default::default()
+    };
+    let server = TestServer::start_with(AuthImpl::with_config(&config).unwrap()).await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+
+    register_user_for_rp(&mut client, &zkp, "B", "rp_user", "password")
+        .await
+        .unwrap();
+
+    // Requests naming another relying party are refused outright
+    let error = register_user_for_rp(&mut client, &zkp, "A", "other_user", "password")
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("Relying party mismatch"));
+
+    // A proof made with the secret for "A" does not verify at "B", even
+    // when the request claims "B"
+    let secret_a = derive_secret("password", "rp_user", "A", &zkp);
+    assert_ne!(secret_a, derive_secret("password", "rp_user", "B", &zkp));
+    let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+    let (r1, r2) = zkp.compute_pair(&k).unwrap();
+    let challenge = client
+        .create_authentication_challenge(AuthenticationChallengeRequest {
+            user: "rp_user".to_string(),
+            r1: serialization::serialize_biguint(&r1),
+            r2: serialization::serialize_biguint(&r2),
+            protocol_version: PROTOCOL_VERSION,
+            pow_nonce: 0,
+            rp_id: "B".to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let c = serialization::deserialize_biguint(&challenge.c).unwrap();
+    let status = client
+        .verify_authentication(AuthenticationAnswerRequest {
+            auth_id: challenge.auth_id,
+            s: serialization::serialize_biguint(&zkp.solve(&k, &c, &secret_a).unwrap()),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    // Proving for "B" succeeds
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    authenticate_with_commitment_for_rp(
+        &mut client,
+        &zkp,
+        "B",
+        "rp_user",
+        "password",
+        Commitment::generate(&zkp).unwrap(),
+        &RetryPolicy::default(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_registered_password_hash_is_used_at_login() {
+    let server = TestServer::start().await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+
+    let sha256 = derive_secret_with_hash("password", "user", "", PasswordHash::Sha256, &zkp);
+    let blake3 = derive_secret_with_hash("password", "user", "", PasswordHash::Blake3, &zkp);
+    assert_ne!(
+        zkp.public_key(&sha256).unwrap(),
+        zkp.public_key(&blake3).unwrap()
+    );
+
+    for (username, hash) in [
+        ("sha256_user", PasswordHash::Sha256),
+        ("blake3_user", PasswordHash::Blake3),
+    ] {
+        register_user_with_hash(&mut client, &zkp, "", hash, username, "password")
+            .await
+            .unwrap();
+
+        // Login is not told the hash; the challenge carries the stored choice
+        authenticate_user(
+            &mut client,
+            &zkp,
+            username,
+            "password",
+            &RetryPolicy::default(),
+        )
+        .await
+        .unwrap();
+    }
+
+    let (y1, y2) = zkp.public_key(&sha256).unwrap();
+    let status = client
+        .register(RegisterRequest {
+            user: "md5_user".to_string(),
+            y1: serialization::serialize_biguint(&y1),
+            y2: serialization::serialize_biguint(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            rp_id: String::new(),
+            password_hash: "md5".to_string(),
+            salt: Vec::new(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+/// Plain HTTP/1.0 GET of `path`, returning the whole response
+async fn http_get(addr: std::net::SocketAddr, path: &str) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(format!("GET {} HTTP/1.0\r\n\r\n", path).as_bytes())
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn test_client_rotates_salt_when_asked() {
+    let config = ServerConfig {
+        salt_rotation_age_secs: 1,
+        enable_public_commitment_export: true,
+        ..Default::default()
+    };
+    let server = TestServer::start_with(AuthImpl::with_config(&config).unwrap()).await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+    let retry = RetryPolicy::default();
+
+    register_user(&mut client, &zkp, "rotating_user", "password")
+        .await
+        .unwrap();
+    let registered_y1 = client
+        .get_public_commitment(PublicCommitmentRequest {
+            user: "rotating_user".to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .y1;
+
+    // Once the salt is a second old, logging in also rotates it
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    authenticate_user(&mut client, &zkp, "rotating_user", "password", &retry)
+        .await
+        .unwrap();
+    let rotated_y1 = client
+        .get_public_commitment(PublicCommitmentRequest {
+            user: "rotating_user".to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .y1;
+    assert_ne!(rotated_y1, registered_y1);
+
+    // The next login derives its secret under the new salt
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    authenticate_user(&mut client, &zkp, "rotating_user", "password", &retry)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_counts_authentications() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let metrics_addr = listener.local_addr().unwrap();
+    tokio::spawn(serve_metrics(auth_impl.prometheus.clone(), listener));
+
+    let server = TestServer::start_with(auth_impl).await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+
+    let before = http_get(metrics_addr, "/metrics").await;
+    assert!(before.starts_with("HTTP/1.0 200"));
+    assert!(before.contains("zkp_auth_success_total 0"));
+
+    register_user(&mut client, &zkp, "metrics_user", "password")
+        .await
+        .unwrap();
+    authenticate_user(
+        &mut client,
+        &zkp,
+        "metrics_user",
+        "password",
+        &RetryPolicy::default(),
+    )
+    .await
+    .unwrap();
+
+    let after = http_get(metrics_addr, "/metrics").await;
+    for line in [
+        "zkp_registrations_total 1",
+        "zkp_challenges_total 1",
+        "zkp_auth_success_total 1",
+        "zkp_auth_failure_total 0",
+        "zkp_verify_duration_seconds_count 1",
+    ] {
+        assert!(after.contains(line), "missing {:?} in {}", line, after);
+    }
+
+    assert!(http_get(metrics_addr, "/other")
+        .await
+        .starts_with("HTTP/1.0 404"));
+}
+
+#[tokio::test]
+async fn test_idle_connections_are_closed() {
+    use tokio::io::AsyncReadExt;
+
+    let config = ServerConfig {
+        http2_keepalive_interval_secs: Some(1),
+        http2_keepalive_timeout_secs: Some(5),
+        max_connection_idle_secs: Some(1),
+        ..Default::default()
+    };
+    let server = TestServer::start_with_connection_config(AuthImpl::new().unwrap(), &config).await;
+
+    // A connection that never sends an RPC is closed once it has been idle
+    let mut socket = tokio::net::TcpStream::connect(server.addr()).await.unwrap();
+    let start = std::time::Instant::now();
+    let mut buf = [0u8; 64];
+    let read = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match socket.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+    })
+    .await;
+    assert!(read.is_ok(), "idle connection was not closed");
+    assert!(start.elapsed() >= Duration::from_millis(900));
+
+    // Clients reconnect transparently after their connection is reaped
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+    register_user(&mut client, &zkp, "idle_user_1", "password")
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    register_user(&mut client, &zkp, "idle_user_2", "password")
+        .await
+        .unwrap();
+}
+
+/// Peer address logged for the registration RPC, from a JSON log capture
+fn registration_peer(logs: &CapturedLogs) -> String {
+    logs.contents()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .find(|event| event["fields"]["event"] == "registration")
+        .and_then(|event| event["fields"]["peer"].as_str().map(String::from))
+        .expect("No registration event")
+}
+
+#[tokio::test]
+async fn test_registration_logs_the_peer_address() {
+    let logs = CapturedLogs::default();
+    let _guard = logs.set_default("info");
+
+    let server = TestServer::start().await;
+    let (channel, local_addr) = server.connect_from().await;
+    let mut client = AuthClient::new(channel);
+    register_user(
+        &mut client,
+        &ZKP::new(None).unwrap(),
+        "peer_user",
+        "password",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(local_addr.ip().to_string(), "127.0.0.1");
+    assert_eq!(registration_peer(&logs), local_addr.to_string());
+}
+
+#[tokio::test]
+async fn test_idle_reaping_keeps_the_peer_address() {
+    let logs = CapturedLogs::default();
+    let _guard = logs.set_default("info");
+
+    let config = ServerConfig {
+        max_connection_idle_secs: Some(60),
+        ..Default::default()
+    };
+    let server = TestServer::start_with_connection_config(AuthImpl::new().unwrap(), &config).await;
+    let (channel, local_addr) = server.connect_from().await;
+    let mut client = AuthClient::new(channel);
+    register_user(
+        &mut client,
+        &ZKP::new(None).unwrap(),
+        "peer_user",
+        "password",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(registration_peer(&logs), local_addr.to_string());
+}
+
+#[tokio::test]
+async fn test_rpc_latency_layer_records_each_method() {
+    let auth_impl = Arc::new(AuthImpl::new().unwrap());
+    let server = TestServer::start_with_rpc_latency(auth_impl.clone()).await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+
+    register_user(&mut client, &zkp, "timed_user", "password")
+        .await
+        .unwrap();
+    authenticate_user(
+        &mut client,
+        &zkp,
+        "timed_user",
+        "password",
+        &RetryPolicy::default(),
+    )
+    .await
+    .unwrap();
+
+    let latency = auth_impl.metrics_snapshot().await.rpc_latency;
+    for method in [
+        "Register",
+        "CreateAuthenticationChallenge",
+        "VerifyAuthentication",
+    ] {
+        let summary = latency
+            .iter()
+            .find(|summary| summary.method == method)
+            .unwrap_or_else(|| panic!("no latency recorded for {}", method));
+        assert_eq!(summary.count, 1);
+        assert!(summary.p50_us <= summary.p95_us && summary.p95_us <= summary.p99_us);
+    }
+}
+
+#[tokio::test]
+async fn test_tls_server_authenticates_over_tls_only() {
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+    let config = ServerConfig {
+        tls_cert_path: Some(format!("{}/server.pem", fixtures)),
+        tls_key_path: Some(format!("{}/server.key", fixtures)),
+        ..Default::default()
+    };
+    let tls = config.tls_config().unwrap().expect("TLS should be enabled");
+    let server = TestServer::start_tls(AuthImpl::with_config(&config).unwrap(), tls).await;
+
+    let ca = std::fs::read(format!("{}/server.pem", fixtures)).unwrap();
+    let channel = Endpoint::from_shared(format!("https://localhost:{}", server.addr().port()))
+        .unwrap()
+        .tls_config(
+            ClientTlsConfig::new()
+                .ca_certificate(Certificate::from_pem(ca))
+                .domain_name("localhost"),
+        )
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = AuthClient::new(channel);
+    let zkp = ZKP::new(None).unwrap();
+    register_user(&mut client, &zkp, "tls_user", "password")
+        .await
+        .unwrap();
+    let session_id = authenticate_user(
+        &mut client,
+        &zkp,
+        "tls_user",
+        "password",
+        &RetryPolicy::default(),
+    )
+    .await
+    .unwrap();
+    assert!(!session_id.is_empty());
+
+    // Plaintext clients cannot talk to it
+    let mut plaintext = AuthClient::connect(server.endpoint()).await.unwrap();
+    assert!(plaintext
+        .get_server_info(ServerInfoRequest {})
+        .await
+        .is_err());
+}
+
+#[test]
+fn test_tls_requires_both_cert_and_key() {
+    let config = ServerConfig {
+        tls_cert_path: Some("server.pem".to_string()),
+        ..Default::default()
+    };
+    let error = config.tls_config().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "tls_cert_path and tls_key_path must be set together"
+    );
+    assert!(config
+        .validate()
+        .unwrap_err()
+        .to_string()
+        .contains("tls_cert_path and tls_key_path must be set together"));
+
+    // Plaintext stays the default
+    assert!(ServerConfig::default().tls_config().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_bulk_registration_of_parallel_packages() {
+    let server = TestServer::start().await;
+    let mut client = AuthClient::connect(server.endpoint()).await.unwrap();
+    let zkp = ZKP::new(None).unwrap();
+
+    let users: Vec<(String, String)> = (0..100)
+        .map(|i| (format!("bulk_user_{}", i), format!("password_{}", i)))
+        .collect();
+    let user_ids = register_users_bulk(&client, &zkp, &users).await.unwrap();
+    assert_eq!(user_ids.len(), 100);
+    let distinct: std::collections::HashSet<_> = user_ids.iter().collect();
+    assert_eq!(distinct.len(), 100);
+
+    // Each package carries the public key for its own password
+    let session_id = authenticate_user(
+        &mut client,
+        &zkp,
+        "bulk_user_42",
+        "password_42",
+        &RetryPolicy::default(),
+    )
+    .await
+    .unwrap();
+    assert!(!session_id.is_empty());
+}
+
+#[tokio::test]
+async fn test_non_interactive_client_binary() {
+    let server = TestServer::start().await;
+    let run_client = |command: &'static str, password: Option<&'static str>| {
+        let endpoint = server.endpoint();
+        tokio::task::spawn_blocking(move || {
+            let mut client = std::process::Command::new(env!("CARGO_BIN_EXE_client"));
+            client
+                .args([command, "--non-interactive", "--server", &endpoint])
+                .args(["--username", "scripted_user"])
+                .env_remove("ZKP_PASSWORD")
+                .stdin(std::process::Stdio::null());
+            if let Some(password) = password {
+                client.env("ZKP_PASSWORD", password);
+            }
+            client.output().unwrap()
+        })
+    };
+
+    let output = run_client("register", Some("password")).await.unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("User ID: "));
+
+    // Repeated logins work without registering again
+    for _ in 0..2 {
+        let output = run_client("login", Some("password")).await.unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        assert!(String::from_utf8_lossy(&output.stdout).contains("Session ID: "));
+        // The server allows one challenge per second per user
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+    }
+
+    // Failures exit non-zero instead of prompting
+    let output = run_client("register", Some("password")).await.unwrap();
+    assert!(!output.status.success(), "re-registration should fail");
+    let output = run_client("login", Some("wrong")).await.unwrap();
+    assert!(!output.status.success());
+    let output = run_client("login", None).await.unwrap();
+    assert!(!output.status.success());
+}
+
+#[tokio::test]
+async fn test_client_binary_aborts_on_group_checksum_mismatch() {
+    let server = TestServer::start().await;
+    let register = |checksum: String| {
+        let endpoint = server.endpoint();
+        tokio::task::spawn_blocking(move || {
+            std::process::Command::new(env!("CARGO_BIN_EXE_client"))
+                .args(["register", "--non-interactive", "--server", &endpoint])
+                .args([
+                    "--username",
+                    "pinned_user",
+                    "--expect-group-checksum",
+                    &checksum,
+                ])
+                .env("ZKP_PASSWORD", "password")
+                .stdin(std::process::Stdio::null())
+                .output()
+                .unwrap()
+        })
+    };
+
+    let output = register("00".repeat(32)).await.unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Group checksum mismatch"));
+
+    // Nothing was registered, so the same user can still register under the right pin
+    let output = register(hex::encode(ZKP::constants_checksum()))
+        .await
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[tokio::test]
+async fn test_server_binary_shuts_down_on_sigterm() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    // The server reads config/server.toml relative to its working directory
+    let dir = std::env::temp_dir().join(format!("zkp-server-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("config")).unwrap();
+    std::fs::write(
+        dir.join("config/server.toml"),
+        format!(
+            "host = \"127.0.0.1\"\nport = {}\nuser_store_path = \"users.json\"\n",
+            port
+        ),
+    )
+    .unwrap();
+    let mut server = std::process::Command::new(env!("CARGO_BIN_EXE_server"))
+        .current_dir(&dir)
+        .env("RUST_LOG", "info")
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut client = loop {
+        match AuthClient::connect(format!("http://127.0.0.1:{}", port)).await {
+            Ok(client) => break client,
+            Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+        }
+    };
+    let zkp = ZKP::new(None).unwrap();
+    register_user(&mut client, &zkp, "shutdown_user", "password")
+        .await
+        .unwrap();
+
+    let status = std::process::Command::new("kill")
+        .args(["-TERM", &server.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = tokio::task::spawn_blocking(move || {
+        let status = server.wait().unwrap();
+        let mut stdout = String::new();
+        std::io::Read::read_to_string(&mut server.stdout.take().unwrap(), &mut stdout).unwrap();
+        (status, stdout)
+    })
+    .await
+    .unwrap();
+    assert!(output.0.success(), "{:?}", output);
+    assert!(output.1.contains("Server shutdown gracefully"));
+
+    // State was flushed on the way out
+    let stored = std::fs::read_to_string(dir.join("users.json")).unwrap();
+    assert!(stored.contains("shutdown_user"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
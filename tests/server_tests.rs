@@ -0,0 +1,1983 @@
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+use tonic::{Code, Request, Status};
+use zkp::{
+    pow, serialization, ProofTranscript, PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS, ZKP,
+};
+
+mod common;
+
+use common::{
+    server::{
+        check_config, group_id, session_hash,
+        zkp_auth::{
+            auth_server::Auth, AuthenticationAnswerRequest, AuthenticationAnswerResponse,
+            AuthenticationChallengeRequest, AuthenticationChallengeResponse, EchoRequest,
+            PublicCommitmentRequest, RegisterRequest, ServerInfoRequest, UpdateCredentialsRequest,
+            ValidateSessionRequest,
+        },
+        AuthImpl, GroupConfig, LatencyTracker, ServerConfig,
+    },
+    CapturedLogs,
+};
+
+/// Register the deterministic user for `index`, returning its name and secret
+async fn register_deterministic_user(auth_impl: &AuthImpl, index: u64) -> (String, BigUint) {
+    let (username, x, y1, y2) = auth_impl.active_group().deterministic_user(index);
+
+    let request = Request::new(RegisterRequest {
+        user: username.clone(),
+        y1: serialization::serialize_biguint(&y1),
+        y2: serialization::serialize_biguint(&y2),
+        protocol_version: PROTOCOL_VERSION,
+        rp_id: String::new(),
+        password_hash: String::new(),
+        salt: Vec::new(),
+    });
+    auth_impl.register(request).await.unwrap();
+
+    (username, x)
+}
+
+/// Run a full challenge/answer round for `username` proving knowledge of `x`
+async fn authenticate(
+    auth_impl: &AuthImpl,
+    username: &str,
+    x: &BigUint,
+) -> Result<AuthenticationAnswerResponse, Status> {
+    let group_id = auth_impl.user_info.read().await[username].group_id.clone();
+    let zkp = auth_impl.group(&group_id).unwrap();
+    // k = 0 gives r1 = r2 = 1, which the server rejects; likely in toy groups
+    let k = ZKP::generate_random_number_below(&(&zkp.q - 1u32)).unwrap() + 1u32;
+    let (r1, r2) = zkp.compute_pair(&k).unwrap();
+
+    let challenge = auth_impl
+        .create_authentication_challenge(Request::new(AuthenticationChallengeRequest {
+            user: username.to_string(),
+            r1: serialization::serialize_biguint(&r1),
+            r2: serialization::serialize_biguint(&r2),
+            protocol_version: PROTOCOL_VERSION,
+            pow_nonce: 0,
+            rp_id: String::new(),
+        }))
+        .await?
+        .into_inner();
+
+    let c = serialization::deserialize_biguint(&challenge.c).unwrap();
+    let s = zkp.solve(&k, &c, x).unwrap();
+
+    let answer = auth_impl
+        .verify_authentication(Request::new(AuthenticationAnswerRequest {
+            auth_id: challenge.auth_id,
+            s: serialization::serialize_biguint(&s),
+        }))
+        .await?
+        .into_inner();
+
+    Ok(answer)
+}
+
+/// Request a challenge for `username`, returning the auth id and the solved answer
+async fn solved_challenge(auth_impl: &AuthImpl, username: &str, x: &BigUint) -> (String, BigUint) {
+    let zkp = auth_impl.active_group();
+    let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+    let (r1, r2) = zkp.compute_pair(&k).unwrap();
+
+    let challenge = auth_impl
+        .create_authentication_challenge(Request::new(AuthenticationChallengeRequest {
+            user: username.to_string(),
+            r1: serialization::serialize_biguint(&r1),
+            r2: serialization::serialize_biguint(&r2),
+            protocol_version: PROTOCOL_VERSION,
+            pow_nonce: 0,
+            rp_id: String::new(),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+
+    let c = serialization::deserialize_biguint(&challenge.c).unwrap();
+    (challenge.auth_id, zkp.solve(&k, &c, x).unwrap())
+}
+
+/// Server-side behaviour tests that drive `AuthImpl` directly
+#[test]
+fn test_latency_tracker_p99() {
+    let tracker = LatencyTracker::new(Duration::from_secs(60), Some(Duration::from_millis(50)));
+    assert_eq!(tracker.p99(), None);
+    assert!(!tracker.should_shed());
+
+    for ms in 1..=100 {
+        tracker.record(Duration::from_millis(ms));
+    }
+
+    assert_eq!(tracker.p99(), Some(Duration::from_millis(99)));
+    assert!(tracker.should_shed());
+}
+
+#[test]
+fn test_latency_tracker_requires_budget_and_samples() {
+    let unbounded = LatencyTracker::new(Duration::from_secs(60), None);
+    for _ in 0..LatencyTracker::MIN_SAMPLES {
+        unbounded.record(Duration::from_secs(1));
+    }
+    assert!(!unbounded.should_shed());
+
+    let bounded = LatencyTracker::new(Duration::from_secs(60), Some(Duration::from_millis(1)));
+    for _ in 0..LatencyTracker::MIN_SAMPLES - 1 {
+        bounded.record(Duration::from_secs(1));
+    }
+    assert!(!bounded.should_shed());
+}
+
+#[tokio::test]
+async fn test_verify_sheds_load_when_p99_over_budget() {
+    let config = ServerConfig {
+        verify_p99_budget_ms: Some(10),
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+
+    // Simulate a burst of slow verifications
+    for _ in 0..LatencyTracker::MIN_SAMPLES {
+        auth_impl.verify_latency.record(Duration::from_millis(500));
+    }
+
+    let request = Request::new(AuthenticationAnswerRequest {
+        auth_id: "any".to_string(),
+        s: vec![1],
+    });
+
+    let status = auth_impl.verify_authentication(request).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+}
+
+#[tokio::test]
+async fn test_registered_commitments_are_stored_compressed() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let (username, _) = register_deterministic_user(&auth_impl, 0).await;
+    let (_, _, y1, y2) = auth_impl.active_group().deterministic_user(0);
+
+    let user_info_map = auth_impl.user_info.read().await;
+    let stored = user_info_map.get(&username).unwrap();
+    assert_eq!(stored.y1.to_biguint(), y1);
+    assert_eq!(stored.y2.to_biguint(), y2);
+
+    // A BigUint keeps its value in 64-bit limbs behind a Vec header
+    let biguint_footprint =
+        |value: &BigUint| std::mem::size_of::<BigUint>() + value.iter_u64_digits().len() * 8;
+    let full = biguint_footprint(&y1) + biguint_footprint(&y2);
+    let compressed = stored.y1.memory_footprint() + stored.y2.memory_footprint();
+
+    // Minimal bytes behind a boxed-slice header, with no limb padding
+    let header = std::mem::size_of::<serialization::CompressedBigUint>();
+    assert_eq!(header, 2 * std::mem::size_of::<usize>());
+    assert_eq!(
+        compressed,
+        2 * header + y1.to_bytes_be().len() + y2.to_bytes_be().len()
+    );
+    // Each value saves at least the Vec capacity word over a BigUint
+    assert!(full - compressed >= 2 * std::mem::size_of::<usize>());
+}
+
+fn corrupted_group() -> ZKP {
+    let mut zkp = ZKP::new(None).unwrap();
+    zkp.alpha = BigUint::from(1u32);
+    zkp
+}
+
+#[test]
+fn test_corrupted_group_reports_diagnostic() {
+    let err = AuthImpl::with_group(corrupted_group(), &ServerConfig::default()).unwrap_err();
+    let message = err.to_string();
+
+    assert!(message.contains("Generators must be greater than 1"));
+    assert!(message.contains("p: 1024 bits"));
+    assert!(message.contains("q: 160 bits"));
+    assert!(message.contains("alpha: 1 bits"));
+}
+
+#[test]
+fn test_corrupted_group_falls_back_to_configured_group() {
+    let (alpha, beta, p, q) = ZKP::get_constants();
+    let config = ServerConfig {
+        fallback_group: Some(GroupConfig {
+            p: hex::encode(p.to_bytes_be()),
+            q: hex::encode(q.to_bytes_be()),
+            alpha: hex::encode(alpha.to_bytes_be()),
+            beta: hex::encode(beta.to_bytes_be()),
+        }),
+        ..Default::default()
+    };
+
+    let auth_impl = AuthImpl::with_group(corrupted_group(), &config).unwrap();
+    let zkp = auth_impl.active_group();
+    assert_eq!(zkp.alpha, alpha);
+    assert_eq!(zkp.beta, beta);
+    assert_eq!(zkp.p, p);
+    assert_eq!(zkp.q, q);
+}
+
+#[test]
+fn test_binding_challenge_is_reproducible_per_commitment() {
+    let config = ServerConfig {
+        deterministic_challenge_binding: true,
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let (_, _, r1, r2) = auth_impl.active_group().deterministic_user(1);
+    let (_, _, other_r1, other_r2) = auth_impl.active_group().deterministic_user(2);
+
+    let zkp = auth_impl.active_group();
+
+    let c = auth_impl
+        .generate_challenge(&zkp, "alice", &r1, &r2)
+        .unwrap();
+    assert_eq!(
+        c,
+        auth_impl
+            .generate_challenge(&zkp, "alice", &r1, &r2)
+            .unwrap()
+    );
+    assert_ne!(
+        c,
+        auth_impl
+            .generate_challenge(&zkp, "alice", &other_r1, &other_r2)
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_configured_scopes_propagate_to_session() {
+    let config = ServerConfig {
+        default_scopes: vec!["read".to_string(), "write".to_string()],
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+
+    let answer = authenticate(&auth_impl, &username, &x).await.unwrap();
+    assert!(!answer.session_id.is_empty());
+    assert_eq!(answer.scopes, vec!["read", "write"]);
+}
+
+#[tokio::test]
+async fn test_registration_rejected_past_user_capacity() {
+    let config = ServerConfig {
+        max_users: Some(1),
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+
+    let (extra_user, _, y1, y2) = auth_impl.active_group().deterministic_user(1);
+    let status = auth_impl
+        .register(Request::new(RegisterRequest {
+            user: extra_user,
+            y1: serialization::serialize_biguint(&y1),
+            y2: serialization::serialize_biguint(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            rp_id: String::new(),
+            password_hash: String::new(),
+            salt: Vec::new(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(status.message(), "user capacity reached");
+
+    // Existing users are unaffected by the cap
+    assert!(authenticate(&auth_impl, &username, &x).await.is_ok());
+
+    let snapshot = auth_impl.metrics_snapshot().await;
+    assert_eq!(snapshot.registered_users, 1);
+    assert_eq!(snapshot.max_users, Some(1));
+}
+
+#[tokio::test]
+async fn test_public_commitment_export() {
+    let config = ServerConfig {
+        enable_public_commitment_export: true,
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let (username, _) = register_deterministic_user(&auth_impl, 0).await;
+    let (_, _, y1, y2) = auth_impl.active_group().deterministic_user(0);
+
+    let response = auth_impl
+        .get_public_commitment(Request::new(PublicCommitmentRequest { user: username }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(
+        serialization::deserialize_biguint(&response.y1).unwrap(),
+        y1
+    );
+    assert_eq!(
+        serialization::deserialize_biguint(&response.y2).unwrap(),
+        y2
+    );
+
+    let status = auth_impl
+        .get_public_commitment(Request::new(PublicCommitmentRequest {
+            user: "unknown".to_string(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_public_commitment_export_disabled_by_default() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let (username, _) = register_deterministic_user(&auth_impl, 0).await;
+
+    let status = auth_impl
+        .get_public_commitment(Request::new(PublicCommitmentRequest { user: username }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+}
+
+/// Toy group from the library tests, valid but distinct from the default one
+fn toy_group() -> ZKP {
+    ZKP {
+        p: BigUint::from(23u32),
+        q: BigUint::from(11u32),
+        alpha: BigUint::from(4u32),
+        beta: BigUint::from(9u32),
+    }
+}
+
+#[tokio::test]
+async fn test_group_rotation_swaps_active_group() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let original_group_id = group_id(&auth_impl.active_group());
+    let (old_user, old_x) = register_deterministic_user(&auth_impl, 0).await;
+
+    let event = auth_impl.rotate_group(toy_group()).unwrap();
+    assert_eq!(event.previous_group_id, original_group_id);
+    assert_eq!(event.new_group_id, group_id(&toy_group()));
+    assert_eq!(group_id(&auth_impl.active_group()), event.new_group_id);
+
+    let events = auth_impl.rotation_events.lock().unwrap().clone();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].new_group_id, event.new_group_id);
+
+    // New registrations land on the new group, old ones keep theirs.
+    // Index 2 maps to a non-zero secret in the toy group.
+    let (new_user, new_x) = register_deterministic_user(&auth_impl, 2).await;
+    {
+        let user_info_map = auth_impl.user_info.read().await;
+        assert_eq!(user_info_map[&old_user].group_id, original_group_id);
+        assert_eq!(user_info_map[&new_user].group_id, event.new_group_id);
+    }
+
+    assert!(authenticate(&auth_impl, &old_user, &old_x).await.is_ok());
+    assert!(authenticate(&auth_impl, &new_user, &new_x).await.is_ok());
+}
+
+#[test]
+fn test_group_rotation_rejects_invalid_group() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let active = group_id(&auth_impl.active_group());
+
+    assert!(auth_impl.rotate_group(corrupted_group()).is_err());
+    assert_eq!(group_id(&auth_impl.active_group()), active);
+    assert!(auth_impl.rotation_events.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_swapped_auth_ids_fail_for_both_users() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let (alice, alice_x) = register_deterministic_user(&auth_impl, 0).await;
+    let (bob, bob_x) = register_deterministic_user(&auth_impl, 1).await;
+
+    let (alice_auth_id, alice_s) = solved_challenge(&auth_impl, &alice, &alice_x).await;
+    let (bob_auth_id, bob_s) = solved_challenge(&auth_impl, &bob, &bob_x).await;
+
+    // Corrupt the server state: each auth_id now points at the other user,
+    // who also holds the other user's pending challenge
+    {
+        let mut auth_id_map = auth_impl.auth_id_to_user.write().await;
+        auth_id_map.insert(alice_auth_id.clone(), bob.clone());
+        auth_id_map.insert(bob_auth_id.clone(), alice.clone());
+
+        let mut user_info_map = auth_impl.user_info.write().await;
+        let mut alice_info = user_info_map.remove(&alice).unwrap();
+        let mut bob_info = user_info_map.remove(&bob).unwrap();
+        std::mem::swap(&mut alice_info.r1, &mut bob_info.r1);
+        std::mem::swap(&mut alice_info.r2, &mut bob_info.r2);
+        std::mem::swap(
+            &mut alice_info.challenge_nonce,
+            &mut bob_info.challenge_nonce,
+        );
+        user_info_map.insert(alice, alice_info);
+        user_info_map.insert(bob, bob_info);
+    }
+
+    for (auth_id, s) in [(alice_auth_id, alice_s), (bob_auth_id, bob_s)] {
+        let status = auth_impl
+            .verify_authentication(Request::new(AuthenticationAnswerRequest {
+                auth_id,
+                s: serialization::serialize_biguint(&s),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+    }
+}
+
+#[tokio::test]
+async fn test_failed_verification_produces_failure_report() {
+    let config = ServerConfig {
+        enable_failure_reports: true,
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+    let (auth_id, s) = solved_challenge(&auth_impl, &username, &x).await;
+    let wrong_s = (s + 1u32) % &auth_impl.active_group().q;
+
+    let status = auth_impl
+        .verify_authentication(Request::new(AuthenticationAnswerRequest {
+            auth_id: auth_id.clone(),
+            s: serialization::serialize_biguint(&wrong_s),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    let report = auth_impl.failure_report(&auth_id).unwrap();
+    let user_info_map = auth_impl.user_info.read().await;
+    let user_info = &user_info_map[&username];
+    let hex = |value: &BigUint| hex::encode(value.to_bytes_be());
+
+    assert_eq!(report.auth_id, auth_id);
+    assert_eq!(report.user_id, user_info.user_id);
+    assert_eq!(report.group_id, group_id(&auth_impl.active_group()));
+    assert_eq!(report.c, hex(&user_info.c.as_ref().unwrap().0));
+    assert_eq!(report.s, hex(&wrong_s));
+    assert_eq!(report.expected_r1, hex(user_info.r1.as_ref().unwrap()));
+    assert_eq!(report.expected_r2, hex(user_info.r2.as_ref().unwrap()));
+    assert_ne!(report.recomputed_r1, report.expected_r1);
+    assert_ne!(report.recomputed_r2, report.expected_r2);
+
+    // The registered commitment only shows up as a fingerprint
+    assert_eq!(report.commitment_fingerprint.len(), 64);
+    let json = serde_json::to_string(&report).unwrap();
+    assert!(!json.contains(&hex(&user_info.y1.to_biguint())));
+    assert!(!json.contains(&hex(&user_info.y2.to_biguint())));
+}
+
+#[tokio::test]
+async fn test_failure_reports_disabled_by_default() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+    let (auth_id, s) = solved_challenge(&auth_impl, &username, &x).await;
+    let wrong_s = (s + 1u32) % &auth_impl.active_group().q;
+
+    let request = Request::new(AuthenticationAnswerRequest {
+        auth_id: auth_id.clone(),
+        s: serialization::serialize_biguint(&wrong_s),
+    });
+    assert!(auth_impl.verify_authentication(request).await.is_err());
+    assert!(auth_impl.failure_report(&auth_id).is_none());
+}
+
+#[tokio::test]
+async fn test_failed_attempts_and_lock_survive_restart() {
+    let store_path = std::env::temp_dir().join(format!("zkp-users-{}.json", uuid::Uuid::new_v4()));
+    let config = ServerConfig {
+        user_store_path: Some(store_path.to_string_lossy().into_owned()),
+        max_failed_attempts: Some(1),
+        ..Default::default()
+    };
+
+    let (username, x) = {
+        let auth_impl = AuthImpl::with_config(&config).unwrap();
+        let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+        let (auth_id, s) = solved_challenge(&auth_impl, &username, &x).await;
+        let wrong_s = (s + 1u32) % &auth_impl.active_group().q;
+        let request = Request::new(AuthenticationAnswerRequest {
+            auth_id,
+            s: serialization::serialize_biguint(&wrong_s),
+        });
+        assert!(auth_impl.verify_authentication(request).await.is_err());
+        auth_impl.flush().await;
+        (username, x)
+    };
+
+    // A fresh instance reloads the user at the lockout threshold
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    {
+        let user_info_map = auth_impl.user_info.read().await;
+        let user_info = &user_info_map[&username];
+        assert_eq!(user_info.failed_attempts, 1);
+        assert!(user_info.is_locked(chrono::Utc::now()));
+    }
+
+    let status = authenticate(&auth_impl, &username, &x).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    assert_eq!(status.message(), "account locked");
+
+    std::fs::remove_file(store_path).unwrap();
+}
+
+#[tokio::test]
+async fn test_lockout_triggers_and_clears_after_cooldown() {
+    let auth_impl = AuthImpl::with_config(&ServerConfig {
+        max_failed_attempts: Some(2),
+        ..Default::default()
+    })
+    .unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+
+    for _ in 0..2 {
+        // Skip the one-challenge-per-second rate limit between attempts
+        if let Some(user_info) = auth_impl.user_info.write().await.get_mut(&username) {
+            user_info.last_challenge_timestamp = None;
+        }
+        let (auth_id, s) = solved_challenge(&auth_impl, &username, &x).await;
+        let wrong_s = (s + 1u32) % &auth_impl.active_group().q;
+        let request = Request::new(AuthenticationAnswerRequest {
+            auth_id,
+            s: serialization::serialize_biguint(&wrong_s),
+        });
+        assert!(auth_impl.verify_authentication(request).await.is_err());
+    }
+
+    let status = authenticate(&auth_impl, &username, &x).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    assert_eq!(status.message(), "account locked");
+
+    // Let the cooldown run out
+    if let Some(user_info) = auth_impl.user_info.write().await.get_mut(&username) {
+        user_info.locked_until = Some(chrono::Utc::now() - chrono::Duration::seconds(1));
+        user_info.last_challenge_timestamp = None;
+    }
+
+    authenticate(&auth_impl, &username, &x).await.unwrap();
+    let user_info_map = auth_impl.user_info.read().await;
+    assert_eq!(user_info_map[&username].failed_attempts, 0);
+    assert_eq!(user_info_map[&username].locked_until, None);
+}
+
+#[tokio::test]
+async fn test_old_salt_is_flagged_for_rotation_on_login() {
+    let auth_impl = AuthImpl::with_config(&ServerConfig {
+        salt_rotation_age_secs: 60 * 60,
+        ..Default::default()
+    })
+    .unwrap();
+    let (fresh_user, fresh_x) = register_deterministic_user(&auth_impl, 0).await;
+    let (old_user, old_x) = register_deterministic_user(&auth_impl, 1).await;
+    if let Some(user_info) = auth_impl.user_info.write().await.get_mut(&old_user) {
+        user_info.salt_updated_at = chrono::Utc::now() - chrono::Duration::hours(2);
+    }
+
+    let answer = authenticate(&auth_impl, &fresh_user, &fresh_x)
+        .await
+        .unwrap();
+    assert!(!answer.rotate_salt);
+    let answer = authenticate(&auth_impl, &old_user, &old_x).await.unwrap();
+    assert!(answer.rotate_salt);
+}
+
+#[tokio::test]
+async fn test_credential_updates_need_a_fresh_rotation_token() {
+    let auth_impl = AuthImpl::with_config(&ServerConfig {
+        salt_rotation_age_secs: 60 * 60,
+        ..Default::default()
+    })
+    .unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+    let (_, _, new_y1, new_y2) = auth_impl.active_group().deterministic_user(1);
+    let age_salt = || async {
+        let mut user_info_map = auth_impl.user_info.write().await;
+        let user_info = user_info_map.get_mut(&username).unwrap();
+        user_info.salt_updated_at = chrono::Utc::now() - chrono::Duration::hours(2);
+        user_info.last_challenge_timestamp = None;
+    };
+    let update = |session_id: &str, rotation_token: &str| {
+        auth_impl.update_credentials(Request::new(UpdateCredentialsRequest {
+            session_id: session_id.to_string(),
+            y1: serialization::serialize_biguint(&new_y1),
+            y2: serialization::serialize_biguint(&new_y2),
+            salt: vec![7; 16],
+            password_hash: String::new(),
+            rotation_token: rotation_token.to_string(),
+        }))
+    };
+
+    // A login that does not flag rotation hands out no token
+    let answer = authenticate(&auth_impl, &username, &x).await.unwrap();
+    assert!(!answer.rotate_salt);
+    assert!(answer.rotation_token.is_empty());
+    let status = update(&answer.session_id, "").await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    // A session alone is not enough, and a wrong guess spends the token
+    age_salt().await;
+    let answer = authenticate(&auth_impl, &username, &x).await.unwrap();
+    assert!(answer.rotate_salt);
+    assert!(!answer.rotation_token.is_empty());
+    let status = update(&answer.session_id, "guess").await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    let status = update(&answer.session_id, &answer.rotation_token)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    // The token of a fresh login works exactly once
+    age_salt().await;
+    let answer = authenticate(&auth_impl, &username, &x).await.unwrap();
+    update(&answer.session_id, &answer.rotation_token)
+        .await
+        .unwrap();
+    assert_eq!(
+        auth_impl.user_info.read().await[&username].y1.to_biguint(),
+        new_y1
+    );
+    let status = update(&answer.session_id, &answer.rotation_token)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+}
+
+#[tokio::test]
+async fn test_stored_legacy_group_ids_are_migrated() {
+    let store_path = std::env::temp_dir().join(format!("zkp-users-{}.json", uuid::Uuid::new_v4()));
+    let config = ServerConfig {
+        user_store_path: Some(store_path.to_string_lossy().into_owned()),
+        ..Default::default()
+    };
+
+    let (username, x, legacy_id) = {
+        let auth_impl = AuthImpl::with_config(&config).unwrap();
+        let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+        auth_impl.flush().await;
+        let legacy_id = hex::encode(auth_impl.active_group().legacy_group_checksum());
+        (username, x, legacy_id)
+    };
+
+    // Rewrite the store as a version before the canonical checksum would have
+    let json = std::fs::read_to_string(&store_path).unwrap();
+    let mut users: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+    users[0]["group_id"] = legacy_id.into();
+    std::fs::write(&store_path, serde_json::to_string(&users).unwrap()).unwrap();
+
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    assert_eq!(
+        auth_impl.user_info.read().await[&username].group_id,
+        group_id(&auth_impl.active_group())
+    );
+    authenticate(&auth_impl, &username, &x).await.unwrap();
+
+    std::fs::remove_file(store_path).unwrap();
+}
+
+#[tokio::test]
+async fn test_live_sessions_are_restored_after_restart() {
+    let dir = std::env::temp_dir();
+    let user_store_path = dir.join(format!("zkp-users-{}.json", uuid::Uuid::new_v4()));
+    let session_store_path = dir.join(format!("zkp-sessions-{}.json", uuid::Uuid::new_v4()));
+    let config = ServerConfig {
+        user_store_path: Some(user_store_path.to_string_lossy().into_owned()),
+        session_store_path: Some(session_store_path.to_string_lossy().into_owned()),
+        session_ttl_secs: 60 * 60,
+        ..Default::default()
+    };
+
+    let (live_user, expired_user, live_session) = {
+        let auth_impl = AuthImpl::with_config(&config).unwrap();
+        let (live_user, live_x) = register_deterministic_user(&auth_impl, 0).await;
+        let (expired_user, expired_x) = register_deterministic_user(&auth_impl, 1).await;
+        let live_session = authenticate(&auth_impl, &live_user, &live_x)
+            .await
+            .unwrap()
+            .session_id;
+        authenticate(&auth_impl, &expired_user, &expired_x)
+            .await
+            .unwrap();
+        auth_impl.flush().await;
+        (live_user, expired_user, live_session)
+    };
+
+    // Only hashes of the session ids are written out
+    let json = std::fs::read_to_string(&session_store_path).unwrap();
+    assert!(!json.contains(&live_session));
+    assert!(json.contains(&session_hash(&live_session)));
+
+    // Backdate one session past the TTL and add an entry that fails to parse
+    let mut sessions: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+    for session in &mut sessions {
+        if session["user_name"] == expired_user.as_str() {
+            session["issued_at"] = (chrono::Utc::now() - chrono::Duration::days(2))
+                .to_rfc3339()
+                .into();
+        }
+    }
+    sessions.push(serde_json::json!({ "user_name": 42 }));
+    std::fs::write(
+        &session_store_path,
+        serde_json::to_string(&sessions).unwrap(),
+    )
+    .unwrap();
+
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    {
+        let user_info_map = auth_impl.user_info.read().await;
+        let restored = user_info_map[&live_user].session_hash.as_ref().unwrap();
+        assert_eq!(restored.0, session_hash(&live_session));
+        assert!(user_info_map[&expired_user].session_hash.is_none());
+    }
+    let response = auth_impl
+        .validate_session(Request::new(ValidateSessionRequest {
+            session_id: live_session,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.valid);
+    assert_eq!(response.user, live_user);
+
+    std::fs::remove_file(user_store_path).unwrap();
+    std::fs::remove_file(session_store_path).unwrap();
+}
+
+#[tokio::test]
+async fn test_sweep_removes_old_never_authenticated_accounts() {
+    let auth_impl = AuthImpl::with_config(&ServerConfig {
+        unverified_account_ttl_secs: 60 * 60,
+        reject_duplicate_public_keys: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let (stale_user, _) = register_deterministic_user(&auth_impl, 0).await;
+    let (verified_user, verified_x) = register_deterministic_user(&auth_impl, 1).await;
+    let (fresh_user, _) = register_deterministic_user(&auth_impl, 2).await;
+    authenticate(&auth_impl, &verified_user, &verified_x)
+        .await
+        .unwrap();
+
+    // Age every account but the fresh one past the TTL
+    {
+        let mut user_info_map = auth_impl.user_info.write().await;
+        for user_name in [&stale_user, &verified_user] {
+            user_info_map
+                .get_mut(user_name)
+                .unwrap()
+                .registration_timestamp = chrono::Utc::now() - chrono::Duration::hours(2);
+        }
+    }
+
+    assert_eq!(
+        auth_impl.sweep_unverified_accounts().await,
+        vec![stale_user.clone()]
+    );
+    let user_info_map = auth_impl.user_info.read().await;
+    assert!(user_info_map.get(&stale_user).is_none());
+    assert!(user_info_map.get(&verified_user).is_some());
+    assert!(user_info_map.get(&fresh_user).is_some());
+    drop(user_info_map);
+
+    // The removed account's public key can be registered again
+    register_deterministic_user(&auth_impl, 0).await;
+}
+
+#[tokio::test]
+async fn test_sweep_keeps_users_from_stores_without_login_tracking() {
+    let store_path = std::env::temp_dir().join(format!("zkp-users-{}.json", uuid::Uuid::new_v4()));
+    let config = ServerConfig {
+        user_store_path: Some(store_path.to_string_lossy().into_owned()),
+        unverified_account_ttl_secs: 60 * 60,
+        ..Default::default()
+    };
+
+    let (legacy_user, never_user) = {
+        let auth_impl = AuthImpl::with_config(&config).unwrap();
+        let (legacy_user, _) = register_deterministic_user(&auth_impl, 0).await;
+        let (never_user, _) = register_deterministic_user(&auth_impl, 1).await;
+        auth_impl.flush().await;
+        (legacy_user, never_user)
+    };
+
+    // Age both accounts past the TTL and drop the login field from one, as
+    // a store written before logins were tracked would
+    let registered_at = chrono::Utc::now() - chrono::Duration::hours(2);
+    let json = std::fs::read_to_string(&store_path).unwrap();
+    let mut users: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+    for user in users.iter_mut() {
+        user["registration_timestamp"] = serde_json::to_value(registered_at).unwrap();
+        assert!(user["last_successful_auth"].is_null());
+        if user["user_name"] == legacy_user.as_str() {
+            user.as_object_mut().unwrap().remove("last_successful_auth");
+        }
+    }
+    std::fs::write(&store_path, serde_json::to_string(&users).unwrap()).unwrap();
+
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    assert_eq!(
+        auth_impl.sweep_unverified_accounts().await,
+        vec![never_user.clone()]
+    );
+    let user_info_map = auth_impl.user_info.read().await;
+    assert_eq!(
+        user_info_map[&legacy_user].last_successful_auth,
+        Some(registered_at)
+    );
+    assert!(user_info_map.get(&never_user).is_none());
+    drop(user_info_map);
+
+    std::fs::remove_file(store_path).unwrap();
+}
+
+#[tokio::test]
+async fn test_unverified_account_sweep_disabled_by_zero_ttl() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let (user_name, _) = register_deterministic_user(&auth_impl, 0).await;
+    auth_impl
+        .user_info
+        .write()
+        .await
+        .get_mut(&user_name)
+        .unwrap()
+        .registration_timestamp = chrono::Utc::now() - chrono::Duration::days(365);
+
+    assert!(auth_impl.sweep_unverified_accounts().await.is_empty());
+    assert!(auth_impl.user_info.read().await.get(&user_name).is_some());
+}
+
+#[tokio::test]
+async fn test_server_info_advertises_active_group() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let zkp = auth_impl.active_group();
+
+    let info = auth_impl
+        .get_server_info(Request::new(ServerInfoRequest {}))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(serialization::deserialize_biguint(&info.p).unwrap(), zkp.p);
+    assert_eq!(serialization::deserialize_biguint(&info.q).unwrap(), zkp.q);
+    assert_eq!(
+        serialization::deserialize_biguint(&info.alpha).unwrap(),
+        zkp.alpha
+    );
+    assert_eq!(
+        serialization::deserialize_biguint(&info.beta).unwrap(),
+        zkp.beta
+    );
+    assert_eq!(info.group_id, group_id(&zkp));
+    assert_eq!(info.security_level_bits, 80);
+    assert_eq!(
+        info.min_protocol_version,
+        *SUPPORTED_PROTOCOL_VERSIONS.start()
+    );
+    assert_eq!(
+        info.max_protocol_version,
+        *SUPPORTED_PROTOCOL_VERSIONS.end()
+    );
+}
+
+#[tokio::test]
+async fn test_client_group_from_server_info_is_compatible() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let mut info = auth_impl
+        .get_server_info(Request::new(ServerInfoRequest {}))
+        .await
+        .unwrap()
+        .into_inner();
+
+    let zkp = ZKP::from_server_info(&info).unwrap();
+    assert!(zkp.is_compatible_with(&auth_impl.active_group()));
+    assert!(!zkp.is_compatible_with(&toy_group()));
+
+    // The client can register and log in with the adopted group
+    let password_x = ZKP::generate_random_number_below(&zkp.q).unwrap();
+    let (y1, y2) = zkp.compute_pair(&password_x).unwrap();
+    auth_impl
+        .register(Request::new(RegisterRequest {
+            user: "adopter".to_string(),
+            y1: serialization::serialize_biguint(&y1),
+            y2: serialization::serialize_biguint(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            rp_id: String::new(),
+            password_hash: String::new(),
+            salt: Vec::new(),
+        }))
+        .await
+        .unwrap();
+    assert!(authenticate(&auth_impl, "adopter", &password_x)
+        .await
+        .is_ok());
+
+    // Tampered parameters or a mismatched id are refused
+    info.group_id = group_id(&toy_group());
+    assert!(ZKP::from_server_info(&info).is_err());
+    info.group_id = group_id(&zkp);
+    info.alpha = serialization::serialize_biguint(&BigUint::from(5u32));
+    assert!(ZKP::from_server_info(&info).is_err());
+}
+
+#[tokio::test]
+async fn test_batched_verification_routes_per_request_results() {
+    let config = ServerConfig {
+        verify_batch_window_ms: Some(50),
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let q = auth_impl.active_group().q.clone();
+
+    let mut answers = Vec::new();
+    for index in 0..6 {
+        let (username, x) = register_deterministic_user(&auth_impl, index).await;
+        let (auth_id, s) = solved_challenge(&auth_impl, &username, &x).await;
+        // Every third answer is wrong
+        let valid = index % 3 != 1;
+        let s = if valid { s } else { (s + 1u32) % &q };
+        answers.push((auth_id, s, valid));
+    }
+
+    let results = futures::future::join_all(answers.iter().map(|(auth_id, s, _)| {
+        auth_impl.verify_authentication(Request::new(AuthenticationAnswerRequest {
+            auth_id: auth_id.clone(),
+            s: serialization::serialize_biguint(s),
+        }))
+    }))
+    .await;
+
+    for ((_, _, valid), result) in answers.iter().zip(results) {
+        match result {
+            Ok(answer) => {
+                assert!(valid);
+                assert!(!answer.into_inner().session_id.is_empty());
+            }
+            Err(status) => {
+                assert!(!valid);
+                assert_eq!(status.code(), Code::PermissionDenied);
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_values_off_the_subgroup_are_rejected() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let zkp = auth_impl.active_group();
+    let (username, _, y1, y2) = zkp.deterministic_user(0);
+
+    // -y1 is in range but has order 2q, not q
+    let status = auth_impl
+        .register(Request::new(RegisterRequest {
+            user: username.clone(),
+            y1: serialization::serialize_biguint(&(&zkp.p - &y1)),
+            y2: serialization::serialize_biguint(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            rp_id: String::new(),
+            password_hash: String::new(),
+            salt: Vec::new(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert!(status.message().contains("subgroup"));
+
+    register_deterministic_user(&auth_impl, 0).await;
+    let k = ZKP::generate_random_number_below(&(&zkp.q - 1u32)).unwrap() + 1u32;
+    let (r1, r2) = zkp.compute_pair(&k).unwrap();
+    let status = request_challenge(&auth_impl, &username, &(&zkp.p - &r1), &r2, 0)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert!(status.message().contains("subgroup"));
+    assert!(request_challenge(&auth_impl, &username, &r1, &r2, 0)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_verify_outcomes_match_with_and_without_batcher() {
+    for verify_batch_window_ms in [None, Some(0)] {
+        let config = ServerConfig {
+            verify_batch_window_ms,
+            ..Default::default()
+        };
+        let auth_impl = AuthImpl::with_config(&config).unwrap();
+        let q = auth_impl.active_group().q.clone();
+        let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+
+        let (auth_id, s) = solved_challenge(&auth_impl, &username, &x).await;
+        let wrong = (&s + 1u32) % &q;
+        let status = auth_impl
+            .verify_authentication(Request::new(AuthenticationAnswerRequest {
+                auth_id,
+                s: serialization::serialize_biguint(&wrong),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        {
+            let users = auth_impl.user_info.read().await;
+            assert_eq!(users[&username].failed_attempts, 1);
+            assert_eq!(users[&username].s.as_ref().unwrap().0, wrong);
+        }
+
+        auth_impl
+            .user_info
+            .write()
+            .await
+            .get_mut(&username)
+            .unwrap()
+            .last_challenge_timestamp = None;
+        let (auth_id, s) = solved_challenge(&auth_impl, &username, &x).await;
+        let status = auth_impl
+            .verify_authentication(Request::new(AuthenticationAnswerRequest {
+                auth_id: auth_id.clone(),
+                s: serialization::serialize_biguint(&q),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let answer = auth_impl
+            .verify_authentication(Request::new(AuthenticationAnswerRequest {
+                auth_id,
+                s: serialization::serialize_biguint(&s),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!answer.session_id.is_empty());
+        let users = auth_impl.user_info.read().await;
+        assert_eq!(users[&username].failed_attempts, 0);
+        assert_eq!(users[&username].s.as_ref().unwrap().0, s);
+    }
+}
+
+#[tokio::test]
+async fn test_auth_id_accepts_a_single_answer() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+    let (auth_id, s) = solved_challenge(&auth_impl, &username, &x).await;
+
+    let answer = || {
+        auth_impl.verify_authentication(Request::new(AuthenticationAnswerRequest {
+            auth_id: auth_id.clone(),
+            s: serialization::serialize_biguint(&s),
+        }))
+    };
+    answer().await.unwrap();
+    let status = answer().await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    // Answers racing on one auth_id: exactly one is verified
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let (auth_id, s) = solved_challenge(&auth_impl, &username, &x).await;
+    let answer = || {
+        auth_impl.verify_authentication(Request::new(AuthenticationAnswerRequest {
+            auth_id: auth_id.clone(),
+            s: serialization::serialize_biguint(&s),
+        }))
+    };
+    let (first, second) = tokio::join!(answer(), answer());
+    let statuses: Vec<Code> = [first, second]
+        .into_iter()
+        .filter_map(|result| result.err().map(|status| status.code()))
+        .collect();
+    assert_eq!(statuses, [Code::NotFound]);
+}
+
+#[tokio::test]
+async fn test_snapshot_reads_do_not_wait_on_writers() {
+    let config = ServerConfig {
+        read_snapshot_interval_ms: Some(1000),
+        enable_public_commitment_export: true,
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let (first, _) = register_deterministic_user(&auth_impl, 0).await;
+    auth_impl.refresh_read_snapshot().await;
+    let (second, _) = register_deterministic_user(&auth_impl, 1).await;
+
+    let public_commitment = |user: &str| {
+        auth_impl.get_public_commitment(Request::new(PublicCommitmentRequest {
+            user: user.to_string(),
+        }))
+    };
+
+    // A writer holding the authoritative map does not stall snapshot reads
+    let writer = auth_impl.user_info.write().await;
+    let read = tokio::time::timeout(Duration::from_secs(1), public_commitment(&first))
+        .await
+        .expect("snapshot read waited on the writer");
+    assert!(read.is_ok());
+    assert_eq!(auth_impl.metrics_snapshot().await.registered_users, 1);
+    drop(writer);
+
+    // Writes show up once the snapshot is rebuilt
+    let status = public_commitment(&second).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    auth_impl.refresh_read_snapshot().await;
+    assert!(public_commitment(&second).await.is_ok());
+    assert_eq!(auth_impl.metrics_snapshot().await.registered_users, 2);
+}
+
+#[tokio::test]
+async fn test_export_directory_lists_public_values_only() {
+    let config = ServerConfig {
+        enable_admin: true,
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let mut expected = Vec::new();
+    for index in 0..3 {
+        let (username, x) = register_deterministic_user(&auth_impl, index).await;
+        let (_, _, y1, y2) = auth_impl.active_group().deterministic_user(index);
+        expected.push((username, y1, y2));
+
+        // Leave challenge and session state behind for the export to skip
+        if index == 0 {
+            authenticate(&auth_impl, &expected[0].0, &x).await.unwrap();
+        }
+    }
+
+    let mut directory = auth_impl.export_directory().await.unwrap();
+    directory.sort();
+    assert_eq!(directory, expected);
+}
+
+#[tokio::test]
+async fn test_export_directory_requires_admin() {
+    let auth_impl = AuthImpl::new().unwrap();
+    register_deterministic_user(&auth_impl, 0).await;
+    assert!(auth_impl.export_directory().await.is_err());
+}
+
+#[tokio::test]
+async fn test_echo_values_reports_deserialized_value() {
+    let echo = |value: Vec<u8>| Request::new(EchoRequest { value });
+
+    let status = AuthImpl::new()
+        .unwrap()
+        .echo_values(echo(vec![1, 0]))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+
+    let auth_impl = AuthImpl::with_config(&ServerConfig {
+        enable_admin: true,
+        ..Default::default()
+    })
+    .unwrap();
+
+    // Leading zero bytes are not part of the value
+    let response = auth_impl
+        .echo_values(echo(vec![0x00, 0x01, 0x00, 0xff]))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.bit_length, 17);
+    assert_eq!(response.byte_length, 3);
+    assert_eq!(response.hex, "100ff");
+
+    let status = auth_impl.echo_values(echo(Vec::new())).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_proof_transcript_reverifies_and_detects_tampering() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let zkp = auth_impl.active_group();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+    assert!(auth_impl.user_info.read().await[&username]
+        .proof_transcript()
+        .is_none());
+
+    authenticate(&auth_impl, &username, &x).await.unwrap();
+    let transcript = auth_impl.user_info.read().await[&username]
+        .proof_transcript()
+        .unwrap();
+    assert!(transcript.challenged_at.is_some() && transcript.verified_at.is_some());
+    assert!(transcript.verify(&zkp).unwrap());
+
+    // Survives a round trip through the audit format
+    let restored = ProofTranscript::from_json(&transcript.to_json().unwrap()).unwrap();
+    assert_eq!(restored, transcript);
+    assert!(restored.verify(&zkp).unwrap());
+
+    // Changing any value breaks it
+    let tamperings: [fn(&mut ProofTranscript); 6] = [
+        |t| t.r1 += 1u32,
+        |t| t.r2 += 1u32,
+        |t| t.y1 += 1u32,
+        |t| t.y2 += 1u32,
+        |t| t.c += 1u32,
+        |t| t.s += 1u32,
+    ];
+    for tamper in tamperings {
+        let mut tampered = transcript.clone();
+        tamper(&mut tampered);
+        assert!(!tampered.verify(&zkp).unwrap_or(false));
+    }
+}
+
+#[tokio::test]
+async fn test_token_bucket_rejects_requests_beyond_refill_rate() {
+    let auth_impl = AuthImpl::with_config(&ServerConfig {
+        rate_limit_capacity: Some(3),
+        rate_limit_refill_per_sec: 2.0,
+        ..Default::default()
+    })
+    .unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+    let (other_user, other_x) = register_deterministic_user(&auth_impl, 1).await;
+    let challenge = |user: &str| {
+        let (r1, r2) = auth_impl.active_group().compute_pair(&x).unwrap();
+        auth_impl.create_authentication_challenge(Request::new(AuthenticationChallengeRequest {
+            user: user.to_string(),
+            r1: serialization::serialize_biguint(&r1),
+            r2: serialization::serialize_biguint(&r2),
+            protocol_version: PROTOCOL_VERSION,
+            pow_nonce: 0,
+            rp_id: String::new(),
+        }))
+    };
+
+    // A burst up to the capacity goes through, the rest is rejected
+    for _ in 0..3 {
+        challenge(&username).await.unwrap();
+    }
+    let status = challenge(&username).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+
+    // Buckets are per user
+    authenticate(&auth_impl, &other_user, &other_x)
+        .await
+        .unwrap();
+
+    // Answers draw from the same bucket
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    let (auth_id, s) = solved_challenge(&auth_impl, &username, &x).await;
+    let answer = || {
+        auth_impl.verify_authentication(Request::new(AuthenticationAnswerRequest {
+            auth_id: auth_id.clone(),
+            s: serialization::serialize_biguint(&s),
+        }))
+    };
+    let status = answer().await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+
+    // Once refilled, the same answer is accepted
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    answer().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_verify_waits_for_min_response_time() {
+    let min_response = Duration::from_millis(300);
+    let auth_impl = AuthImpl::with_config(&ServerConfig {
+        min_response_ms: Some(min_response.as_millis() as u64),
+        ..Default::default()
+    })
+    .unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+
+    let wrong_x = &x + 1u32;
+    for (secret, succeeds) in [(&x, true), (&wrong_x, false)] {
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let (auth_id, s) = solved_challenge(&auth_impl, &username, secret).await;
+        let started = Instant::now();
+        let result = auth_impl
+            .verify_authentication(Request::new(AuthenticationAnswerRequest {
+                auth_id,
+                s: serialization::serialize_biguint(&s),
+            }))
+            .await;
+        assert!(started.elapsed() >= min_response);
+        assert_eq!(result.is_ok(), succeeds);
+    }
+
+    // Early rejections are padded too
+    let started = Instant::now();
+    let status = auth_impl
+        .verify_authentication(Request::new(AuthenticationAnswerRequest {
+            auth_id: "unknown".to_string(),
+            s: vec![1],
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    assert!(started.elapsed() >= min_response);
+}
+
+#[tokio::test]
+async fn test_malformed_answer_rejected_before_lookup() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let q_len = auth_impl.active_group().q.to_bytes_be().len();
+
+    // An unknown auth_id would be NOT_FOUND; structure is checked first
+    for s in [Vec::new(), vec![1; q_len + 1]] {
+        let status = auth_impl
+            .verify_authentication(Request::new(AuthenticationAnswerRequest {
+                auth_id: "unknown".to_string(),
+                s,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    let status = auth_impl
+        .verify_authentication(Request::new(AuthenticationAnswerRequest {
+            auth_id: "unknown".to_string(),
+            s: vec![1; q_len],
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_user_debug_snapshot_redacts_secrets() {
+    let auth_impl = AuthImpl::with_config(&ServerConfig {
+        enable_admin: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+
+    let snapshot = auth_impl.user_debug_snapshot(&username).await.unwrap();
+    assert!(!snapshot.challenge.present);
+    assert_eq!(snapshot.session_count, 0);
+
+    // Mid-challenge: the challenge is reported, not revealed
+    solved_challenge(&auth_impl, &username, &x).await;
+    let (nonce, c) = {
+        let users = auth_impl.user_info.read().await;
+        let user = &users[&username];
+        (
+            user.challenge_nonce.clone().unwrap().0,
+            user.c.clone().unwrap().0,
+        )
+    };
+    let snapshot = auth_impl.user_debug_snapshot(&username).await.unwrap();
+    assert!(snapshot.commitment.present && snapshot.challenge_nonce.present);
+    assert_eq!(snapshot.challenge.bits, c.bits());
+    assert_eq!(snapshot.failed_attempts, 0);
+    assert!(!snapshot.locked);
+    let json = serde_json::to_string(&snapshot).unwrap();
+    for secret in [&nonce, &c] {
+        assert!(!json.contains(&secret.to_string()));
+        assert!(!json.contains(&secret.to_str_radix(16)));
+    }
+
+    // After logging in, the session is counted but its id is not shown
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let session_id = authenticate(&auth_impl, &username, &x)
+        .await
+        .unwrap()
+        .session_id;
+    let snapshot = auth_impl.user_debug_snapshot(&username).await.unwrap();
+    assert_eq!(snapshot.session_count, 1);
+    assert!(snapshot.answer.present);
+    assert!(snapshot.last_successful_auth.is_some());
+    assert!(!serde_json::to_string(&snapshot)
+        .unwrap()
+        .contains(&session_id));
+
+    assert!(AuthImpl::new()
+        .unwrap()
+        .user_debug_snapshot(&username)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_validate_session() {
+    let auth_impl = AuthImpl::with_config(&ServerConfig {
+        session_ttl_secs: 60,
+        ..Default::default()
+    })
+    .unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+    let session_id = authenticate(&auth_impl, &username, &x)
+        .await
+        .unwrap()
+        .session_id;
+    let validate = |session_id: &str| {
+        auth_impl.validate_session(Request::new(ValidateSessionRequest {
+            session_id: session_id.to_string(),
+        }))
+    };
+
+    // Fresh session
+    let response = validate(&session_id).await.unwrap().into_inner();
+    assert!(response.valid);
+    assert_eq!(response.user, username);
+    assert!(response.expires_at > chrono::Utc::now().timestamp());
+
+    // Unknown session id
+    let response = validate("no-such-session").await.unwrap().into_inner();
+    assert!(!response.valid);
+    assert!(response.user.is_empty());
+
+    // Expired session
+    auth_impl
+        .user_info
+        .write()
+        .await
+        .get_mut(&username)
+        .unwrap()
+        .last_successful_auth = Some(chrono::Utc::now() - chrono::Duration::seconds(61));
+    let response = validate(&session_id).await.unwrap().into_inner();
+    assert!(!response.valid);
+    assert!(!auth_impl
+        .session_to_user
+        .read()
+        .await
+        .contains_key(&session_hash(&session_id)));
+
+    // A new login replaces the user's previous session
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let new_session_id = authenticate(&auth_impl, &username, &x)
+        .await
+        .unwrap()
+        .session_id;
+    assert!(validate(&new_session_id).await.unwrap().into_inner().valid);
+    assert!(!validate(&session_id).await.unwrap().into_inner().valid);
+}
+
+#[tokio::test]
+async fn test_unsupported_protocol_version_is_rejected() {
+    let auth_impl = AuthImpl::new().unwrap();
+    let (_, _, y1, y2) = auth_impl.active_group().deterministic_user(0);
+
+    let status = auth_impl
+        .register(Request::new(RegisterRequest {
+            user: "future_client".to_string(),
+            y1: serialization::serialize_biguint(&y1),
+            y2: serialization::serialize_biguint(&y2),
+            protocol_version: 99,
+            rp_id: String::new(),
+            password_hash: String::new(),
+            salt: Vec::new(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    assert!(status.message().contains("supported versions: 1"));
+    assert!(auth_impl.user_info.read().await.is_empty());
+
+    let (username, _) = register_deterministic_user(&auth_impl, 0).await;
+    let status = auth_impl
+        .create_authentication_challenge(Request::new(AuthenticationChallengeRequest {
+            user: username,
+            r1: serialization::serialize_biguint(&y1),
+            r2: serialization::serialize_biguint(&y2),
+            protocol_version: 99,
+            pow_nonce: 0,
+            rp_id: String::new(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+}
+
+#[tokio::test]
+async fn test_ordered_store_keeps_concurrent_registration_order() {
+    let config = ServerConfig {
+        enable_admin: true,
+        preserve_registration_order: true,
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+
+    // join_all polls the registrations in order, so that is the order in
+    // which they take the user map lock
+    futures::future::join_all((0..8).map(|index| register_deterministic_user(&auth_impl, index)))
+        .await;
+
+    let expected: Vec<String> = (0..8)
+        .map(|index| auth_impl.active_group().deterministic_user(index).0)
+        .collect();
+    for _ in 0..3 {
+        let names: Vec<String> = auth_impl
+            .export_directory()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect();
+        assert_eq!(names, expected);
+    }
+}
+
+/// Register `user` with the public values of deterministic user 0
+async fn register_shared_public_values(auth_impl: &AuthImpl, user: &str) -> Result<(), Status> {
+    let (_, _, y1, y2) = auth_impl.active_group().deterministic_user(0);
+    auth_impl
+        .register(Request::new(RegisterRequest {
+            user: user.to_string(),
+            y1: serialization::serialize_biguint(&y1),
+            y2: serialization::serialize_biguint(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            rp_id: String::new(),
+            password_hash: String::new(),
+            salt: Vec::new(),
+        }))
+        .await
+        .map(|_| ())
+}
+
+#[tokio::test]
+async fn test_duplicate_public_values_rejected_under_another_name() {
+    let config = ServerConfig {
+        reject_duplicate_public_keys: true,
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    register_shared_public_values(&auth_impl, "alice")
+        .await
+        .unwrap();
+    let status = register_shared_public_values(&auth_impl, "mallory")
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::AlreadyExists);
+    assert!(!auth_impl.user_info.read().await.contains_key("mallory"));
+
+    // Without the flag both registrations succeed
+    let auth_impl = AuthImpl::new().unwrap();
+    register_shared_public_values(&auth_impl, "alice")
+        .await
+        .unwrap();
+    register_shared_public_values(&auth_impl, "mallory")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_registration_emits_one_structured_event() {
+    let logs = CapturedLogs::default();
+    let _guard = logs.set_default("info");
+
+    let auth_impl = AuthImpl::new().unwrap();
+    let (username, _) = register_deterministic_user(&auth_impl, 0).await;
+    let (_, _, y1, y2) = auth_impl.active_group().deterministic_user(0);
+    let duplicate = auth_impl
+        .register(Request::new(RegisterRequest {
+            user: username.clone(),
+            y1: serialization::serialize_biguint(&y1),
+            y2: serialization::serialize_biguint(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            rp_id: String::new(),
+            password_hash: String::new(),
+            salt: Vec::new(),
+        }))
+        .await;
+    assert_eq!(duplicate.unwrap_err().code(), Code::AlreadyExists);
+
+    let events: Vec<serde_json::Value> = logs
+        .contents()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|event| event["fields"]["event"] == "registration")
+        .collect();
+    assert_eq!(events.len(), 2);
+
+    let registered = &events[0]["fields"];
+    assert_eq!(events[0]["level"], "INFO");
+    assert_eq!(registered["user"], username.as_str());
+    assert_eq!(registered["outcome"], "registered");
+    assert!(registered["user_id"]
+        .as_str()
+        .is_some_and(|id| !id.is_empty()));
+    assert_eq!(registered["y1_bits"], y1.bits());
+    assert_eq!(registered["y2_bits"], y2.bits());
+    // Called in-process, so there is no peer address
+    assert_eq!(registered["peer"], "unknown");
+
+    let rejected = &events[1]["fields"];
+    assert_eq!(events[1]["level"], "WARN");
+    assert_eq!(rejected["user"], username.as_str());
+    assert_eq!(rejected["outcome"], "rejected");
+    assert_eq!(rejected["code"], "AlreadyExists");
+    assert_eq!(rejected["reason"], "User already registered");
+    assert_eq!(rejected["y1_bits"], y1.bits());
+}
+
+#[tokio::test]
+async fn test_challenge_answer_delay_is_traced_and_recorded() {
+    let logs = CapturedLogs::default();
+    let _guard = logs.set_default("info");
+
+    let auth_impl = AuthImpl::new().unwrap();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+    authenticate(&auth_impl, &username, &x).await.unwrap();
+
+    let logs = logs.contents();
+    let event: serde_json::Value = logs
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .find(|event| event["fields"]["challenge_answer_delay_ms"].is_u64())
+        .expect("no challenge_answer_delay_ms event");
+    assert_eq!(event["fields"]["user"], username.as_str());
+
+    let buckets = auth_impl.metrics_snapshot().await.challenge_answer_delay;
+    assert_eq!(buckets.iter().map(|bucket| bucket.count).sum::<u64>(), 1);
+    assert_eq!(buckets.last().unwrap().le_ms, None);
+}
+
+/// Request a challenge for `username` with the given commitment and proof of work
+async fn request_challenge(
+    auth_impl: &AuthImpl,
+    username: &str,
+    r1: &BigUint,
+    r2: &BigUint,
+    pow_nonce: u64,
+) -> Result<AuthenticationChallengeResponse, Status> {
+    // Skip the one-challenge-per-second rate limit between rounds
+    auth_impl
+        .user_info
+        .write()
+        .await
+        .get_mut(username)
+        .unwrap()
+        .last_challenge_timestamp = None;
+
+    auth_impl
+        .create_authentication_challenge(Request::new(AuthenticationChallengeRequest {
+            user: username.to_string(),
+            r1: serialization::serialize_biguint(r1),
+            r2: serialization::serialize_biguint(r2),
+            protocol_version: PROTOCOL_VERSION,
+            pow_nonce,
+            rp_id: String::new(),
+        }))
+        .await
+        .map(|response| response.into_inner())
+}
+
+#[tokio::test]
+async fn test_pow_difficulty_grows_with_failures_and_resets_on_success() {
+    let config = ServerConfig {
+        pow_base_difficulty: Some(4),
+        pow_difficulty_growth: 2,
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let zkp = auth_impl.active_group();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+
+    let mut required = Vec::new();
+    for failed_attempts in 0..=3u32 {
+        let k = ZKP::generate_random_number_below(&(&zkp.q - 1u32)).unwrap() + 1u32;
+        let (r1, r2) = zkp.compute_pair(&k).unwrap();
+        let difficulty = auth_impl.pow_difficulty(failed_attempts);
+        required.push(difficulty);
+
+        let mut nonce = 0;
+        if difficulty > 0 {
+            let seed = auth_impl.pow_seed(&username, failed_attempts);
+            let context = pow::context(&username, &r1, &r2, &seed);
+
+            let bad_nonce = (0..)
+                .find(|&nonce| !pow::is_valid(&context, nonce, difficulty))
+                .unwrap();
+            let status = request_challenge(&auth_impl, &username, &r1, &r2, bad_nonce)
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), Code::FailedPrecondition);
+            let metadata = status.metadata();
+            assert_eq!(
+                metadata.get(pow::DIFFICULTY_METADATA_KEY).unwrap(),
+                difficulty.to_string().as_str()
+            );
+            assert_eq!(
+                metadata.get(pow::SEED_METADATA_KEY).unwrap(),
+                hex::encode(seed).as_str()
+            );
+
+            nonce = pow::solve(&context, difficulty);
+        }
+
+        let challenge = request_challenge(&auth_impl, &username, &r1, &r2, nonce)
+            .await
+            .unwrap();
+        let c = serialization::deserialize_biguint(&challenge.c).unwrap();
+        let s = zkp.solve(&k, &c, &x).unwrap();
+
+        // Fail the first three rounds, then log in
+        let s = if failed_attempts < 3 {
+            (s + 1u32) % &zkp.q
+        } else {
+            s
+        };
+        let result = auth_impl
+            .verify_authentication(Request::new(AuthenticationAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: serialization::serialize_biguint(&s),
+            }))
+            .await;
+        assert_eq!(result.is_ok(), failed_attempts == 3);
+    }
+    assert_eq!(required, vec![0, 4, 6, 8]);
+
+    // Success resets the counter, so no proof of work is needed again
+    assert_eq!(
+        auth_impl.user_info.read().await[&username].failed_attempts,
+        0
+    );
+    assert_eq!(auth_impl.pow_difficulty(0), 0);
+    let k = ZKP::generate_random_number_below(&(&zkp.q - 1u32)).unwrap() + 1u32;
+    let (r1, r2) = zkp.compute_pair(&k).unwrap();
+    assert!(request_challenge(&auth_impl, &username, &r1, &r2, 0)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_argon2_pow_is_advertised_and_enforced() {
+    let algorithm = pow::Algorithm::Argon2 { memory_kib: 16 };
+    let config = ServerConfig {
+        pow_base_difficulty: Some(3),
+        pow_algorithm: algorithm,
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let zkp = auth_impl.active_group();
+    let (username, _) = register_deterministic_user(&auth_impl, 0).await;
+    auth_impl
+        .user_info
+        .write()
+        .await
+        .get_mut(&username)
+        .unwrap()
+        .failed_attempts = 1;
+
+    let k = ZKP::generate_random_number_below(&(&zkp.q - 1u32)).unwrap() + 1u32;
+    let (r1, r2) = zkp.compute_pair(&k).unwrap();
+    let context = pow::context(&username, &r1, &r2, &auth_impl.pow_seed(&username, 1));
+
+    let bad_nonce = (0..)
+        .find(|&nonce| !algorithm.is_valid(&context, nonce, 3))
+        .unwrap();
+    let status = request_challenge(&auth_impl, &username, &r1, &r2, bad_nonce)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    assert_eq!(
+        status.metadata().get(pow::ALGORITHM_METADATA_KEY).unwrap(),
+        "argon2:16"
+    );
+
+    let nonce = algorithm.solve(&context, 3).unwrap();
+    assert!(request_challenge(&auth_impl, &username, &r1, &r2, nonce)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_weak_challenges_are_redrawn() {
+    // Two-bit challenges land on the weak value 1 about half the time
+    for regenerate_weak_challenges in [false, true] {
+        let config = ServerConfig {
+            challenge_bits: Some(2),
+            regenerate_weak_challenges,
+            ..Default::default()
+        };
+        let auth_impl = AuthImpl::with_config(&config).unwrap();
+        let zkp = auth_impl.active_group();
+        let (username, _) = register_deterministic_user(&auth_impl, 0).await;
+
+        let mut weak = 0;
+        for _ in 0..40 {
+            let k = ZKP::generate_random_number_below(&(&zkp.q - 1u32)).unwrap() + 1u32;
+            let (r1, r2) = zkp.compute_pair(&k).unwrap();
+            let challenge = request_challenge(&auth_impl, &username, &r1, &r2, 0)
+                .await
+                .unwrap();
+            let c = serialization::deserialize_biguint(&challenge.c).unwrap();
+            if zkp.is_weak_challenge(&c) {
+                weak += 1;
+            }
+        }
+        assert_eq!(weak == 0, regenerate_weak_challenges);
+    }
+}
+
+#[tokio::test]
+async fn test_short_challenges_verify_end_to_end() {
+    let config = ServerConfig {
+        challenge_bits: Some(128),
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let zkp = auth_impl.active_group();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+
+    let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+    let (r1, r2) = zkp.compute_pair(&k).unwrap();
+    let challenge = request_challenge(&auth_impl, &username, &r1, &r2, 0)
+        .await
+        .unwrap();
+    let c = serialization::deserialize_biguint(&challenge.c).unwrap();
+    assert!(c.bits() <= 128);
+    assert!(c >= BigUint::from(1u32));
+    assert_eq!(auth_impl.soundness_error(&zkp), 2f64.powi(-128));
+    assert_eq!(
+        AuthImpl::new().unwrap().soundness_error(&zkp),
+        zkp.soundness_error()
+    );
+
+    let s = zkp.solve(&k, &c, &x).unwrap();
+    auth_impl
+        .verify_authentication(Request::new(AuthenticationAnswerRequest {
+            auth_id: challenge.auth_id,
+            s: serialization::serialize_biguint(&s),
+        }))
+        .await
+        .unwrap();
+
+    let config = ServerConfig {
+        challenge_bits: Some(0),
+        ..Default::default()
+    };
+    assert!(AuthImpl::with_config(&config).is_err());
+}
+
+#[tokio::test]
+async fn test_server_never_logs_secrets_in_the_clear() {
+    let logs = CapturedLogs::default();
+    let _guard = logs.set_default("zkp=trace,server_tests=trace");
+
+    let config = ServerConfig {
+        enable_failure_reports: true,
+        ..Default::default()
+    };
+    let auth_impl = AuthImpl::with_config(&config).unwrap();
+    let zkp = auth_impl.active_group();
+    let (username, x) = register_deterministic_user(&auth_impl, 0).await;
+
+    let mut secrets = vec![x.clone()];
+    for succeed in [false, true] {
+        let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+        let (r1, r2) = zkp.compute_pair(&k).unwrap();
+        let challenge = request_challenge(&auth_impl, &username, &r1, &r2, 0)
+            .await
+            .unwrap();
+        let c = serialization::deserialize_biguint(&challenge.c).unwrap();
+        let mut s = zkp.solve(&k, &c, &x).unwrap();
+        if !succeed {
+            s = (s + 1u32) % &zkp.q;
+        }
+
+        let result = auth_impl
+            .verify_authentication(Request::new(AuthenticationAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: serialization::serialize_biguint(&s),
+            }))
+            .await;
+        assert_eq!(result.is_ok(), succeed);
+        secrets.extend([k, c, s]);
+    }
+
+    let logs = logs.contents();
+    assert!(logs.contains("Failure report"));
+    for secret in &secrets {
+        assert!(!logs.contains(&secret.to_string()));
+        assert!(!logs.contains(&secret.to_str_radix(16)));
+    }
+}
+
+#[test]
+fn test_check_config_accepts_good_and_reports_bad_config() {
+    let summary = check_config(&ServerConfig::default()).unwrap();
+    assert!(summary.contains(&group_id(&ZKP::new(None).unwrap())));
+    assert!(summary.contains("127.0.0.1:50051"));
+
+    let config = ServerConfig {
+        host: "not a host".to_string(),
+        request_timeout_secs: 0,
+        challenge_bits: Some(0),
+        group_rotation_days: Some(7),
+        fallback_group: Some(GroupConfig {
+            p: "zz".to_string(),
+            q: "0b".to_string(),
+            alpha: "02".to_string(),
+            beta: "03".to_string(),
+        }),
+        ..Default::default()
+    };
+    let error = check_config(&config).unwrap_err().to_string();
+    for problem in [
+        "invalid listen address",
+        "request_timeout_secs",
+        "challenge_bits",
+        "no rotation_groups",
+        "fallback_group",
+    ] {
+        assert!(
+            error.contains(problem),
+            "{} missing from {}",
+            problem,
+            error
+        );
+    }
+}
+
+#[test]
+fn test_connection_settings_come_from_config() {
+    let config = ServerConfig::default();
+    assert_eq!(config.http2_keepalive_interval(), None);
+    assert_eq!(config.http2_keepalive_timeout(), None);
+    assert_eq!(config.max_connection_idle(), None);
+
+    let config = ServerConfig {
+        http2_keepalive_interval_secs: Some(30),
+        http2_keepalive_timeout_secs: Some(10),
+        max_connection_idle_secs: Some(300),
+        ..Default::default()
+    };
+    config.validate().unwrap();
+    assert_eq!(
+        config.http2_keepalive_interval(),
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(
+        config.http2_keepalive_timeout(),
+        Some(Duration::from_secs(10))
+    );
+    assert_eq!(config.max_connection_idle(), Some(Duration::from_secs(300)));
+
+    let config = ServerConfig {
+        http2_keepalive_timeout_secs: Some(10),
+        max_connection_idle_secs: Some(0),
+        ..Default::default()
+    };
+    let error = config.validate().unwrap_err().to_string();
+    assert!(error.contains("requires http2_keepalive_interval_secs"));
+    assert!(error.contains("max_connection_idle_secs must be positive"));
+}
+
+#[cfg(feature = "test-determinism")]
+#[tokio::test]
+async fn test_seeded_replay_reproduces_auth_id_and_challenge() {
+    async fn first_challenge(seed: u64) -> (String, Vec<u8>) {
+        let mut auth_impl = AuthImpl::new().unwrap();
+        auth_impl.seed_for_replay(seed);
+        let zkp = auth_impl.active_group();
+        let (username, _) = register_deterministic_user(&auth_impl, 0).await;
+        let (r1, r2) = zkp.compute_pair(&BigUint::from(7u32)).unwrap();
+        let challenge = request_challenge(&auth_impl, &username, &r1, &r2, 0)
+            .await
+            .unwrap();
+        (challenge.auth_id, challenge.c)
+    }
+
+    let first = first_challenge(42).await;
+    assert_eq!(first, first_challenge(42).await);
+    assert_ne!(first, first_challenge(43).await);
+}
@@ -0,0 +1,113 @@
+//! Smoke test for the wasm prover and verifier
+//!
+//! Runs natively with `cargo test --features wasm --test wasm`, and in a
+//! JS runtime with `wasm-pack test --node -- --no-default-features --features wasm`.
+
+use zkp::{serialization, wasm, ZKP};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test as test;
+
+fn hex(value: &num_bigint::BigUint) -> String {
+    serialization::serialize_biguint_hex(value)
+}
+
+#[test]
+fn prove_and_verify_with_hex_strings() {
+    let zkp = ZKP::new(None).unwrap();
+    let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
+    let (y1, y2) = zkp.compute_pair(&x).unwrap();
+    let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+    let c = ZKP::generate_random_number_below(&zkp.q).unwrap();
+
+    let (r1, r2) = wasm::compute_pair_hex(&hex(&k)).unwrap();
+    let s = wasm::solve_hex(&hex(&k), &hex(&c), &hex(&x)).unwrap();
+    assert!(wasm::verify_hex(&r1, &r2, &hex(&y1), &hex(&y2), &hex(&c), &s).unwrap());
+
+    // A different challenge must not verify
+    let other = hex(&((&c + 1u32) % &zkp.q));
+    assert!(!wasm::verify_hex(&r1, &r2, &hex(&y1), &hex(&y2), &other, &s).unwrap());
+
+    assert!(wasm::compute_pair_hex("not hex").is_err());
+}
+
+#[cfg(target_arch = "wasm32")]
+#[test]
+fn exports_surface_errors() {
+    let pair = wasm::compute_pair("02").unwrap();
+    assert_eq!(pair.len(), 2);
+    assert!(wasm::compute_pair("zz").is_err());
+}
+
+#[test]
+fn verifier_checks_proofs_in_a_known_group() {
+    let zkp = ZKP::new(None).unwrap();
+    let x = ZKP::generate_random_number_below(&zkp.q).unwrap();
+    let (y1, y2) = zkp.compute_pair(&x).unwrap();
+    let k = ZKP::generate_random_number_below(&zkp.q).unwrap();
+    let c = ZKP::generate_random_number_below(&zkp.q).unwrap();
+    let (r1, r2) = zkp.compute_pair(&k).unwrap();
+    let s = zkp.solve(&k, &c, &x).unwrap();
+
+    let verifier = wasm::Verifier::from_hex(
+        &hex(&zkp.p),
+        &hex(&zkp.q),
+        &hex(&zkp.alpha),
+        &hex(&zkp.beta),
+    )
+    .unwrap();
+    let (r1, r2, y1, y2, c, s) = (hex(&r1), hex(&r2), hex(&y1), hex(&y2), hex(&c), hex(&s));
+    assert!(verifier.verify_hex(&r1, &r2, &y1, &y2, &c, &s).unwrap());
+    assert!(!verifier.verify_hex(&r1, &r2, &y2, &y1, &c, &s).unwrap());
+    assert!(verifier.verify_hex(&r1, &r2, &y1, &y2, &c, "zz").is_err());
+
+    // The group is validated before any proof is checked
+    assert!(wasm::Verifier::from_hex(&hex(&zkp.p), &hex(&zkp.q), "02", "02").is_err());
+}
+
+#[cfg(target_arch = "wasm32")]
+#[test]
+fn verifier_export_returns_a_boolean() {
+    let verifier = wasm::Verifier::default_group().unwrap();
+    let (r1, r2) = wasm::compute_pair_hex("05").unwrap();
+    let (y1, y2) = wasm::compute_pair_hex("03").unwrap();
+    let s = wasm::solve_hex("05", "01", "03").unwrap();
+    assert!(verifier.verify(&r1, &r2, &y1, &y2, "01", &s).unwrap());
+    assert!(!verifier.verify(&r1, &r2, &y1, &y2, "02", &s).unwrap());
+}
+
+/// The verifier must keep building for the browser without tokio or tonic
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn wasm_target_builds_without_grpc() {
+    use std::process::Command;
+
+    let installed = Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains("wasm32-unknown-unknown"))
+        .unwrap_or(false);
+    if !installed {
+        eprintln!("wasm32-unknown-unknown is not installed; skipping");
+        return;
+    }
+
+    let status = Command::new(env!("CARGO"))
+        .args([
+            "build",
+            "--lib",
+            "--target",
+            "wasm32-unknown-unknown",
+            "--no-default-features",
+            "--features",
+            "wasm",
+        ])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env(
+            "CARGO_TARGET_DIR",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/target/wasm-build-test"),
+        )
+        .status()
+        .unwrap();
+    assert!(status.success());
+}